@@ -1 +1,6 @@
+pub mod dot;
+pub mod infix;
+pub mod json;
 pub mod nodes;
+pub mod rpn;
+pub mod tree;
@@ -0,0 +1,111 @@
+use crate::ast::nodes::AstNode;
+use crate::tokenizer::tokens::IToken;
+
+/// Renders the AST rooted at node as a Graphviz DOT digraph.
+/// Every node is labelled with its lexeme and the edges to the operands
+/// of operators and functions are labelled with the operand position.
+pub fn to_dot(node: &dyn AstNode) -> String {
+    let mut graph = String::from("digraph ast {\n");
+    let mut next_id = 0;
+
+    write_node(node, &mut graph, &mut next_id);
+
+    graph.push_str("}\n");
+    graph
+}
+
+/// Writes the statement for node and its subtree into graph and returns
+/// the id assigned to node.
+fn write_node(node: &dyn AstNode, graph: &mut String, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let shape = if node.children().is_empty() {
+        "ellipse"
+    } else {
+        "box"
+    };
+
+    graph.push_str(&format!(
+        "    n{} [label=\"{}\", shape={}];\n",
+        id,
+        escape(&node.token().to_string()),
+        shape
+    ));
+
+    for (position, child) in node.children().iter().enumerate() {
+        let child_id = write_node(child.as_ref(), graph, next_id);
+
+        graph.push_str(&format!(
+            "    n{} -> n{} [label=\"{}\"];\n",
+            id,
+            child_id,
+            edge_label(node.token_type(), position)
+        ));
+    }
+
+    id
+}
+
+/// Operands of binary operators are labelled lhs and rhs while
+/// function arguments and the equations of a system are labelled by
+/// their position. The operand of the unary minus is labelled operand.
+fn edge_label(token_type: IToken, position: usize) -> String {
+    match token_type {
+        IToken::Fun(_) => format!("arg{}", position + 1),
+        IToken::Semicolon => format!("eq{}", position + 1),
+        IToken::Neg => String::from("operand"),
+        _ if position == 0 => String::from("lhs"),
+        _ => String::from("rhs"),
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    #[test]
+    fn to_dot_renders_leaf() {
+        let mut binding = Tokenizer::build("2");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build(tokens);
+
+        assert_eq!(
+            "digraph ast {\n    n0 [label=\"2\", shape=ellipse];\n}\n",
+            to_dot(parser.ast())
+        );
+    }
+
+    #[test]
+    fn to_dot_renders_operators_with_labelled_children() {
+        let mut binding = Tokenizer::build("1 + 2 * max(3, pi)");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build(tokens);
+
+        assert_eq!(
+            "digraph ast {
+    n0 [label=\"+\", shape=box];
+    n1 [label=\"1\", shape=ellipse];
+    n0 -> n1 [label=\"lhs\"];
+    n2 [label=\"*\", shape=box];
+    n3 [label=\"2\", shape=ellipse];
+    n2 -> n3 [label=\"lhs\"];
+    n4 [label=\"max\", shape=box];
+    n5 [label=\"3\", shape=ellipse];
+    n4 -> n5 [label=\"arg1\"];
+    n6 [label=\"pi\", shape=ellipse];
+    n4 -> n6 [label=\"arg2\"];
+    n2 -> n4 [label=\"rhs\"];
+    n0 -> n2 [label=\"rhs\"];
+}
+",
+            to_dot(parser.ast())
+        );
+    }
+}
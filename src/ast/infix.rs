@@ -0,0 +1,105 @@
+use crate::ast::nodes::AstNode;
+use crate::tokenizer::tokens::IToken;
+
+/// Renders the AST rooted at node as an infix expression with only the
+/// parentheses needed to parse it back into the same tree. Additions,
+/// subtractions, conversions and equations are written with spaces
+/// around the operator, and the equations of a system separated by
+/// semicolons.
+pub fn to_infix(node: &dyn AstNode) -> String {
+    let token = node.token();
+    let children = node.children();
+
+    match token.token_type {
+        IToken::Fun(function) => format!(
+            "{}({})",
+            function.name(),
+            children
+                .iter()
+                .map(|child| to_infix(child.as_ref()))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        IToken::Neg => match binding(children[0].as_ref()) {
+            1..=3 => format!("-({})", to_infix(children[0].as_ref())),
+            _ => format!("-{}", to_infix(children[0].as_ref())),
+        },
+        IToken::Semicolon => children
+            .iter()
+            .map(|child| to_infix(child.as_ref()))
+            .collect::<Vec<String>>()
+            .join("; "),
+        IToken::Add | IToken::Sub | IToken::To | IToken::Eq => {
+            format!("{} {} {}", operand(node, 0), token, operand(node, 1))
+        }
+        IToken::Mul | IToken::Div | IToken::Pow => {
+            format!("{}{}{}", operand(node, 0), token, operand(node, 1))
+        }
+        _ => token.to_string(),
+    }
+}
+
+/// Renders the operand at position of the binary operator node, in
+/// parentheses when it binds looser than the operator, binds as tightly
+/// on the side the operator does not associate to, or is a negative
+/// right operand
+fn operand(node: &dyn AstNode, position: usize) -> String {
+    let child = node.children()[position].as_ref();
+    let (outer, inner) = (binding(node), binding(child));
+    let right_associative = node.token_type() == IToken::Pow;
+    let parenthesized = inner < outer
+        || (inner == outer && (position == 1) != right_associative)
+        || (position == 1 && is_negative(child));
+
+    match parenthesized {
+        true => format!("({})", to_infix(child)),
+        false => to_infix(child),
+    }
+}
+
+/// How tightly the node binds its operands, the precedence of its
+/// operator. Leaves and functions bind tightest, negative numbers like
+/// the unary minus.
+fn binding(node: &dyn AstNode) -> u8 {
+    match node.token_type() {
+        IToken::Semicolon | IToken::Eq => 0,
+        IToken::To => 1,
+        IToken::Add | IToken::Sub => 2,
+        IToken::Mul | IToken::Div | IToken::Neg => 3,
+        IToken::Num if is_negative(node) => 3,
+        IToken::Pow => 4,
+        _ => 5,
+    }
+}
+
+fn is_negative(node: &dyn AstNode) -> bool {
+    match node.token_type() {
+        IToken::Neg => true,
+        IToken::Num => node.token().literal.is_some_and(|value| value < 0.0),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    fn round_trip(expr: &str) -> String {
+        let mut binding = Tokenizer::build(expr);
+        let tokens = binding.tokens().as_mut().unwrap();
+
+        to_infix(ShuntingYardParser::build(tokens).ast())
+    }
+
+    #[test]
+    fn to_infix_keeps_only_needed_parentheses() {
+        assert_eq!("2*x + 3", round_trip("((2 * x) + 3)"));
+        assert_eq!("(a - b)*c - (d - e)", round_trip("(a - b) * c - (d - e)"));
+        assert_eq!("a^b^c + (a^b)^c", round_trip("a ^ b ^ c + (a ^ b) ^ c"));
+        assert_eq!("-(x + 1)/max(x, 2)", round_trip("-(x + 1) / max(x, 2)"));
+        assert_eq!("x*(-y)", round_trip("x * -y"));
+        assert_eq!("2*x = a + b; y = 1", round_trip("2x=a+b;y=1"));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::ast::nodes::AstNode;
+use crate::tokenizer::tokens::{IAssociativity, Token};
+
+/// Renders the AST rooted at node as a JSON object. Every node is
+/// written as its token with operands in the children array.
+pub fn to_json(node: &dyn AstNode) -> String {
+    let children: Vec<String> = node
+        .children()
+        .iter()
+        .map(|child| to_json(child.as_ref()))
+        .collect();
+    let token = token_to_json(&node.token());
+
+    if children.is_empty() {
+        return token;
+    }
+
+    format!(
+        "{},\"children\":[{}]}}",
+        &token[..token.len() - 1],
+        children.join(",")
+    )
+}
+
+/// Renders the token as a JSON object with its kind and lexeme.
+/// Numbers and constants carry their value while operators carry
+/// their precedence and associativity.
+pub fn token_to_json(token: &Token) -> String {
+    let mut fields = vec![
+        format!("\"kind\":{}", string(token.token_type.kind())),
+        format!("\"text\":{}", string(&token.to_string())),
+    ];
+
+    if let Some(literal) = token.literal {
+        fields.push(format!("\"value\":{}", number(literal)));
+    }
+
+    if let Some(precedence) = token.precedence {
+        fields.push(format!("\"precedence\":{}", precedence));
+    }
+
+    if let Some(associativity) = token.associativity {
+        let associativity = match associativity {
+            IAssociativity::Left => "left",
+            IAssociativity::Right => "right",
+        };
+
+        fields.push(format!("\"associativity\":{}", string(associativity)));
+    }
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Writes value as a JSON string literal
+pub fn string(value: &str) -> String {
+    let mut literal = String::from("\"");
+
+    for char in value.chars() {
+        match char {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            char if char.is_control() => literal.push_str(&format!("\\u{:04x}", char as u32)),
+            char => literal.push(char),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
+
+/// Writes value as a JSON number. JSON has no representation for
+/// infinities and NaN so they are written as null.
+pub fn number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        String::from("null")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    #[test]
+    fn to_json_nests_children() {
+        let mut binding = Tokenizer::build("x ^ 2");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build(tokens);
+
+        assert_eq!(
+            "{\"kind\":\"operator\",\"text\":\"^\",\"precedence\":4,\"associativity\":\"right\",\"children\":[{\"kind\":\"variable\",\"text\":\"x\"},{\"kind\":\"number\",\"text\":\"2\",\"value\":2}]}",
+            to_json(parser.ast())
+        );
+    }
+
+    #[test]
+    fn string_and_number_escape_values() {
+        assert_eq!("\"a\\\"b\\\\\\n\"", string("a\"b\\\n"));
+        assert_eq!("0.5", number(0.5));
+        assert_eq!("null", number(f64::INFINITY));
+    }
+}
@@ -1,32 +1,98 @@
+use crate::numeric::{evaluate_numeric, Numeric};
 use crate::tokenizer::tokens::{IToken, Token};
+use std::collections::HashMap;
+
+/// Values bound to the variables of an expression by name
+pub type Variables<T = f64> = HashMap<String, T>;
+
+/// Creates the node for token with children as its operands. Leaves
+/// take no children.
+pub fn new_node(token: Token, children: Vec<Box<dyn AstNode>>) -> Box<dyn AstNode> {
+    match token.token_type {
+        IToken::Pow => Box::new(Pow { token, children }),
+        IToken::Mul => Box::new(Mul { token, children }),
+        IToken::Div => Box::new(Div { token, children }),
+        IToken::Add => Box::new(Add { token, children }),
+        IToken::Sub => Box::new(Sub { token, children }),
+        IToken::Neg => Box::new(Neg { token, children }),
+        IToken::Fun(_) => Box::new(Fun { token, children }),
+        IToken::To => Box::new(To { token, children }),
+        IToken::Eq => Box::new(Eq { token, children }),
+        IToken::Semicolon => Box::new(System { token, children }),
+        IToken::Num => Box::new(Num { token }),
+        IToken::Const(_) => Box::new(Const { token }),
+        IToken::Var => Box::new(Var { token }),
+        IToken::Imag => Box::new(Imag { token }),
+        IToken::Angle(_) => Box::new(Angle { token }),
+        IToken::Quantity => Box::new(Quantity { token }),
+        IToken::Currency => Box::new(Currency { token }),
+        IToken::Unit => Box::new(Unit { token }),
+        IToken::Str => Box::new(Str { token }),
+        IToken::LPar | IToken::RPar | IToken::Comma => Box::new(LPar { token }),
+    }
+}
+
+/// Copies the tree rooted at node
+pub fn copy_node(node: &dyn AstNode) -> Box<dyn AstNode> {
+    new_node(
+        node.token(),
+        node.children()
+            .iter()
+            .map(|child| copy_node(child.as_ref()))
+            .collect(),
+    )
+}
 
 pub trait AstNode {
     fn evaluate(&self) -> f64 {
+        self.evaluate_with(&Variables::new())
+    }
+    /// Evaluates the node looking up the values of variables in variables.
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
         panic!("evaluation not implemented for this type.");
     }
     fn precedence(&self) -> Option<u8>;
     fn token_type(&self) -> IToken;
     fn token(&self) -> Token;
+    /// The operands of the node in the order they appear in the expression.
+    /// Leaves have no children.
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &[]
+    }
+    /// Sets the operands of the node once they have been parsed.
+    fn attach(&mut self, _children: Vec<Box<dyn AstNode>>) {
+        panic!("children not supported for this type.");
+    }
 }
 
 pub struct Pow {
     pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
 }
 
 pub struct Mul {
     pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
 }
 
 pub struct Div {
     pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
 }
 
 pub struct Add {
     pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
 }
 
 pub struct Sub {
     pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
+}
+
+pub struct Neg {
+    pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
 }
 
 pub struct Num {
@@ -37,8 +103,61 @@ pub struct Const {
     pub token: Token,
 }
 
+pub struct Var {
+    pub token: Token,
+}
+
+pub struct Imag {
+    pub token: Token,
+}
+
+/// A number with an angle unit suffix like 90deg
+pub struct Angle {
+    pub token: Token,
+}
+
+/// A number with a unit like 5 km
+pub struct Quantity {
+    pub token: Token,
+}
+
+/// A number with a currency code like 100 USD
+pub struct Currency {
+    pub token: Token,
+}
+
+/// A string in double quotes like "2026-10-18"
+pub struct Str {
+    pub token: Token,
+}
+
+/// A unit on its own, the target of a conversion
+pub struct Unit {
+    pub token: Token,
+}
+
+/// A conversion of its first child to the unit of its second child
+pub struct To {
+    pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
+}
+
+/// An equation of its two children
+pub struct Eq {
+    pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
+}
+
+/// A system of equations, one child per equation, with the token of the
+/// first semicolon
+pub struct System {
+    pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
+}
+
 pub struct Fun {
     pub token: Token,
+    pub children: Vec<Box<dyn AstNode>>,
 }
 
 /// mere tombstone for handling parenthesis
@@ -47,7 +166,7 @@ pub struct LPar {
 }
 
 impl AstNode for Const {
-    fn evaluate(&self) -> f64 {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
         self.token.literal.unwrap()
     }
     fn precedence(&self) -> Option<u8> {
@@ -57,12 +176,12 @@ impl AstNode for Const {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
     }
 }
 
 impl AstNode for Num {
-    fn evaluate(&self) -> f64 {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
         self.token.literal.unwrap()
     }
     fn precedence(&self) -> Option<u8> {
@@ -72,11 +191,33 @@ impl AstNode for Num {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+}
+
+impl AstNode for Var {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        let name = self.token.lexeme.as_ref().unwrap();
+
+        *variables
+            .get(name)
+            .unwrap_or_else(|| panic!("Unbound variable: {}", name))
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
     }
 }
 
 impl AstNode for Sub {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        self.children[0].evaluate_with(variables) - self.children[1].evaluate_with(variables)
+    }
     fn precedence(&self) -> Option<u8> {
         self.token.precedence
     }
@@ -84,11 +225,20 @@ impl AstNode for Sub {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
     }
 }
 
 impl AstNode for Add {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        self.children[0].evaluate_with(variables) + self.children[1].evaluate_with(variables)
+    }
     fn precedence(&self) -> Option<u8> {
         self.token.precedence
     }
@@ -96,11 +246,20 @@ impl AstNode for Add {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
     }
 }
 
 impl AstNode for Div {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        self.children[0].evaluate_with(variables) / self.children[1].evaluate_with(variables)
+    }
     fn precedence(&self) -> Option<u8> {
         self.token.precedence
     }
@@ -108,11 +267,20 @@ impl AstNode for Div {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
     }
 }
 
 impl AstNode for Mul {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        self.children[0].evaluate_with(variables) * self.children[1].evaluate_with(variables)
+    }
     fn precedence(&self) -> Option<u8> {
         self.token.precedence
     }
@@ -120,11 +288,22 @@ impl AstNode for Mul {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
     }
 }
 
 impl AstNode for Pow {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        self.children[0]
+            .evaluate_with(variables)
+            .powf(self.children[1].evaluate_with(variables))
+    }
     fn precedence(&self) -> Option<u8> {
         self.token.precedence
     }
@@ -132,11 +311,116 @@ impl AstNode for Pow {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
     }
 }
 
 impl AstNode for Fun {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        if matches!(self.token.token_type, IToken::Fun(function) if function.binds_variable()) {
+            return evaluate_numeric(self, variables).unwrap_or_else(|error| panic!("{}", error));
+        }
+
+        let arguments: Vec<f64> = self
+            .children
+            .iter()
+            .map(|child| child.evaluate_with(variables))
+            .collect();
+
+        match self.token.token_type {
+            IToken::Fun(function) => {
+                f64::function(function, &arguments).unwrap_or_else(|error| panic!("{}", error))
+            }
+            _ => panic!("Unidentified function {:#?}", self.token),
+        }
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
+    }
+}
+
+impl AstNode for Neg {
+    fn evaluate_with(&self, variables: &Variables) -> f64 {
+        -self.children[0].evaluate_with(variables)
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
+    }
+}
+
+impl AstNode for Imag {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Imaginary numbers require complex arithmetic");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+}
+
+impl AstNode for Angle {
+    /// Evaluates to the angle in radians
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        match self.token.token_type {
+            IToken::Angle(unit) => self
+                .token
+                .literal
+                .unwrap()
+                .angle_to_radians(unit)
+                .unwrap_or_else(|error| panic!("{}", error)),
+            _ => panic!("Unidentified angle {:#?}", self.token),
+        }
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+}
+
+impl AstNode for Quantity {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Quantities with units require unit evaluation");
+    }
     fn precedence(&self) -> Option<u8> {
         self.token.precedence
     }
@@ -144,7 +428,115 @@ impl AstNode for Fun {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
+    }
+}
+
+impl AstNode for Currency {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Amounts of money require currency evaluation");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+}
+
+impl AstNode for Str {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Dates and durations require date evaluation");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+}
+
+impl AstNode for Unit {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Units require unit evaluation");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+}
+
+impl AstNode for To {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Unit conversions require unit evaluation");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
+    }
+}
+
+impl AstNode for Eq {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Equations cannot be evaluated, only solved");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
+    }
+}
+
+impl AstNode for System {
+    fn evaluate_with(&self, _variables: &Variables) -> f64 {
+        panic!("Equations cannot be evaluated, only solved");
+    }
+    fn precedence(&self) -> Option<u8> {
+        self.token.precedence
+    }
+    fn token_type(&self) -> IToken {
+        self.token.token_type
+    }
+    fn token(&self) -> Token {
+        self.token.clone()
+    }
+    fn children(&self) -> &[Box<dyn AstNode>] {
+        &self.children
+    }
+    fn attach(&mut self, children: Vec<Box<dyn AstNode>>) {
+        self.children = children;
     }
 }
 
@@ -156,6 +548,6 @@ impl AstNode for LPar {
         self.token.token_type
     }
     fn token(&self) -> Token {
-        self.token
+        self.token.clone()
     }
 }
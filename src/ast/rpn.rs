@@ -0,0 +1,45 @@
+use crate::ast::nodes::AstNode;
+
+/// Renders the AST rooted at node in Reverse Polish notation with
+/// the lexemes separated by single spaces.
+pub fn to_rpn(node: &dyn AstNode) -> String {
+    let mut lexemes = vec![];
+
+    collect_lexemes(node, &mut lexemes);
+
+    lexemes.join(" ")
+}
+
+/// Walks the tree in post-order pushing the lexeme of every node.
+fn collect_lexemes(node: &dyn AstNode, lexemes: &mut Vec<String>) {
+    for child in node.children() {
+        collect_lexemes(child.as_ref(), lexemes);
+    }
+
+    lexemes.push(node.token().to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    #[test]
+    fn to_rpn_renders_postfix_notation() {
+        let mut binding = Tokenizer::build("3 + 4 * 2 / ( 1 - 5 ) ^ 2 ^ 3");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build(tokens);
+
+        assert_eq!("3 4 2 * 1 5 - 2 3 ^ ^ / +", to_rpn(parser.ast()));
+    }
+
+    #[test]
+    fn to_rpn_places_functions_after_arguments() {
+        let mut binding = Tokenizer::build("max(1, pi) - 2");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build(tokens);
+
+        assert_eq!("1 pi max 2 -", to_rpn(parser.ast()));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::ast::nodes::AstNode;
+
+/// Renders the AST rooted at node as an indented text tree with
+/// one node per line and its operands nested below it.
+pub fn to_tree(node: &dyn AstNode) -> String {
+    let mut tree = format!("{}\n", node.token());
+
+    write_children(node, "", &mut tree);
+
+    tree
+}
+
+/// Writes the children of node into tree, prefixing every line
+/// with the guides of the ancestors of the children.
+fn write_children(node: &dyn AstNode, prefix: &str, tree: &mut String) {
+    let children = node.children();
+
+    for (position, child) in children.iter().enumerate() {
+        let is_last = position + 1 == children.len();
+        let (branch, guide) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        tree.push_str(&format!("{}{}{}\n", prefix, branch, child.token()));
+
+        write_children(child.as_ref(), &format!("{}{}", prefix, guide), tree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    #[test]
+    fn to_tree_nests_operands_below_operators() {
+        let mut binding = Tokenizer::build("(x - 1) * max(2, pi)");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build(tokens);
+
+        assert_eq!(
+            "*
+├── -
+│   ├── x
+│   └── 1
+└── max
+    ├── 2
+    └── pi
+",
+            to_tree(parser.ast())
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::ast::nodes::Variables;
+use crate::parse_expression_shunting_yard;
+use std::io::{BufRead, Error};
+
+/// Marks the start of a comment which runs to the end of the line
+pub const COMMENT_MARKER: char = '#';
+
+/// The outcome of evaluating the expression on a single line
+#[derive(Debug)]
+pub struct LineResult {
+    /// The line number in the input, starting from 1
+    pub line_number: usize,
+    /// The expression with comments and surrounding whitespace removed
+    pub expr: String,
+    pub value: Result<f64, Error>,
+}
+
+/// Evaluates the expression on every line of reader. Blank lines and
+/// comments are skipped and failing lines are reported in their
+/// LineResult so the remaining lines are still evaluated.
+/// Errors reading from reader end the iteration.
+pub fn evaluate_lines<R: BufRead>(reader: R) -> impl Iterator<Item = Result<LineResult, Error>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| match line {
+            Ok(line) => evaluate_line(index + 1, &line).map(Ok),
+            Err(error) => Some(Err(error)),
+        })
+}
+
+/// Evaluates a single line returning None when there is no expression on it
+pub fn evaluate_line(line_number: usize, line: &str) -> Option<LineResult> {
+    let expr = line.split(COMMENT_MARKER).next().unwrap_or_default().trim();
+
+    if expr.is_empty() {
+        return None;
+    }
+
+    Some(LineResult {
+        line_number,
+        expr: expr.to_string(),
+        value: parse_expression_shunting_yard(expr)
+            .and_then(|parser| parser.try_evaluate_with(&Variables::new())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, ErrorKind};
+
+    #[test]
+    fn evaluate_lines_skips_blank_lines_and_comments() {
+        let input = Cursor::new("# totals\n2+2\n\n   \n3 * 3 # nine\n");
+        let results: Vec<LineResult> = evaluate_lines(input).map(Result::unwrap).collect();
+
+        assert_eq!(2, results.len());
+        assert_eq!(2, results[0].line_number);
+        assert_eq!(4.0, *results[0].value.as_ref().unwrap());
+        assert_eq!(5, results[1].line_number);
+        assert_eq!("3 * 3", results[1].expr);
+        assert_eq!(9.0, *results[1].value.as_ref().unwrap());
+    }
+
+    #[test]
+    fn evaluate_lines_continues_after_failing_lines() {
+        let input = Cursor::new("1 +\nx\n1 - 1\n");
+        let results: Vec<LineResult> = evaluate_lines(input).map(Result::unwrap).collect();
+
+        assert_eq!(3, results.len());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            results[0].value.as_ref().unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::NotFound,
+            results[1].value.as_ref().unwrap_err().kind()
+        );
+        assert_eq!(0.0, *results[2].value.as_ref().unwrap());
+    }
+
+    #[test]
+    fn evaluate_lines_continues_after_malformed_numbers() {
+        let input = Cursor::new(
+            "1..2
+2+2
+",
+        );
+        let results: Vec<LineResult> = evaluate_lines(input).map(Result::unwrap).collect();
+
+        assert_eq!(2, results.len());
+        assert_eq!(
+            ErrorKind::InvalidData,
+            results[0].value.as_ref().unwrap_err().kind()
+        );
+        assert_eq!(4.0, *results[1].value.as_ref().unwrap());
+    }
+}
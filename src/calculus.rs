@@ -0,0 +1,237 @@
+use crate::ast::nodes::{copy_node, new_node, AstNode, Variables};
+use crate::numeric::evaluate_numeric;
+use crate::simplify::simplify;
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IFunctions, IToken, Token};
+use std::io::{Error, ErrorKind};
+
+/// Differentiates the tree rooted at node with respect to variable and
+/// simplifies the derivative. Sums, products, quotients, powers with an
+/// exponent not depending on variable and powers of positive numbers
+/// follow their rules, and every function of one argument follows the
+/// chain rule. Trigonometric functions take radians. Errors carry the
+/// span of the token they were raised at.
+pub fn differentiate(node: &dyn AstNode, variable: &str) -> Result<Box<dyn AstNode>, Error> {
+    derivative(node, variable).map(|derivative| simplify(derivative.as_ref()))
+}
+
+/// Whether the tree rooted at node uses variable
+pub fn depends_on(node: &dyn AstNode, variable: &str) -> bool {
+    (node.token_type() == IToken::Var && node.token().lexeme.as_deref() == Some(variable))
+        || node
+            .children()
+            .iter()
+            .any(|child| depends_on(child.as_ref(), variable))
+}
+
+fn derivative(node: &dyn AstNode, variable: &str) -> Result<Box<dyn AstNode>, Error> {
+    let token = node.token();
+    let children = node.children();
+    let operand = |position: usize| copy_node(children[position].as_ref());
+    let differential = |position: usize| derivative(children[position].as_ref(), variable);
+
+    let result = match token.token_type {
+        IToken::Num | IToken::Const(_) => Ok(number(0.0)),
+        IToken::Var if depends_on(node, variable) => Ok(number(1.0)),
+        IToken::Var => Ok(number(0.0)),
+        IToken::Add | IToken::Sub => {
+            Ok(binary(token.token_type, differential(0)?, differential(1)?))
+        }
+        IToken::Neg => Ok(unary(IToken::Neg, differential(0)?)),
+        IToken::Mul => Ok(binary(
+            IToken::Add,
+            binary(IToken::Mul, differential(0)?, operand(1)),
+            binary(IToken::Mul, operand(0), differential(1)?),
+        )),
+        IToken::Div => Ok(binary(
+            IToken::Div,
+            binary(
+                IToken::Sub,
+                binary(IToken::Mul, differential(0)?, operand(1)),
+                binary(IToken::Mul, operand(0), differential(1)?),
+            ),
+            binary(IToken::Pow, operand(1), number(2.0)),
+        )),
+        IToken::Pow if !depends_on(children[1].as_ref(), variable) => Ok(binary(
+            IToken::Mul,
+            binary(
+                IToken::Mul,
+                operand(1),
+                binary(
+                    IToken::Pow,
+                    operand(0),
+                    binary(IToken::Sub, operand(1), number(1.0)),
+                ),
+            ),
+            differential(0)?,
+        )),
+        IToken::Pow if depends_on(children[0].as_ref(), variable) => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Cannot differentiate a power with {} in its base and exponent",
+                variable
+            ),
+        )),
+        // a^u is a^u * ln(a) * u', with ln(a) folded into a number
+        IToken::Pow => match evaluate_numeric::<f64>(children[0].as_ref(), &Variables::new()) {
+            Ok(base) if base > 0.0 => Ok(binary(
+                IToken::Mul,
+                binary(IToken::Mul, copy_node(node), number(base.ln())),
+                differential(1)?,
+            )),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "Cannot differentiate a power with {} in its exponent unless its base is a positive number",
+                    variable
+                ),
+            )),
+        },
+        IToken::Fun(function) if function.arity() == 1 => {
+            chain(function, operand(0), differential(0)?)
+        }
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} cannot be differentiated", token),
+        )),
+    };
+
+    result.map_err(|error| with_span(error, token.span))
+}
+
+/// The derivative of function applied to argument, given the derivative
+/// of argument
+fn chain(
+    function: IFunctions,
+    argument: Box<dyn AstNode>,
+    differential: Box<dyn AstNode>,
+) -> Result<Box<dyn AstNode>, Error> {
+    let square = |argument: Box<dyn AstNode>| binary(IToken::Pow, argument, number(2.0));
+    // 1 - u^2, under the root in the derivatives of asin and acos
+    let complement = |argument| binary(IToken::Sub, number(1.0), square(argument));
+
+    Ok(match function {
+        IFunctions::Sqrt => binary(
+            IToken::Div,
+            differential,
+            binary(IToken::Mul, number(2.0), call(IFunctions::Sqrt, argument)),
+        ),
+        IFunctions::Abs => {
+            let sign = binary(
+                IToken::Div,
+                copy_node(argument.as_ref()),
+                call(IFunctions::Abs, argument),
+            );
+
+            binary(IToken::Mul, differential, sign)
+        }
+        IFunctions::Exp => binary(IToken::Mul, call(IFunctions::Exp, argument), differential),
+        IFunctions::Sin => binary(IToken::Mul, call(IFunctions::Cos, argument), differential),
+        IFunctions::Cos => unary(
+            IToken::Neg,
+            binary(IToken::Mul, call(IFunctions::Sin, argument), differential),
+        ),
+        IFunctions::Tan => binary(
+            IToken::Div,
+            differential,
+            square(call(IFunctions::Cos, argument)),
+        ),
+        IFunctions::Asin => binary(
+            IToken::Div,
+            differential,
+            call(IFunctions::Sqrt, complement(argument)),
+        ),
+        IFunctions::Acos => unary(
+            IToken::Neg,
+            binary(
+                IToken::Div,
+                differential,
+                call(IFunctions::Sqrt, complement(argument)),
+            ),
+        ),
+        IFunctions::Atan => binary(
+            IToken::Div,
+            differential,
+            binary(IToken::Add, number(1.0), square(argument)),
+        ),
+        IFunctions::Re | IFunctions::Conj => differential,
+        // constant for real arguments, where they are differentiable
+        IFunctions::Im | IFunctions::Arg => number(0.0),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{} cannot be differentiated", function.name()),
+            ))
+        }
+    })
+}
+
+fn number(value: f64) -> Box<dyn AstNode> {
+    new_node(Token::number(value), vec![])
+}
+
+fn unary(token_type: IToken, operand: Box<dyn AstNode>) -> Box<dyn AstNode> {
+    new_node(Token::operator(token_type), vec![operand])
+}
+
+fn binary(token_type: IToken, left: Box<dyn AstNode>, right: Box<dyn AstNode>) -> Box<dyn AstNode> {
+    new_node(Token::operator(token_type), vec![left, right])
+}
+
+fn call(function: IFunctions, argument: Box<dyn AstNode>) -> Box<dyn AstNode> {
+    new_node(Token::operator(IToken::Fun(function)), vec![argument])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::infix::to_infix;
+    use crate::span::{span_of, Span};
+
+    fn derive(expr: &str) -> Result<String, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        differentiate(parser.ast(), "x").map(|derivative| to_infix(derivative.as_ref()))
+    }
+
+    #[test]
+    fn differentiate_applies_rules_and_simplifies() {
+        assert_eq!("2*x + 3", derive("x^2 + 3*x").unwrap());
+        assert_eq!("3*x^2", derive("x^3 - y").unwrap());
+        assert_eq!("2*cos(x^2)*x", derive("sin(x^2)").unwrap());
+        assert_eq!("-sin(x)", derive("cos(x)").unwrap());
+        assert_eq!("2*exp(2*x)", derive("exp(2 * x)").unwrap());
+        assert_eq!("-1/x^2", derive("(x + 1) / x").unwrap());
+        assert_eq!("1/(2*sqrt(x))", derive("sqrt(x)").unwrap());
+        assert_eq!("0", derive("pi * y").unwrap());
+        // a number before a name multiplies it
+        assert_eq!("2*x + 3", derive("x^2 + 3x").unwrap());
+    }
+
+    #[test]
+    fn differentiate_powers_of_positive_numbers() {
+        assert_eq!("0.6931471805599453*2^x", derive("2^x").unwrap());
+        assert_eq!("2.0794415416798357*2^(3*x)", derive("2^(3*x)").unwrap());
+        assert_eq!(
+            "3*x^2 + 1.1447298858494661*pi^x",
+            derive("x^3 + pi^x").unwrap()
+        );
+    }
+
+    #[test]
+    fn differentiate_fails_for_variable_bases_and_exponents_and_max() {
+        let error = derive("1 + x ^ x").unwrap_err();
+
+        assert_eq!(
+            "Cannot differentiate a power with x in its base and exponent at 6..7",
+            error.to_string()
+        );
+        assert_eq!(Some(Span::new(6, 7)), span_of(&error));
+        assert_eq!(
+            "Cannot differentiate a power with x in its exponent unless its base is a positive number at 4..5",
+            derive("(-2)^x").unwrap_err().to_string()
+        );
+        assert!(derive("y^x").is_err());
+        assert!(derive("max(x, 1)").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::numeric::{ordered_function, Numeric};
+use crate::tokenizer::tokens::{IAngleUnit, IConstants, IFunctions};
+use num_traits::Zero;
+use std::cmp::Ordering;
+use std::io::{Error, ErrorKind};
+
+pub use num_complex::Complex64;
+
+/// Complex arithmetic on pairs of f64. Literals are real and the i
+/// suffix makes them imaginary. Functions return the principal value,
+/// so sqrt(-1) is i, and max and min only accept real values.
+impl Numeric for Complex64 {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        f64::from_literal(text).map(|re| Complex64::new(re, 0.0))
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        // the precise value so exp(i*pi) is -1 up to rounding
+        match constant {
+            IConstants::Pi => Ok(Complex64::new(std::f64::consts::PI, 0.0)),
+        }
+    }
+    fn imaginary(text: &str) -> Result<Self, Error> {
+        f64::from_literal(text).map(|im| Complex64::new(0.0, im))
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self + other)
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self - other)
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self * other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self / other)
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        // integer powers by repeated multiplication keep results like
+        // (1-2i)^2 exact
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= i32::MAX as f64
+        {
+            return Ok(self.powi(exponent.re as i32));
+        }
+
+        if self.is_zero() && exponent.re > 0.0 {
+            return Ok(Complex64::zero());
+        }
+
+        Ok(self.powc(*exponent))
+    }
+    fn to_real(&self) -> Option<f64> {
+        Some(self.re).filter(|_| self.im == 0.0)
+    }
+    fn from_real(value: f64) -> Option<Self> {
+        Some(Complex64::new(value, 0.0))
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        if self.im == 0.0 && other.im == 0.0 {
+            self.re.partial_cmp(&other.re)
+        } else {
+            None
+        }
+    }
+    fn angle_to_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        Ok(Complex64::new(
+            self.re.angle_to_radians(unit)?,
+            self.im.angle_to_radians(unit)?,
+        ))
+    }
+    fn angle_from_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        Ok(Complex64::new(
+            self.re.angle_from_radians(unit)?,
+            self.im.angle_from_radians(unit)?,
+        ))
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = arguments[0];
+
+        Ok(match function {
+            IFunctions::Sqrt => argument.sqrt(),
+            IFunctions::Abs => Complex64::new(argument.norm(), 0.0),
+            IFunctions::Arg => Complex64::new(argument.arg(), 0.0),
+            IFunctions::Conj => argument.conj(),
+            IFunctions::Re => Complex64::new(argument.re, 0.0),
+            IFunctions::Im => Complex64::new(argument.im, 0.0),
+            IFunctions::Exp => argument.exp(),
+            IFunctions::Sin => argument.sin(),
+            IFunctions::Cos => argument.cos(),
+            IFunctions::Tan => argument.tan(),
+            IFunctions::Asin => argument.asin(),
+            IFunctions::Acos => argument.acos(),
+            IFunctions::Atan => argument.atan(),
+            IFunctions::Max | IFunctions::Min
+                if arguments.iter().any(|argument| argument.im != 0.0) =>
+            {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "{} is not defined for numbers with an imaginary part",
+                        function.name()
+                    ),
+                ))
+            }
+            _ => return ordered_function(function, arguments),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::nodes::Variables;
+    use crate::numeric::evaluate_numeric;
+
+    fn evaluate(expr: &str) -> Result<Complex64, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        evaluate_numeric(parser.ast(), &Variables::new())
+    }
+
+    #[test]
+    fn evaluate_complex_expressions() {
+        assert_eq!(
+            Complex64::new(11.0, -2.0),
+            evaluate("(3+4i)*(1-2i)").unwrap()
+        );
+        assert_eq!(Complex64::new(0.0, 1.0), evaluate("sqrt(-1)").unwrap());
+        assert_eq!(Complex64::new(-1.0, 0.0), evaluate("i ^ 2").unwrap());
+        assert_eq!(Complex64::new(-2.0, 0.0), evaluate("2i^2").unwrap());
+        assert_eq!(Complex64::new(5.0, 0.0), evaluate("abs(3 - 4i)").unwrap());
+        assert_eq!(
+            Complex64::new(3.0, -4.0),
+            evaluate("conj(re(3+4i) + im(3+4i) * i)").unwrap()
+        );
+
+        let euler = evaluate("exp(i * pi)").unwrap();
+
+        assert!((euler - Complex64::new(-1.0, 0.0)).norm() < 1e-15);
+        assert_eq!(std::f64::consts::FRAC_PI_2, evaluate("arg(2i)").unwrap().re);
+    }
+
+    #[test]
+    fn max_and_min_require_real_arguments() {
+        assert_eq!(Complex64::new(2.0, 0.0), evaluate("max(2, 1)").unwrap());
+        assert_eq!(
+            ErrorKind::Unsupported,
+            evaluate("max(i, 1)").unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use crate::ast::nodes::Variables;
+use crate::shunting_yard_parser::parse::ShuntingYardParser;
+use std::io::{BufRead, Error, ErrorKind};
+
+/// Reads comma separated records from a reader. Fields may be quoted
+/// with double quotes, in which case they can contain commas, line
+/// breaks and quotes written as two double quotes.
+pub struct CsvReader<R: BufRead> {
+    reader: R,
+}
+
+/// The outcome of evaluating the expression for a single record
+#[derive(Debug)]
+pub struct RowResult {
+    /// The number of the record after the header, starting from 1
+    pub row_number: usize,
+    pub record: Vec<String>,
+    pub value: Result<f64, Error>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Reads the next record returning None at the end of the input.
+    /// Blank lines between records are skipped.
+    fn read_record(&mut self) -> Result<Option<Vec<String>>, Error> {
+        let mut line = String::new();
+
+        loop {
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            if !line.trim().is_empty() {
+                break;
+            }
+
+            line.clear();
+        }
+
+        let mut fields = vec![];
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = line.chars().peekable();
+
+        loop {
+            let char = match chars.next() {
+                Some(char) => char,
+                None if in_quotes => {
+                    // the quoted field continues on the next line
+                    line.clear();
+
+                    if self.reader.read_line(&mut line)? == 0 {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            "Unterminated quoted field",
+                        ));
+                    }
+
+                    chars = line.chars().peekable();
+                    continue;
+                }
+                None => break,
+            };
+
+            match char {
+                '"' if in_quotes && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' if in_quotes => in_quotes = false,
+                '"' if field.is_empty() => in_quotes = true,
+                ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+                '\n' | '\r' if !in_quotes => (),
+                char => field.push(char),
+            }
+        }
+
+        fields.push(field);
+
+        Ok(Some(fields))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = Result<Vec<String>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Writes the fields as a single CSV record without the line break,
+/// quoting the fields which need it
+pub fn write_record(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Reads the header of the CSV from reader and returns it along with
+/// the results of evaluating the expression held by parser for every
+/// record. The columns of a record are bound to the variables named by
+/// the header. Records which fail are reported in their RowResult so
+/// the remaining records are still evaluated.
+pub fn evaluate_records<'a, R: BufRead + 'a>(
+    reader: R,
+    parser: &'a ShuntingYardParser,
+) -> Result<
+    (
+        Vec<String>,
+        impl Iterator<Item = Result<RowResult, Error>> + 'a,
+    ),
+    Error,
+> {
+    let mut records = CsvReader::new(reader);
+    let header: Vec<String> = records
+        .next()
+        .transpose()?
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing CSV header"))?
+        .iter()
+        .map(|name| name.trim().to_string())
+        .collect();
+    let mut columns = vec![];
+
+    for name in parser.variables() {
+        match header.iter().position(|column| *column == name) {
+            Some(column) => columns.push((name, column)),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!("Unbound variable: {} is not a column", name),
+                ))
+            }
+        }
+    }
+
+    let width = header.len();
+    let rows = records.enumerate().map(move |(index, record)| {
+        let record = record?;
+        let value = bind_record(&record, width, &columns)
+            .and_then(|variables| parser.try_evaluate_with(&variables));
+
+        Ok(RowResult {
+            row_number: index + 1,
+            record,
+            value,
+        })
+    });
+
+    Ok((header, rows))
+}
+
+/// Parses the values of columns in record into variables
+fn bind_record(
+    record: &[String],
+    width: usize,
+    columns: &[(String, usize)],
+) -> Result<Variables, Error> {
+    if record.len() != width {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Expected {} fields but found {}", width, record.len()),
+        ));
+    }
+
+    let mut variables = Variables::new();
+
+    for (name, column) in columns {
+        let value = record[*column].trim().parse::<f64>().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Column {} is not a number: {}", name, record[*column]),
+            )
+        })?;
+
+        variables.insert(name.clone(), value);
+    }
+
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_expression_shunting_yard;
+    use std::io::Cursor;
+
+    #[test]
+    fn csv_reader_reads_quoted_fields() {
+        let input = Cursor::new("a,\"b,c\",\"say \"\"hi\"\"\"\r\n\n\"multi\nline\",,x\n\n");
+        let records: Vec<Vec<String>> = CsvReader::new(input).map(Result::unwrap).collect();
+
+        assert_eq!(
+            vec![vec!["a", "b,c", "say \"hi\""], vec!["multi\nline", "", "x"]],
+            records
+        );
+    }
+
+    #[test]
+    fn write_record_quotes_fields_when_needed() {
+        let fields = vec![
+            String::from("plain"),
+            String::from("a,b"),
+            String::from("say \"hi\""),
+        ];
+
+        assert_eq!("plain,\"a,b\",\"say \"\"hi\"\"\"", write_record(&fields));
+    }
+
+    #[test]
+    fn evaluate_records_binds_columns_to_variables() {
+        let parser = parse_expression_shunting_yard("price * qty").unwrap();
+        let input = Cursor::new("item,price,qty\npen,1.5,4\nbook,n/a,1\nmug,3\nink,2,2\n");
+        let (header, rows) = evaluate_records(input, &parser).unwrap();
+        let rows: Vec<RowResult> = rows.map(Result::unwrap).collect();
+
+        assert_eq!(vec!["item", "price", "qty"], header);
+        assert_eq!(6.0, *rows[0].value.as_ref().unwrap());
+        assert_eq!(
+            "Column price is not a number: n/a",
+            rows[1].value.as_ref().unwrap_err().to_string()
+        );
+        assert_eq!(
+            ErrorKind::InvalidData,
+            rows[2].value.as_ref().unwrap_err().kind()
+        );
+        assert_eq!(4, rows[3].row_number);
+        assert_eq!(4.0, *rows[3].value.as_ref().unwrap());
+    }
+
+    #[test]
+    fn evaluate_records_returns_error_for_missing_column() {
+        let parser = parse_expression_shunting_yard("price * total").unwrap();
+        let input = Cursor::new("price,qty\n1,2\n");
+
+        assert_eq!(
+            ErrorKind::NotFound,
+            evaluate_records(input, &parser).err().unwrap().kind()
+        );
+    }
+}
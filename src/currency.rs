@@ -0,0 +1,397 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::decimal::{Decimal, DecimalContext};
+use crate::numeric::unsupported_function;
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IFunctions, IToken};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A source of exchange rates between currencies named by their ISO 4217
+/// codes, like USD
+pub trait ExchangeRateProvider {
+    /// The amount of to one unit of from is worth, or None when the
+    /// provider has no rate from from to to
+    fn rate(&self, from: &str, to: &str) -> Option<Decimal>;
+}
+
+/// A fixed table of exchange rates, which also converts with the inverse
+/// of the rate in the other direction
+#[derive(Debug, Clone, Default)]
+pub struct RateTable {
+    rates: HashMap<(String, String), Decimal>,
+}
+
+/// An amount of money in a currency, or a plain number when currency is
+/// None
+#[derive(Debug, Clone, PartialEq)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: Option<String>,
+}
+
+/// Whether code is written like an ISO 4217 currency code, three
+/// uppercase letters
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|char| char.is_ascii_uppercase())
+}
+
+impl RateTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the amount of to one unit of from is worth
+    pub fn insert(&mut self, from: &str, to: &str, rate: Decimal) {
+        self.rates.insert((from.to_string(), to.to_string()), rate);
+    }
+
+    /// Reads the table from a file in the format parsed by from_str
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+impl FromStr for RateTable {
+    type Err = Error;
+
+    /// Parses one rate per line written as the two currency codes and the
+    /// rate, so EUR USD 1.08 means one EUR is worth 1.08 USD. Empty lines
+    /// and lines starting with # are skipped. Malformed lines are errors
+    /// of ErrorKind::Other, like failures to read the file.
+    fn from_str(table: &str) -> Result<Self, Self::Err> {
+        let mut rates = RateTable::new();
+
+        for (number, line) in table.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                [from, to, rate] if is_currency_code(from) && is_currency_code(to) => {
+                    let rate = Decimal::parse(rate)
+                        .filter(|rate| *rate > Decimal::new(BigInt::zero(), 0))
+                        .ok_or_else(|| {
+                            Error::other(format!("Line {}: invalid rate: {}", number + 1, rate))
+                        })?;
+
+                    rates.insert(from, to, rate);
+                }
+                _ => {
+                    return Err(Error::other(format!(
+                        "Line {}: expected <from> <to> <rate>, found: {}",
+                        number + 1,
+                        line
+                    )))
+                }
+            }
+        }
+
+        Ok(rates)
+    }
+}
+
+impl ExchangeRateProvider for RateTable {
+    fn rate(&self, from: &str, to: &str) -> Option<Decimal> {
+        self.rates.get(&(from.to_string(), to.to_string())).cloned()
+    }
+}
+
+impl Money {
+    pub fn number(amount: Decimal) -> Self {
+        Money {
+            amount,
+            currency: None,
+        }
+    }
+
+    pub fn new(amount: Decimal, currency: &str) -> Self {
+        Money {
+            amount,
+            currency: Some(currency.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.currency {
+            Some(currency) => write!(f, "{} {}", self.amount, currency),
+            None => write!(f, "{}", self.amount),
+        }
+    }
+}
+
+/// Evaluates the tree rooted at node with decimal amounts of money.
+/// Amounts in different currencies are added, subtracted and compared
+/// after converting the right operand to the currency of the left one
+/// with the rates of provider, and to or in converts to the currency
+/// after it. Amounts can be multiplied and divided by numbers, and an
+/// amount divided by an amount is a number. Errors carry the span of the
+/// operator they were raised at.
+pub fn evaluate_currency(
+    node: &dyn AstNode,
+    variables: &Variables<Money>,
+    provider: &dyn ExchangeRateProvider,
+    context: &DecimalContext,
+) -> Result<Money, Error> {
+    evaluate_in(node, variables, provider, context, None)
+}
+
+/// Evaluates node converting every amount to the target currency of the
+/// conversion it is under, if any, so 100 USD + 50 EUR in GBP only needs
+/// rates to GBP
+fn evaluate_in(
+    node: &dyn AstNode,
+    variables: &Variables<Money>,
+    provider: &dyn ExchangeRateProvider,
+    context: &DecimalContext,
+    target: Option<&str>,
+) -> Result<Money, Error> {
+    let token = node.token();
+    let in_target = |money: Money| match (&money.currency, target) {
+        (Some(from), Some(to)) => {
+            convert(&money.amount, from, to, provider, context).map(|amount| Money::new(amount, to))
+        }
+        _ => Ok(money),
+    };
+    let decimal = |text: &str| {
+        Decimal::parse(text)
+            .map(|amount| context.round(amount))
+            .ok_or_else(|| Error::new(ErrorKind::Unsupported, format!("Invalid number: {}", text)))
+    };
+    let result = match token.token_type {
+        IToken::Num => decimal(&token.lexeme.unwrap()).map(Money::number),
+        IToken::Currency => {
+            let lexeme = token.lexeme.unwrap();
+            let (amount, currency) = lexeme.split_once(' ').unwrap();
+
+            decimal(amount).and_then(|amount| in_target(Money::new(amount, currency)))
+        }
+        IToken::Var => {
+            let name = token.lexeme.unwrap();
+
+            variables
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| {
+                    Error::new(ErrorKind::NotFound, format!("Unbound variable: {}", name))
+                })
+                .and_then(in_target)
+        }
+        IToken::To => {
+            let children = node.children();
+            let target = children[1].token();
+            let code = target
+                .lexeme
+                .as_deref()
+                .filter(|code| is_currency_code(code));
+            let money = evaluate_in(children[0].as_ref(), variables, provider, context, code)?;
+
+            match (&money.currency, &target.lexeme) {
+                (Some(from), Some(to)) if is_currency_code(to) => {
+                    convert(&money.amount, from, to, provider, context)
+                        .map(|amount| Money::new(amount, to))
+                }
+                (None, _) => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("Cannot convert the number {} to a currency", money),
+                )),
+                (Some(_), _) => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("Expected a currency after {}, found {}", token, target),
+                )),
+            }
+        }
+        token_type if !node.children().is_empty() => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate_in(child.as_ref(), variables, provider, context, target))
+                .collect::<Result<Vec<Money>, Error>>()?;
+
+            apply(token_type, &operands, provider, context)
+        }
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} is not supported in currency evaluation", token),
+        )),
+    };
+
+    result.map_err(|error| with_span(error, token.span))
+}
+
+/// Whether the tree rooted at node has amounts of money, which need
+/// evaluate_currency
+pub fn has_currency(node: &dyn AstNode) -> bool {
+    node.token_type() == IToken::Currency
+        || node
+            .children()
+            .iter()
+            .any(|child| has_currency(child.as_ref()))
+}
+
+/// Converts amount from one currency to another with the rate of
+/// provider in either direction. Conversions never go through a third
+/// currency, which would silently combine two rates.
+fn convert(
+    amount: &Decimal,
+    from: &str,
+    to: &str,
+    provider: &dyn ExchangeRateProvider,
+    context: &DecimalContext,
+) -> Result<Decimal, Error> {
+    if from == to {
+        return Ok(amount.clone());
+    }
+
+    match (provider.rate(from, to), provider.rate(to, from)) {
+        (Some(rate), _) => Ok(context.mul(amount, &rate)),
+        (None, Some(inverse)) => context.div(amount, &inverse),
+        (None, None) => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("No exchange rate from {} to {}", from, to),
+        )),
+    }
+}
+
+fn apply(
+    token_type: IToken,
+    operands: &[Money],
+    provider: &dyn ExchangeRateProvider,
+    context: &DecimalContext,
+) -> Result<Money, Error> {
+    let arg_1 = &operands[0];
+    let arg_2 = operands.get(1).unwrap_or(arg_1);
+    let mismatch = |operation: &str| {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("Cannot {} {} and {}", operation, arg_1, arg_2),
+        )
+    };
+    // the second amount in the currency of the first
+    let aligned = || match (&arg_1.currency, &arg_2.currency) {
+        (Some(from), Some(to)) => convert(&arg_2.amount, to, from, provider, context),
+        (None, None) => Ok(arg_2.amount.clone()),
+        _ => Err(mismatch("combine")),
+    };
+    let with_currency = |amount: Decimal| Money {
+        amount,
+        currency: arg_1.currency.clone(),
+    };
+
+    match token_type {
+        IToken::Add => Ok(with_currency(context.add(&arg_1.amount, &aligned()?)?)),
+        IToken::Sub => Ok(with_currency(context.sub(&arg_1.amount, &aligned()?)?)),
+        IToken::Neg => Ok(with_currency(arg_1.amount.neg())),
+        IToken::Mul => match (&arg_1.currency, &arg_2.currency) {
+            (Some(_), Some(_)) => Err(mismatch("multiply")),
+            (currency, None) | (None, currency) => Ok(Money {
+                amount: context.mul(&arg_1.amount, &arg_2.amount),
+                currency: currency.clone(),
+            }),
+        },
+        IToken::Div => match (&arg_1.currency, &arg_2.currency) {
+            (None, Some(_)) => Err(mismatch("divide")),
+            (Some(_), Some(_)) => Ok(Money::number(context.div(&arg_1.amount, &aligned()?)?)),
+            _ => Ok(with_currency(context.div(&arg_1.amount, &arg_2.amount)?)),
+        },
+        IToken::Pow if arg_1.currency.is_none() && arg_2.currency.is_none() => {
+            Ok(Money::number(context.pow(&arg_1.amount, &arg_2.amount)?))
+        }
+        IToken::Pow => Err(mismatch("raise")),
+        IToken::Fun(IFunctions::Max) if arg_1.amount >= aligned()? => Ok(arg_1.clone()),
+        IToken::Fun(IFunctions::Min) if arg_1.amount <= aligned()? => Ok(arg_1.clone()),
+        IToken::Fun(IFunctions::Max | IFunctions::Min) => Ok(arg_2.clone()),
+        IToken::Fun(IFunctions::Abs) if arg_1.amount < Decimal::new(BigInt::zero(), 0) => {
+            Ok(with_currency(arg_1.amount.neg()))
+        }
+        IToken::Fun(IFunctions::Abs | IFunctions::Re | IFunctions::Conj) => Ok(arg_1.clone()),
+        IToken::Fun(function) => Err(unsupported_function(function)),
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{:?} is not supported in currency evaluation", token_type),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{span_of, Span};
+
+    fn evaluate(expr: &str) -> Result<Money, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+        let rates: RateTable = "# rates on 2026-10-01\nUSD GBP 0.75\n\nEUR USD 1.10\n"
+            .parse()
+            .unwrap();
+
+        evaluate_currency(
+            parser.ast(),
+            &Variables::new(),
+            &rates,
+            &DecimalContext::default(),
+        )
+    }
+
+    #[test]
+    fn evaluate_currency_converts_with_rates_in_either_direction() {
+        assert_eq!(
+            "155.00 USD",
+            evaluate("100 USD + 50 EUR").unwrap().to_string()
+        );
+        assert_eq!(
+            "112.50 GBP",
+            evaluate("100 USD + 50 USD in GBP").unwrap().to_string()
+        );
+        assert_eq!("10 EUR", evaluate("11 USD to EUR").unwrap().to_string());
+        assert_eq!("3.30 USD", evaluate("1.10 USD * 3").unwrap().to_string());
+        assert_eq!("1", evaluate("2.20 USD / 2 EUR").unwrap().to_string());
+        assert_eq!("2 EUR", evaluate("max(1 USD, 2 EUR)").unwrap().to_string());
+    }
+
+    #[test]
+    fn evaluate_currency_fails_without_rate_or_for_mixed_operands() {
+        let error = evaluate("1 USD + 1 JPY").unwrap_err();
+
+        assert_eq!(
+            "No exchange rate from JPY to USD at 6..7",
+            error.to_string()
+        );
+        assert_eq!(Some(Span::new(6, 7)), span_of(&error));
+        // EUR and GBP both have rates to USD but none between them
+        assert_eq!(
+            "No exchange rate from EUR to GBP at 0..5",
+            evaluate("1 EUR in GBP").unwrap_err().to_string()
+        );
+
+        for expr in [
+            "1 USD + 1",
+            "1 USD * 1 USD",
+            "2 / 1 USD",
+            "1 to EUR",
+            "1 USD ^ 2",
+        ] {
+            assert_eq!(
+                ErrorKind::Unsupported,
+                evaluate(expr).unwrap_err().kind(),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn rate_table_rejects_malformed_lines() {
+        assert!("USD GBP".parse::<RateTable>().is_err());
+        assert!("USD GBP -1".parse::<RateTable>().is_err());
+        assert!("usd gbp 1".parse::<RateTable>().is_err());
+    }
+}
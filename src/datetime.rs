@@ -0,0 +1,596 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::{self, Numeric};
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IFunctions, IToken, Token};
+use crate::units::{Dimension, Quantity};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// The days since 1970-01-01 of 0000-01-01 and 9999-12-31, the first and
+/// last dates with the four digit years dates are written with
+const DAYS: (i64, i64) = (-719528, 2932896);
+
+/// The number of seconds from which durations are not read exactly, 2^53
+const MAX_EXACT_SECONDS: f64 = 9007199254740992.0;
+
+/// The dimension of durations written with units like 30 days
+const TIME: Dimension = Dimension([0, 0, 1, 0, 0, 0, 0]);
+
+/// A value of date evaluation. Dates count days and date times seconds
+/// since 1970-01-01, and durations are measured in seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Temporal {
+    Number(f64),
+    Date(i64),
+    DateTime(f64),
+    Duration(f64),
+}
+
+impl Temporal {
+    /// A name for the kind of the value used in errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Temporal::Number(_) => "number",
+            Temporal::Date(_) => "date",
+            Temporal::DateTime(_) => "datetime",
+            Temporal::Duration(_) => "duration",
+        }
+    }
+
+    /// The seconds since 1970-01-01 of dates and date times
+    fn seconds(&self) -> Option<f64> {
+        match self {
+            Temporal::Date(days) => Some(*days as f64 * SECONDS_PER_DAY),
+            Temporal::DateTime(seconds) => Some(*seconds),
+            _ => None,
+        }
+    }
+
+    /// Moves a date or date time by seconds. Dates stay dates when moved
+    /// by whole days. Results outside the years 0000 to 9999 are errors.
+    fn shift(&self, seconds: f64) -> Result<Option<Temporal>, Error> {
+        let Some(start) = self.seconds() else {
+            return Ok(None);
+        };
+        let end = start + seconds;
+        let days = (end / SECONDS_PER_DAY).floor();
+
+        // also false for NaN
+        if !(DAYS.0 as f64..=DAYS.1 as f64).contains(&days) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{} is outside the years 0000 to 9999", self.kind()),
+            ));
+        }
+
+        Ok(Some(match self {
+            Temporal::Date(_) if seconds % SECONDS_PER_DAY == 0.0 => Temporal::Date(days as i64),
+            _ => Temporal::DateTime(end),
+        }))
+    }
+
+    /// The value ordering values of the same kind
+    fn value(&self) -> f64 {
+        match self {
+            Temporal::Date(days) => *days as f64,
+            Temporal::Number(value) | Temporal::DateTime(value) | Temporal::Duration(value) => {
+                *value
+            }
+        }
+    }
+}
+
+impl FromStr for Temporal {
+    type Err = Error;
+
+    /// Parses a date like 2026-10-18, a date time like 2026-10-18T09:30, an
+    /// ISO 8601 duration like PT2H30M or a number
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_date(text)
+            .or_else(|_| parse_duration(text).map(Temporal::Duration))
+            .or_else(|error| text.parse().map(Temporal::Number).map_err(|_| error))
+    }
+}
+
+impl fmt::Display for Temporal {
+    /// Writes dates and date times in ISO 8601 and durations in days,
+    /// hours, minutes and seconds like P1DT2H30M
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Temporal::Number(value) => write!(f, "{}", value),
+            Temporal::Date(days) => {
+                let (year, month, day) = civil_from_days(*days);
+
+                write!(f, "{:04}-{:02}-{:02}", year, month, day)
+            }
+            Temporal::DateTime(seconds) => {
+                let days = (seconds / SECONDS_PER_DAY).floor();
+                let time = seconds - days * SECONDS_PER_DAY;
+
+                write!(
+                    f,
+                    "{}T{:02}:{:02}:",
+                    Temporal::Date(days as i64),
+                    (time / 3600.0).floor(),
+                    (time % 3600.0 / 60.0).floor()
+                )?;
+
+                match time % 60.0 {
+                    second if second < 10.0 => write!(f, "0{}", second),
+                    second => write!(f, "{}", second),
+                }
+            }
+            Temporal::Duration(seconds) => {
+                if *seconds < 0.0 {
+                    write!(f, "-")?;
+                }
+
+                let seconds = seconds.abs();
+                let days = (seconds / SECONDS_PER_DAY).floor();
+                let time = [
+                    ((seconds % SECONDS_PER_DAY / 3600.0).floor(), 'H'),
+                    ((seconds % 3600.0 / 60.0).floor(), 'M'),
+                    (seconds % 60.0, 'S'),
+                ];
+
+                write!(f, "P")?;
+
+                if days > 0.0 {
+                    write!(f, "{}D", days)?;
+                }
+
+                if time.iter().any(|(amount, _)| *amount > 0.0) {
+                    write!(f, "T")?;
+                } else if days == 0.0 {
+                    return write!(f, "T0S");
+                }
+
+                for (amount, designator) in time.iter().filter(|(amount, _)| *amount > 0.0) {
+                    write!(f, "{}{}", amount, designator)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Evaluates the tree rooted at node with dates, date times and durations.
+/// date("2026-10-18") reads a date or date time, duration("PT2H30M") an
+/// ISO 8601 duration and quantities of time like 30 days are durations.
+/// Errors carry the span of the token they were raised at.
+pub fn evaluate_temporal(
+    node: &dyn AstNode,
+    variables: &Variables<Temporal>,
+) -> Result<Temporal, Error> {
+    let token = node.token();
+    let result = match token.token_type {
+        IToken::Num => f64::from_literal(&token.lexeme.unwrap()).map(Temporal::Number),
+        IToken::Const(constant) => f64::constant(constant).map(Temporal::Number),
+        IToken::Quantity => {
+            let lexeme = token.lexeme.unwrap();
+            let (_, unit) = lexeme.split_once(' ').unwrap();
+            let quantity = Quantity::new(token.literal.unwrap(), &unit.parse()?);
+
+            match quantity.dimension == TIME {
+                true => Ok(Temporal::Duration(quantity.value)),
+                false => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("{} is not a duration", lexeme),
+                )),
+            }
+        }
+        IToken::Var => {
+            let name = token.lexeme.unwrap();
+
+            variables.get(&name).copied().ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("Unbound variable: {}", name))
+            })
+        }
+        IToken::Fun(function @ (IFunctions::Date | IFunctions::Duration)) => {
+            let argument = node.children()[0].token();
+
+            match (argument.token_type, function) {
+                (IToken::Str, IFunctions::Date) => parse_date(&argument.lexeme.unwrap()),
+                (IToken::Str, _) => {
+                    parse_duration(&argument.lexeme.unwrap()).map(Temporal::Duration)
+                }
+                _ => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("{} takes a string, found {}", function.name(), argument),
+                )),
+            }
+        }
+        _ if !node.children().is_empty() => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate_temporal(child.as_ref(), variables))
+                .collect::<Result<Vec<Temporal>, Error>>()?;
+
+            apply(&token, &operands)
+        }
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} is not supported in date evaluation", token),
+        )),
+    };
+
+    result.map_err(|error| with_span(error, token.span))
+}
+
+/// Whether the tree rooted at node has dates, durations or strings, which
+/// need evaluate_temporal
+pub fn has_dates(node: &dyn AstNode) -> bool {
+    matches!(
+        node.token_type(),
+        IToken::Str
+            | IToken::Fun(IFunctions::Date | IFunctions::Duration | IFunctions::DaysBetween)
+    ) || node
+        .children()
+        .iter()
+        .any(|child| has_dates(child.as_ref()))
+}
+
+/// Applies the operator or function of token to its operands. Dates and
+/// date times can be moved by durations and subtracted from each other,
+/// and durations can be added, scaled and divided. Anything else with a
+/// date or duration is an error.
+fn apply(token: &Token, operands: &[Temporal]) -> Result<Temporal, Error> {
+    use Temporal::{Duration, Number};
+
+    let numbers = operands
+        .iter()
+        .map(|operand| match operand {
+            Number(value) => Some(*value),
+            _ => None,
+        })
+        .collect::<Option<Vec<f64>>>();
+    let result = match (token.token_type, operands) {
+        (IToken::Fun(IFunctions::DaysBetween), [from, to]) => from
+            .seconds()
+            .zip(to.seconds())
+            .map(|(from, to)| Number((to - from) / SECONDS_PER_DAY)),
+        (token_type, _) if numbers.is_some() => {
+            return numeric::apply(token_type, &numbers.unwrap()).map(Number);
+        }
+        (IToken::Div, [Duration(_), Number(divisor) | Duration(divisor)]) if *divisor == 0.0 => {
+            return Err(Error::new(ErrorKind::Unsupported, "Division by zero"));
+        }
+        (IToken::Add, [Duration(a), Duration(b)]) => Some(Duration(a + b)),
+        (IToken::Add, [date, Duration(seconds)] | [Duration(seconds), date]) => {
+            date.shift(*seconds)?
+        }
+        (IToken::Sub, [Duration(a), Duration(b)]) => Some(Duration(a - b)),
+        (IToken::Sub, [date, Duration(seconds)]) => date.shift(-seconds)?,
+        (IToken::Sub, [a, b]) => a.seconds().zip(b.seconds()).map(|(a, b)| Duration(a - b)),
+        (
+            IToken::Mul,
+            [Duration(seconds), Number(factor)] | [Number(factor), Duration(seconds)],
+        ) => Some(Duration(seconds * factor)),
+        (IToken::Div, [Duration(seconds), Number(divisor)]) => Some(Duration(seconds / divisor)),
+        (IToken::Div, [Duration(a), Duration(b)]) => Some(Number(a / b)),
+        (IToken::Neg, [Duration(seconds)]) => Some(Duration(-seconds)),
+        (IToken::Fun(IFunctions::Abs), [Duration(seconds)]) => Some(Duration(seconds.abs())),
+        (IToken::Fun(function @ (IFunctions::Max | IFunctions::Min)), [a, b])
+            if a.kind() == b.kind() =>
+        {
+            match (function, a.value() >= b.value()) {
+                (IFunctions::Max, true) | (IFunctions::Min, false) => Some(*a),
+                _ => Some(*b),
+            }
+        }
+        _ => None,
+    };
+
+    result.ok_or_else(|| {
+        let kinds: Vec<&str> = operands.iter().map(Temporal::kind).collect();
+        let expression = match token.token_type {
+            IToken::Fun(function) => format!("{}({})", function.name(), kinds.join(", ")),
+            IToken::Neg => format!("-{}", kinds[0]),
+            _ => format!("{} {} {}", kinds[0], token, kinds[1]),
+        };
+
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("{} is not defined", expression),
+        )
+    })
+}
+
+/// Parses a date like 2026-10-18, or a date time when it is followed by
+/// a time like T09:30 or T09:30:15.5 with an optional Z
+fn parse_date(text: &str) -> Result<Temporal, Error> {
+    let invalid = || Error::new(ErrorKind::Unsupported, format!("Invalid date: {}", text));
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time.strip_suffix('Z').unwrap_or(time))),
+        None => (text, None),
+    };
+    let fields = date.split('-').collect::<Vec<&str>>();
+
+    let [year, month, day] = fields.as_slice() else {
+        return Err(invalid());
+    };
+
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return Err(invalid());
+    }
+
+    let number = |field: &str| match field.chars().all(|char| char.is_ascii_digit()) {
+        true => field.parse::<i64>().map_err(|_| invalid()),
+        false => Err(invalid()),
+    };
+    let (year, month, day) = (number(year)?, number(month)?, number(day)?);
+
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(invalid());
+    }
+
+    let days = days_from_civil(year, month, day);
+
+    let Some(time) = time else {
+        return Ok(Temporal::Date(days));
+    };
+
+    let fields = time.split(':').collect::<Vec<&str>>();
+    let (hour, minute, second) = match fields.as_slice() {
+        [hour, minute] if hour.len() == 2 && minute.len() == 2 => (hour, minute, "0"),
+        [hour, minute, second] if hour.len() == 2 && minute.len() == 2 => (hour, minute, *second),
+        _ => return Err(invalid()),
+    };
+    let second = match second.starts_with(|char: char| char.is_ascii_digit()) {
+        true => second.parse::<f64>().map_err(|_| invalid())?,
+        false => return Err(invalid()),
+    };
+    let (hour, minute) = (number(hour)?, number(minute)?);
+
+    if hour > 23 || minute > 59 || second >= 60.0 {
+        return Err(invalid());
+    }
+
+    Ok(Temporal::DateTime(
+        days as f64 * SECONDS_PER_DAY + (hour * 3600 + minute * 60) as f64 + second,
+    ))
+}
+
+/// Parses an ISO 8601 duration like PT2H30M or -P1W2D into seconds.
+/// Years and months have no fixed length and are not accepted.
+fn parse_duration(text: &str) -> Result<f64, Error> {
+    let invalid = || {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("Invalid duration: {}", text),
+        )
+    };
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, text),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+    let (date, time) = match rest.split_once('T') {
+        Some((_, "")) => return Err(invalid()),
+        Some((date, time)) => (date, time),
+        None => (rest, ""),
+    };
+
+    if date.contains(['Y', 'M']) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Years and months have no fixed length: {}", text),
+        ));
+    }
+
+    let mut seconds = 0.0;
+    let mut components = 0;
+
+    for (part, designators) in [
+        (date, &[('W', 604800.0), ('D', SECONDS_PER_DAY)][..]),
+        (time, &[('H', 3600.0), ('M', 60.0), ('S', 1.0)][..]),
+    ] {
+        let mut designators = designators.iter();
+        let mut amount = String::new();
+
+        for char in part.chars() {
+            match char {
+                char if char.is_ascii_digit() || char == '.' => amount.push(char),
+                designator => {
+                    // designators are written largest first, each at most once
+                    let (_, factor) = designators
+                        .find(|(expected, _)| *expected == designator)
+                        .ok_or_else(invalid)?;
+
+                    seconds += amount.parse::<f64>().map_err(|_| invalid())? * factor;
+
+                    if seconds >= MAX_EXACT_SECONDS {
+                        return Err(Error::new(
+                            ErrorKind::Unsupported,
+                            format!("Duration is too long to be read exactly: {}", text),
+                        ));
+                    }
+
+                    components += 1;
+                    amount.clear();
+                }
+            }
+        }
+
+        if !amount.is_empty() {
+            return Err(invalid());
+        }
+    }
+
+    match components {
+        0 => Err(invalid()),
+        _ => Ok(sign * seconds),
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days from 1970-01-01 to the date in the proleptic
+/// Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // years start in March so that leap days are the last day of a year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// The year, month and day of the date days after 1970-01-01, the
+/// inverse of days_from_civil. Computed with i128 so that no number of
+/// days overflows.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days as i128 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = (month_from_march + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + i128::from(month <= 2);
+
+    // a year is at least 365 days, so every year fits in an i64
+    (year as i64, month as i64, day as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{span_of, Span};
+
+    fn evaluate(expr: &str) -> Result<Temporal, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+        let variables = Variables::from([
+            (String::from("a"), "2024-02-28".parse().unwrap()),
+            (String::from("b"), "2024-03-01T12:00".parse().unwrap()),
+        ]);
+
+        evaluate_temporal(parser.ast(), &variables)
+    }
+
+    #[test]
+    fn evaluate_temporal_moves_dates_and_scales_durations() {
+        assert_eq!(
+            "2026-11-17",
+            evaluate("date(\"2026-10-18\") + 30 days")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "PT7H30M",
+            evaluate("duration(\"PT2H30M\") * 3").unwrap().to_string()
+        );
+        assert_eq!(
+            "2024-02-29T12:00:00",
+            evaluate("a + duration(\"P1DT12H\")").unwrap().to_string()
+        );
+        assert_eq!("P2DT12H", evaluate("b - a").unwrap().to_string());
+        assert_eq!(
+            Temporal::Number(2.5),
+            evaluate("days_between(a, b)").unwrap()
+        );
+        assert_eq!(Temporal::Number(4.0), evaluate("1 week / (42 h)").unwrap());
+        assert_eq!(
+            "1999-12-31",
+            evaluate("date(\"2000-01-01\") - 1 day")
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn evaluate_temporal_fails_for_undefined_operations() {
+        let error = evaluate("date(\"2026-10-18\") + a").unwrap_err();
+
+        assert_eq!("date + date is not defined at 19..20", error.to_string());
+        assert_eq!(Some(Span::new(19, 20)), span_of(&error));
+
+        for expr in [
+            "a * 2",
+            "a + 1",
+            "sqrt(1 day)",
+            "days_between(a, 1 day)",
+            "date(\"2026-02-29\")",
+            "duration(\"P1M\")",
+            "duration(\"PT\")",
+            "date(2026)",
+            "5 km + a",
+        ] {
+            assert!(evaluate(expr).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn evaluate_temporal_keeps_dates_in_four_digit_years() {
+        assert_eq!(
+            "9999-12-31",
+            evaluate("date(\"9999-12-30\") + 1 day")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "date is outside the years 0000 to 9999 at 19..20",
+            evaluate("date(\"9999-12-31\") + 1 day")
+                .unwrap_err()
+                .to_string()
+        );
+
+        for expr in [
+            "date(\"0000-01-01\") - 1 s",
+            "a + 100000000000000000000 days",
+            "b - duration(\"P9999999W\")",
+        ] {
+            assert_eq!(
+                ErrorKind::Unsupported,
+                evaluate(expr).unwrap_err().kind(),
+                "{}",
+                expr
+            );
+        }
+
+        assert_eq!(
+            "Duration is too long to be read exactly: PT99999999999999999999H",
+            "PT99999999999999999999H"
+                .parse::<Temporal>()
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn civil_dates_round_trip_through_days() {
+        assert_eq!(0, days_from_civil(1970, 1, 1));
+        assert_eq!(11016, days_from_civil(2000, 2, 29));
+
+        for days in [DAYS.0, -719468, -1, 0, 59, 11016, 20744, DAYS.1] {
+            let (year, month, day) = civil_from_days(days);
+
+            assert_eq!(days, days_from_civil(year, month, day));
+        }
+
+        assert_eq!((0, 1, 1), civil_from_days(DAYS.0));
+        assert_eq!((9999, 12, 31), civil_from_days(DAYS.1));
+        assert!(civil_from_days(i64::MAX).0 > 9999);
+        assert!(civil_from_days(i64::MIN).0 < 0);
+    }
+}
@@ -0,0 +1,659 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::higher_order::evaluate_binding;
+use crate::numeric::{
+    apply, evaluate_numeric, invalid_literal, ordered_function, unsupported_function, InContext,
+    Numeric,
+};
+use crate::rational::fits_exactly;
+use crate::tokenizer::tokens::{IConstants, IFunctions, IToken};
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// The number of significant digits of DecimalContext::default
+pub const DEFAULT_PRECISION: u32 = 28;
+
+/// pi to 100 decimal places, enough to round it to 100 significant digits
+const PI_DIGITS: &str = "3.1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+
+/// The largest precision pi can be rounded to from PI_DIGITS
+const MAX_PI_PRECISION: u32 = 100;
+
+/// How results are rounded to the precision of a DecimalContext
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Rounding {
+    /// To the nearest, ties to the even neighbour
+    #[default]
+    HalfEven,
+    /// To the nearest, ties away from zero
+    HalfUp,
+    /// To the nearest, ties towards zero
+    HalfDown,
+    /// Away from zero
+    Up,
+    /// Towards zero
+    Down,
+    /// Towards positive infinity
+    Ceiling,
+    /// Towards negative infinity
+    Floor,
+}
+
+/// A base 10 number, the coefficient multiplied by 10 to the power of
+/// the exponent
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    coefficient: BigInt,
+    exponent: i64,
+}
+
+/// The precision and rounding of decimal arithmetic
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DecimalContext {
+    /// The number of significant digits results are rounded to
+    pub precision: u32,
+    pub rounding: Rounding,
+}
+
+impl Default for DecimalContext {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+            rounding: Rounding::HalfEven,
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = Error;
+
+    fn from_str(rounding: &str) -> Result<Self, Self::Err> {
+        match rounding {
+            "half-even" => Ok(Rounding::HalfEven),
+            "half-up" => Ok(Rounding::HalfUp),
+            "half-down" => Ok(Rounding::HalfDown),
+            "up" => Ok(Rounding::Up),
+            "down" => Ok(Rounding::Down),
+            "ceiling" => Ok(Rounding::Ceiling),
+            "floor" => Ok(Rounding::Floor),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown rounding: {}", rounding),
+            )),
+        }
+    }
+}
+
+impl Decimal {
+    pub fn new(coefficient: BigInt, exponent: i64) -> Self {
+        Self {
+            coefficient,
+            exponent,
+        }
+    }
+
+    /// Parses a decimal written with digits, an optional decimal point
+    /// and an optional leading minus sign, like the lexeme of a number token
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let digits = format!("{}{}", integer, fraction);
+
+        if digits.is_empty() || !digits.chars().all(|char| char.is_ascii_digit()) {
+            return None;
+        }
+
+        let coefficient: BigInt = digits.parse().ok()?;
+
+        Some(Self::new(
+            if negative { -coefficient } else { coefficient },
+            -(fraction.len() as i64),
+        ))
+    }
+
+    /// Returns the value with the opposite sign
+    pub fn neg(&self) -> Decimal {
+        Decimal::new(-&self.coefficient, self.exponent)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficient.is_zero()
+    }
+
+    /// Returns the nearest f64 to the value
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the value as an integer when it has no fractional part
+    fn to_integer(&self) -> Option<BigInt> {
+        if self.exponent >= 0 {
+            return Some(&self.coefficient * power_of_ten(self.exponent as u64).ok()?);
+        }
+
+        let divisor = power_of_ten(self.exponent.unsigned_abs()).ok()?;
+
+        (&self.coefficient % &divisor)
+            .is_zero()
+            .then(|| &self.coefficient / &divisor)
+    }
+
+    /// Returns the coefficients of both values scaled to their smallest
+    /// exponent along with that exponent
+    fn aligned(&self, other: &Decimal) -> Result<(BigInt, BigInt, i64), Error> {
+        let exponent = self.exponent.min(other.exponent);
+        let scale = |value: &Decimal| {
+            power_of_ten(value.exponent.abs_diff(exponent)).map(|power| &value.coefficient * power)
+        };
+
+        Ok((scale(self)?, scale(other)?, exponent))
+    }
+
+    /// Returns the exponent of the leading digit, so 120 and 0.012 have
+    /// the adjusted exponents 2 and -2
+    fn adjusted_exponent(&self) -> i64 {
+        self.exponent + self.coefficient.abs().to_string().len() as i64 - 1
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign = |value: &Decimal| value.coefficient.sign();
+
+        if sign(self) != sign(other) || self.is_zero() {
+            return sign(self).cmp(&sign(other));
+        }
+
+        // values of the same sign and different leading digits are ordered
+        // without scaling one of them by a power of ten of any size
+        let magnitude = self.adjusted_exponent().cmp(&other.adjusted_exponent());
+
+        if magnitude != Ordering::Equal {
+            return match self.coefficient.is_negative() {
+                true => magnitude.reverse(),
+                false => magnitude,
+            };
+        }
+
+        // the exponents differ by no more than the digits of the coefficients
+        let (coefficient_1, coefficient_2, _) = self
+            .aligned(other)
+            .expect("values with the same leading digit are aligned by a small power");
+
+        coefficient_1.cmp(&coefficient_2)
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Writes the value in positional notation keeping the digits of
+    /// the coefficient, so 1.50 stays 1.50
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.coefficient.is_negative() {
+            "-"
+        } else {
+            ""
+        };
+        let mut digits = self.coefficient.abs().to_string();
+
+        if self.exponent >= 0 {
+            digits.push_str(&"0".repeat(self.exponent as usize));
+        } else {
+            let places = self.exponent.unsigned_abs() as usize;
+
+            if digits.len() <= places {
+                digits.insert_str(0, &"0".repeat(places + 1 - digits.len()));
+            }
+
+            digits.insert(digits.len() - places, '.');
+        }
+
+        write!(f, "{}{}", sign, digits)
+    }
+}
+
+impl DecimalContext {
+    /// Rounds value to the precision of the context
+    pub fn round(&self, value: Decimal) -> Decimal {
+        let negative = value.coefficient.is_negative();
+        let magnitude = value.coefficient.abs();
+        let digits = magnitude.to_string().len() as i64;
+        let dropped = digits - self.precision.max(1) as i64;
+
+        if dropped <= 0 {
+            return value;
+        }
+
+        let divisor =
+            power_of_ten(dropped as u64).expect("no more digits are dropped than there are");
+        let mut quotient = &magnitude / &divisor;
+        let remainder = &magnitude % &divisor;
+        let half = (&remainder * 2u32).cmp(&divisor);
+        let increment = match self.rounding {
+            Rounding::HalfEven => {
+                half == Ordering::Greater
+                    || half == Ordering::Equal && !(&quotient % 2u32).is_zero()
+            }
+            Rounding::HalfUp => half != Ordering::Less,
+            Rounding::HalfDown => half == Ordering::Greater,
+            Rounding::Up => !remainder.is_zero(),
+            Rounding::Down => false,
+            Rounding::Ceiling => !remainder.is_zero() && !negative,
+            Rounding::Floor => !remainder.is_zero() && negative,
+        };
+
+        let mut exponent = value.exponent + dropped;
+
+        if increment {
+            quotient += 1u32;
+
+            // rounding 99.5 up carries into a new digit which is a zero
+            if quotient.to_string().len() as i64 > digits - dropped {
+                quotient /= 10u32;
+                exponent += 1;
+            }
+        }
+
+        Decimal::new(if negative { -quotient } else { quotient }, exponent)
+    }
+
+    pub fn add(&self, arg_1: &Decimal, arg_2: &Decimal) -> Result<Decimal, Error> {
+        let (coefficient_1, coefficient_2, exponent) = arg_1.aligned(arg_2)?;
+
+        Ok(self.round(Decimal::new(coefficient_1 + coefficient_2, exponent)))
+    }
+
+    pub fn sub(&self, arg_1: &Decimal, arg_2: &Decimal) -> Result<Decimal, Error> {
+        let (coefficient_1, coefficient_2, exponent) = arg_1.aligned(arg_2)?;
+
+        Ok(self.round(Decimal::new(coefficient_1 - coefficient_2, exponent)))
+    }
+
+    pub fn mul(&self, arg_1: &Decimal, arg_2: &Decimal) -> Decimal {
+        self.round(Decimal::new(
+            &arg_1.coefficient * &arg_2.coefficient,
+            arg_1.exponent + arg_2.exponent,
+        ))
+    }
+
+    /// Divides arg_1 by arg_2 rounding the quotient to the precision.
+    /// Exact quotients are written without trailing zeros, so 1 / 4 is 0.25.
+    pub fn div(&self, arg_1: &Decimal, arg_2: &Decimal) -> Result<Decimal, Error> {
+        if arg_2.is_zero() {
+            return Err(Error::new(ErrorKind::Unsupported, "Division by zero"));
+        }
+
+        let ideal_exponent = arg_1.exponent - arg_2.exponent;
+        let digits = |value: &BigInt| value.abs().to_string().len() as i64;
+        // enough digits for the quotient to have one more than the precision
+        let shift = (self.precision as i64 + 1 + digits(&arg_2.coefficient)
+            - digits(&arg_1.coefficient))
+        .max(0);
+        let dividend = arg_1.coefficient.abs() * power_of_ten(shift as u64)?;
+        let divisor = arg_2.coefficient.abs();
+        let mut quotient = &dividend / &divisor;
+        let mut exponent = ideal_exponent - shift;
+        let exact = (&dividend % &divisor).is_zero();
+
+        if !exact {
+            // a non zero digit past the ones kept stands for the remainder
+            // so ties are told apart from values above them
+            quotient = quotient * 10u32 + 1u32;
+            exponent -= 1;
+        }
+
+        if arg_1.coefficient.is_negative() != arg_2.coefficient.is_negative() {
+            quotient = -quotient;
+        }
+
+        let mut quotient = self.round(Decimal::new(quotient, exponent));
+
+        while exact
+            && quotient.exponent < ideal_exponent
+            && (&quotient.coefficient % 10u32).is_zero()
+        {
+            quotient.coefficient /= 10u32;
+            quotient.exponent += 1;
+        }
+
+        Ok(quotient)
+    }
+
+    /// Raises base to an integer exponent. Fractional exponents are not
+    /// supported since their results are generally irrational, nor are
+    /// exponents which would make the coefficient or the exponent of the
+    /// result too large to hold exactly.
+    pub fn pow(&self, base: &Decimal, exponent: &Decimal) -> Result<Decimal, Error> {
+        let exponent = exponent
+            .to_integer()
+            .and_then(|exponent| exponent.to_i32())
+            .filter(|exponent| fits_exactly(base.coefficient.bits(), *exponent))
+            .filter(|exponent| fits_exactly(base.exponent.unsigned_abs(), *exponent))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Exponent {} is not an integer decimal arithmetic supports",
+                        exponent
+                    ),
+                )
+            })?;
+        let power = Decimal::new(
+            base.coefficient.pow(exponent.unsigned_abs()),
+            base.exponent * exponent.abs() as i64,
+        );
+
+        if exponent < 0 {
+            self.div(&Decimal::new(BigInt::one(), 0), &power)
+        } else {
+            Ok(self.round(power))
+        }
+    }
+}
+
+fn power_of_ten(exponent: u64) -> Result<BigInt, Error> {
+    let exponent = u32::try_from(exponent).map_err(|_| {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("10^{} is too large for decimal arithmetic", exponent),
+        )
+    })?;
+
+    Ok(BigInt::from(10u32).pow(exponent))
+}
+
+/// Evaluates the tree rooted at node with decimal arithmetic in the
+/// context. Number literals are read from their lexemes and variables
+/// from the shortest decimal which reads back as their value, and both
+/// are rounded to the precision.
+pub fn evaluate_decimal(
+    node: &dyn AstNode,
+    variables: &Variables,
+    context: &DecimalContext,
+) -> Result<Decimal, Error> {
+    evaluate(node, variables, &Variables::new(), context).map(|result| result.value)
+}
+
+/// Evaluates like evaluate_decimal, reading the variables bound by sum
+/// and prod from bound before variables
+fn evaluate(
+    node: &dyn AstNode,
+    variables: &Variables,
+    bound: &Variables<InContext<Decimal, DecimalContext>>,
+    context: &DecimalContext,
+) -> Result<InContext<Decimal, DecimalContext>, Error> {
+    let token_type = node.token_type();
+    let value = match token_type {
+        IToken::Num => {
+            let token = node.token();
+            let text = token
+                .lexeme
+                .unwrap_or_else(|| token.literal.unwrap().to_string());
+
+            read(&text, context)
+        }
+        IToken::Const(IConstants::Pi) => pi(context),
+        IToken::Angle(unit) => {
+            let lexeme = node.token().lexeme.unwrap();
+            let angle = read(lexeme.trim_end_matches(unit.suffix()), context)?;
+
+            match unit.half_turn() {
+                Some(half_turn) => context.div(
+                    &context.mul(&angle, &pi(context)?),
+                    &Decimal::parse(half_turn).unwrap(),
+                ),
+                None => Ok(angle),
+            }
+        }
+        IToken::Var if bound.contains_key(&node.token().lexeme.unwrap()) => {
+            return Ok(bound[&node.token().lexeme.unwrap()].clone());
+        }
+        IToken::Var => {
+            let name = node.token().lexeme.unwrap();
+            let value = *variables.get(&name).ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("Unbound variable: {}", name))
+            })?;
+
+            match Decimal::parse(&value.to_string()) {
+                Some(decimal) if value.is_finite() => Ok(context.round(decimal)),
+                _ => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("{} = {} is not a decimal", name, value),
+                )),
+            }
+        }
+        // their results are approximations even for exact arguments
+        IToken::Fun(function @ (IFunctions::Integrate | IFunctions::Solve)) => {
+            return Err(unsupported_function(function))
+        }
+        IToken::Fun(function) if function.binds_variable() => {
+            return evaluate_binding(node, function, bound, &|node, bound| {
+                evaluate(node, variables, bound, context)
+            })
+        }
+        _ if node.children().is_empty() => return evaluate_numeric(node, &Variables::new()),
+        _ => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate(child.as_ref(), variables, bound, context))
+                .collect::<Result<Vec<InContext<Decimal, DecimalContext>>, Error>>()?;
+
+            return apply(token_type, &operands);
+        }
+    };
+
+    value.map(|value| InContext {
+        value,
+        context: *context,
+    })
+}
+
+/// Reads the digits of a literal rounded to the precision
+fn read(text: &str, context: &DecimalContext) -> Result<Decimal, Error> {
+    Decimal::parse(text)
+        .map(|value| context.round(value))
+        .ok_or_else(|| invalid_literal(text))
+}
+
+/// Returns pi rounded to the precision
+fn pi(context: &DecimalContext) -> Result<Decimal, Error> {
+    if context.precision > MAX_PI_PRECISION {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("pi is only known to {} digits", MAX_PI_PRECISION),
+        ));
+    }
+
+    Ok(context.round(Decimal::parse(PI_DIGITS).unwrap()))
+}
+
+impl Numeric for InContext<Decimal, DecimalContext> {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        let context = DecimalContext::default();
+
+        read(text, &context).map(|value| InContext { value, context })
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        let context = DecimalContext::default();
+
+        match constant {
+            IConstants::Pi => pi(&context).map(|value| InContext { value, context }),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        self.with(self.context.add(&self.value, &other.value)?)
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        self.with(self.context.sub(&self.value, &other.value)?)
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        self.with(self.context.mul(&self.value, &other.value))
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        self.with(self.context.div(&self.value, &other.value)?)
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        self.with(self.context.pow(&self.value, &exponent.value)?)
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        Some(self.value.cmp(&other.value))
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        self.with(self.value.neg())
+    }
+    fn to_real(&self) -> Option<f64> {
+        Some(self.value.to_f64())
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = &arguments[0];
+
+        match function {
+            IFunctions::Abs if argument.value.coefficient.is_negative() => argument.neg(),
+            IFunctions::Abs | IFunctions::Re | IFunctions::Conj => Ok(argument.clone()),
+            IFunctions::Im => argument.with(Decimal::new(BigInt::zero(), 0)),
+            _ => ordered_function(function, arguments),
+        }
+    }
+}
+
+impl InContext<Decimal, DecimalContext> {
+    /// Returns value in the context of self
+    fn with(&self, value: Decimal) -> Result<Self, Error> {
+        Ok(InContext {
+            value,
+            context: self.context,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(expr: &str, context: &DecimalContext) -> Result<Decimal, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        evaluate_decimal(parser.ast(), &Variables::new(), context)
+    }
+
+    fn context(precision: u32, rounding: Rounding) -> DecimalContext {
+        DecimalContext {
+            precision,
+            rounding,
+        }
+    }
+
+    #[test]
+    fn evaluate_decimal_computes_in_base_10() {
+        let context = DecimalContext::default();
+
+        assert_eq!("0.3", evaluate("0.1 + 0.2", &context).unwrap().to_string());
+        assert_eq!("3.00", evaluate("1.50 * 2", &context).unwrap().to_string());
+        assert_eq!("0.25", evaluate("1 / 4", &context).unwrap().to_string());
+        assert_eq!(
+            "0.25",
+            evaluate("2 ^ (0 - 2)", &context).unwrap().to_string()
+        );
+        assert_eq!(
+            "0.3333333333333333333333333333",
+            evaluate("1 / 3", &context).unwrap().to_string()
+        );
+        assert_eq!(
+            "3.14159265358979323846264338328",
+            evaluate("pi", &self::context(30, Rounding::HalfEven))
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn round_applies_rounding_mode() {
+        let expected = [
+            (Rounding::HalfEven, ["2", "-2", "4"]),
+            (Rounding::HalfUp, ["3", "-3", "4"]),
+            (Rounding::HalfDown, ["2", "-2", "3"]),
+            (Rounding::Up, ["3", "-3", "4"]),
+            (Rounding::Down, ["2", "-2", "3"]),
+            (Rounding::Ceiling, ["3", "-2", "4"]),
+            (Rounding::Floor, ["2", "-3", "3"]),
+        ];
+
+        for (rounding, expected) in expected {
+            let rounded: Vec<String> = ["2.5", "-2.5", "3.5"]
+                .iter()
+                .map(|value| {
+                    context(1, rounding)
+                        .round(Decimal::parse(value).unwrap())
+                        .to_string()
+                })
+                .collect();
+
+            assert_eq!(expected.to_vec(), rounded, "{:?}", rounding);
+        }
+
+        assert_eq!(
+            "0.66667",
+            evaluate("2 / 3", &context(5, Rounding::HalfUp))
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "0.66666",
+            evaluate("2 / 3", &context(5, Rounding::Down))
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn evaluate_decimal_returns_error_for_unsupported_operations() {
+        let context = DecimalContext::default();
+
+        for expr in ["1 / (2 - 2)", "2 ^ 0.5", "integrate(x, x, 0, 1)"] {
+            assert_eq!(
+                ErrorKind::Unsupported,
+                evaluate(expr, &context).unwrap_err().kind()
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_decimal_bounds_the_exponents_of_powers() {
+        let context = DecimalContext::default();
+        let error = evaluate("(0.1 ^ 100000) ^ 1000", &context).unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert_eq!(
+            "Exponent 1000 is not an integer decimal arithmetic supports",
+            error.to_string()
+        );
+        assert_eq!(
+            "10^10000000000 is too large for decimal arithmetic",
+            power_of_ten(10_000_000_000).unwrap_err().to_string()
+        );
+        assert_eq!(
+            format!("0.{}1", "0".repeat(99_999)),
+            evaluate("0.1 ^ 100000", &context).unwrap().to_string()
+        );
+    }
+}
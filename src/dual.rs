@@ -0,0 +1,257 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::{evaluate_numeric, unsupported_function, Numeric};
+use crate::tokenizer::tokens::{IConstants, IFunctions};
+use std::cmp::Ordering;
+use std::io::Error;
+
+/// A dual number for forward mode automatic differentiation, a value
+/// with its partial derivatives with respect to the variables of an
+/// evaluation. Constants have an empty gradient, which stands for a
+/// gradient of zeros of any length.
+///
+/// Where a function is not differentiable the operations return a
+/// subgradient: abs has derivative 0 at 0, and max and min of equal
+/// values take the mean of the gradients of their arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dual {
+    value: f64,
+    gradient: Vec<f64>,
+}
+
+impl Dual {
+    /// Returns the dual number of a constant
+    pub fn constant(value: f64) -> Self {
+        Dual {
+            value,
+            gradient: vec![],
+        }
+    }
+
+    /// Returns the dual number of the variable at position of the
+    /// variables the evaluation differentiates with respect to, out of
+    /// count variables
+    pub fn variable(value: f64, position: usize, count: usize) -> Self {
+        let mut gradient = vec![0.0; count];
+
+        gradient[position] = 1.0;
+
+        Dual { value, gradient }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// The partial derivatives of the value. Empty for constants.
+    pub fn gradient(&self) -> &[f64] {
+        &self.gradient
+    }
+
+    /// Returns the dual number of f(self) given f(self) and f'(self)
+    fn chain(&self, value: f64, derivative: f64) -> Self {
+        Dual {
+            value,
+            gradient: self
+                .gradient
+                .iter()
+                .map(|partial| derivative * partial)
+                .collect(),
+        }
+    }
+
+    /// Returns the dual number with value whose gradient is a times the
+    /// gradient of self plus b times that of other
+    fn combine(&self, a: f64, other: &Self, b: f64, value: f64) -> Self {
+        let length = self.gradient.len().max(other.gradient.len());
+        let partial =
+            |gradient: &[f64], position: usize| gradient.get(position).copied().unwrap_or(0.0);
+
+        Dual {
+            value,
+            gradient: (0..length)
+                .map(|position| {
+                    a * partial(&self.gradient, position) + b * partial(&other.gradient, position)
+                })
+                .collect(),
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.gradient.iter().all(|partial| *partial == 0.0)
+    }
+}
+
+impl Numeric for Dual {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        f64::from_literal(text).map(Dual::constant)
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        f64::constant(constant).map(Dual::constant)
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self.combine(1.0, other, 1.0, self.value + other.value))
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self.combine(1.0, other, -1.0, self.value - other.value))
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self.combine(other.value, other, self.value, self.value * other.value))
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        let value = self.value / other.value;
+
+        Ok(self.combine(1.0 / other.value, other, -value / other.value, value))
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        let value = self.value.powf(exponent.value);
+        // d(u^v) = v*u^(v-1)*du + u^v*ln(u)*dv, leaving out the terms of
+        // constant operands so that powers of negative bases stay finite
+        // x^0 is constant, while 0 * 0^-1 would make its derivative NaN at 0
+        let base = match self.is_constant() || exponent.value == 0.0 {
+            true => 0.0,
+            false => exponent.value * self.value.powf(exponent.value - 1.0),
+        };
+        let power = match exponent.is_constant() {
+            true => 0.0,
+            false => value * self.value.ln(),
+        };
+
+        Ok(self.combine(base, exponent, power, value))
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        Ok(self.chain(-self.value, -1.0))
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = &arguments[0];
+        let x = argument.value;
+
+        Ok(match function {
+            IFunctions::Sqrt => argument.chain(x.sqrt(), 0.5 / x.sqrt()),
+            IFunctions::Abs => match x == 0.0 {
+                true => argument.chain(0.0, 0.0),
+                false => argument.chain(x.abs(), x.signum()),
+            },
+            // constant for real arguments like in f64 arithmetic
+            IFunctions::Arg => Dual::constant(f64::function(function, &[x])?),
+            IFunctions::Im => Dual::constant(0.0),
+            IFunctions::Conj | IFunctions::Re => argument.clone(),
+            IFunctions::Exp => argument.chain(x.exp(), x.exp()),
+            IFunctions::Sin => argument.chain(x.sin(), x.cos()),
+            IFunctions::Cos => argument.chain(x.cos(), -x.sin()),
+            IFunctions::Tan => argument.chain(x.tan(), 1.0 / x.cos().powi(2)),
+            IFunctions::Asin => argument.chain(x.asin(), 1.0 / (1.0 - x * x).sqrt()),
+            IFunctions::Acos => argument.chain(x.acos(), -1.0 / (1.0 - x * x).sqrt()),
+            IFunctions::Atan => argument.chain(x.atan(), 1.0 / (1.0 + x * x)),
+            IFunctions::Max | IFunctions::Min => {
+                let (a, b) = (argument, &arguments[1]);
+                let ordering = match function {
+                    IFunctions::Max => Ordering::Greater,
+                    _ => Ordering::Less,
+                };
+
+                match a.compare(b) {
+                    Some(Ordering::Equal) => a.combine(0.5, b, 0.5, a.value),
+                    Some(found) if found == ordering => a.clone(),
+                    _ => b.clone(),
+                }
+            }
+            _ => return Err(unsupported_function(function)),
+        })
+    }
+}
+
+/// Evaluates the tree rooted at node with dual numbers, returning its
+/// value and its gradient with respect to with_respect_to in the same
+/// order. The values of all variables are looked up in variables, and
+/// those not in with_respect_to are constants. Angles are in radians.
+pub fn evaluate_gradient(
+    node: &dyn AstNode,
+    variables: &Variables,
+    with_respect_to: &[&str],
+) -> Result<(f64, Vec<f64>), Error> {
+    let duals: Variables<Dual> = variables
+        .iter()
+        .map(|(name, value)| {
+            let dual = match with_respect_to.iter().position(|wrt| wrt == name) {
+                Some(position) => Dual::variable(*value, position, with_respect_to.len()),
+                None => Dual::constant(*value),
+            };
+
+            (name.clone(), dual)
+        })
+        .collect();
+    let result = evaluate_numeric(node, &duals)?;
+    let mut gradient = result.gradient;
+
+    gradient.resize(with_respect_to.len(), 0.0);
+
+    Ok((result.value, gradient))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(expr: &str, x: f64, y: f64) -> Result<(f64, Vec<f64>), Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+        let variables = Variables::from([(String::from("x"), x), (String::from("y"), y)]);
+
+        evaluate_gradient(parser.ast(), &variables, &["x", "y"])
+    }
+
+    #[test]
+    fn evaluate_gradient_differentiates_operators_and_functions() {
+        assert_eq!(
+            (5.0, vec![2.0, 1.0]),
+            gradient("x * y + 3", 1.0, 2.0).unwrap()
+        );
+        assert_eq!(
+            (0.5, vec![0.5, -0.25]),
+            gradient("x / y", 1.0, 2.0).unwrap()
+        );
+        assert_eq!((8.0, vec![12.0, 0.0]), gradient("x ^ 3", 2.0, 5.0).unwrap());
+        assert_eq!(
+            (9.0, vec![0.0, 9.0 * 3f64.ln()]),
+            gradient("3 ^ y", 0.0, 2.0).unwrap()
+        );
+        assert_eq!(
+            (-8.0, vec![12.0, 0.0]),
+            gradient("x ^ 3", -2.0, 1.0).unwrap()
+        );
+        assert_eq!((1.0, vec![0.0, 0.0]), gradient("x ^ 0", 0.0, 0.0).unwrap());
+        assert_eq!((1.0, vec![0.0, 0.0]), gradient("cos(x)", 0.0, 0.0).unwrap());
+        assert_eq!(
+            (2.0, vec![0.25, 0.0]),
+            gradient("sqrt(x)", 4.0, 0.0).unwrap()
+        );
+        assert_eq!(
+            (0.0, vec![2.0, 0.0]),
+            gradient("sin(x * y)", 0.0, 2.0).unwrap()
+        );
+        assert!(gradient("date(x)", 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn max_min_and_abs_return_subgradients() {
+        assert_eq!(
+            (3.0, vec![1.0, 0.0]),
+            gradient("max(x, y)", 3.0, 1.0).unwrap()
+        );
+        assert_eq!(
+            (1.0, vec![0.0, 1.0]),
+            gradient("min(x, y)", 3.0, 1.0).unwrap()
+        );
+        assert_eq!(
+            (2.0, vec![0.5, 0.5]),
+            gradient("max(x, y)", 2.0, 2.0).unwrap()
+        );
+        assert_eq!((0.0, vec![0.0, 0.0]), gradient("abs(x)", 0.0, 0.0).unwrap());
+        assert_eq!(
+            (2.0, vec![-1.0, 0.0]),
+            gradient("abs(x)", -2.0, 0.0).unwrap()
+        );
+    }
+}
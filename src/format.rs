@@ -0,0 +1,368 @@
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// The largest denominator considered by Notation::Fraction by default
+pub const DEFAULT_MAX_DENOMINATOR: u64 = 1000;
+
+/// How a value is written
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Notation {
+    /// Positional notation, e.g. 1234.5
+    #[default]
+    Plain,
+    /// A mantissa between 1 and 10 with an exponent, e.g. 1.2345e3
+    Scientific,
+    /// A mantissa between 1 and 1000 with an exponent which is a
+    /// multiple of three, e.g. 1.2345e3
+    Engineering,
+    /// The nearest fraction with a denominator no larger than the
+    /// max_denominator of the FormatOptions, e.g. 3/4
+    Fraction,
+}
+
+/// The number of digits a value is rounded to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Precision {
+    /// Digits after the decimal point. The scientific and engineering
+    /// notations round the mantissa to one more significant digit.
+    Decimals(usize),
+    /// Significant digits
+    Significant(usize),
+}
+
+/// Options for writing the result of an evaluation. The default writes
+/// values like the Display implementation of f64.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub notation: Notation,
+    /// The precision of the value. None writes the shortest
+    /// representation which reads back as the same value.
+    pub precision: Option<Precision>,
+    /// Separates groups of three digits of the integer part in the
+    /// plain notation
+    pub thousands_separator: Option<char>,
+    pub max_denominator: u64,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            notation: Notation::Plain,
+            precision: None,
+            thousands_separator: None,
+            max_denominator: DEFAULT_MAX_DENOMINATOR,
+        }
+    }
+}
+
+impl FromStr for Notation {
+    type Err = Error;
+
+    fn from_str(notation: &str) -> Result<Self, Self::Err> {
+        match notation {
+            "plain" => Ok(Notation::Plain),
+            "scientific" => Ok(Notation::Scientific),
+            "engineering" => Ok(Notation::Engineering),
+            "fraction" => Ok(Notation::Fraction),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown notation: {}", notation),
+            )),
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Writes value according to the options
+    pub fn format(&self, value: f64) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+
+        match self.notation {
+            Notation::Plain => self.format_plain(value),
+            Notation::Scientific => self.format_exponential(value, 1),
+            Notation::Engineering => self.format_exponential(value, 3),
+            Notation::Fraction => self.format_fraction(value),
+        }
+    }
+
+    /// Writes a complex number as its real and imaginary parts, like 3-4i,
+    /// leaving out a part which is written as zero
+    pub fn format_complex(&self, re: f64, im: f64) -> String {
+        let real = self.format(re);
+        let imaginary = self.format(im.abs());
+        let is_zero = |written: &str| written.parse::<f64>() == Ok(0.0);
+
+        match (is_zero(&real), is_zero(&imaginary)) {
+            (_, true) => real,
+            (true, false) if im < 0.0 => format!("-{}i", imaginary),
+            (true, false) => format!("{}i", imaginary),
+            (false, false) if im < 0.0 => format!("{}-{}i", real, imaginary),
+            (false, false) => format!("{}+{}i", real, imaginary),
+        }
+    }
+
+    fn format_plain(&self, value: f64) -> String {
+        let digits = match self.precision {
+            None => value.to_string(),
+            Some(Precision::Decimals(decimals)) => format!("{:.*}", decimals, value),
+            Some(Precision::Significant(significant)) => {
+                let (mantissa, exponent) = Self::split_exponential(value, significant);
+                let rounded: f64 = format!("{}e{}", mantissa, exponent).parse().unwrap();
+                let decimals = (significant as i32 - 1 - exponent).max(0) as usize;
+
+                format!("{:.*}", decimals, rounded)
+            }
+        };
+
+        match self.thousands_separator {
+            Some(separator) => Self::group_thousands(&digits, separator),
+            None => digits,
+        }
+    }
+
+    /// Writes value with an exponent which is a multiple of step
+    fn format_exponential(&self, value: f64, step: i32) -> String {
+        let (mantissa, exponent) = match self.precision {
+            Some(Precision::Significant(significant)) => {
+                Self::split_exponential(value, significant)
+            }
+            Some(Precision::Decimals(decimals)) => Self::split_exponential(value, decimals + 1),
+            None => {
+                let written = format!("{:e}", value);
+                let (mantissa, exponent) = written.split_once('e').unwrap();
+
+                (mantissa.to_string(), exponent.parse().unwrap())
+            }
+        };
+        let shift = exponent.rem_euclid(step);
+
+        format!(
+            "{}e{}",
+            Self::shift_point(&mantissa, shift as usize),
+            exponent - shift
+        )
+    }
+
+    /// Finds the nearest fraction with a denominator no larger than
+    /// max_denominator using the continued fraction expansion of value
+    fn format_fraction(&self, value: f64) -> String {
+        let sign = if value < 0.0 { "-" } else { "" };
+        let target = value.abs();
+        let (mut numerator, mut denominator) = (target.round(), 1.0);
+        let max_denominator = self.max_denominator.max(1) as f64;
+        let (mut previous_numerator, mut previous_denominator) = (0.0, 1.0);
+        let (mut current_numerator, mut current_denominator) = (1.0, 0.0);
+        let mut remainder = target;
+
+        loop {
+            let term = remainder.floor();
+            let next_numerator = term * current_numerator + previous_numerator;
+            let next_denominator = term * current_denominator + previous_denominator;
+
+            if next_denominator > max_denominator {
+                // the largest semiconvergent within the limit can be
+                // nearer than the last convergent
+                let steps =
+                    ((max_denominator - previous_denominator) / current_denominator).floor();
+                let semi_numerator = previous_numerator + steps * current_numerator;
+                let semi_denominator = previous_denominator + steps * current_denominator;
+
+                if (semi_numerator / semi_denominator - target).abs()
+                    < (numerator / denominator - target).abs()
+                {
+                    (numerator, denominator) = (semi_numerator, semi_denominator);
+                }
+
+                break;
+            }
+
+            (previous_numerator, previous_denominator) = (current_numerator, current_denominator);
+            (current_numerator, current_denominator) = (next_numerator, next_denominator);
+
+            if (current_numerator / current_denominator - target).abs()
+                < (numerator / denominator - target).abs()
+            {
+                (numerator, denominator) = (current_numerator, current_denominator);
+            }
+
+            let fraction = remainder - term;
+
+            if fraction < f64::EPSILON * remainder.max(1.0) {
+                break;
+            }
+
+            remainder = 1.0 / fraction;
+        }
+
+        if numerator == 0.0 {
+            String::from("0")
+        } else if denominator == 1.0 {
+            format!("{}{}", sign, numerator)
+        } else {
+            format!("{}{}/{}", sign, numerator, denominator)
+        }
+    }
+
+    /// Rounds value to significant digits and returns the mantissa,
+    /// between 1 and 10, and the decimal exponent
+    fn split_exponential(value: f64, significant: usize) -> (String, i32) {
+        let written = format!("{:.*e}", significant.max(1) - 1, value);
+        let (mantissa, exponent) = written.split_once('e').unwrap();
+
+        (mantissa.to_string(), exponent.parse().unwrap())
+    }
+
+    /// Moves the decimal point of the written mantissa shift places to
+    /// the right, keeping the number of significant digits
+    fn shift_point(mantissa: &str, shift: usize) -> String {
+        let (sign, unsigned) = match mantissa.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", mantissa),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let mut fraction = fraction.to_string();
+
+        while fraction.len() < shift {
+            fraction.push('0');
+        }
+
+        let (moved, rest) = fraction.split_at(shift);
+
+        if rest.is_empty() {
+            format!("{}{}{}", sign, integer, moved)
+        } else {
+            format!("{}{}{}.{}", sign, integer, moved, rest)
+        }
+    }
+
+    /// Inserts separator between groups of three digits of the integer part
+    fn group_thousands(digits: &str, separator: char) -> String {
+        let (sign, unsigned) = match digits.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", digits),
+        };
+        let (integer, fraction) = match unsigned.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (unsigned, None),
+        };
+        let mut grouped = String::from(sign);
+
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                grouped.push(separator);
+            }
+
+            grouped.push(digit);
+        }
+
+        if let Some(fraction) = fraction {
+            grouped.push('.');
+            grouped.push_str(fraction);
+        }
+
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(notation: Notation, precision: Option<Precision>) -> FormatOptions {
+        FormatOptions {
+            notation,
+            precision,
+            ..FormatOptions::default()
+        }
+    }
+
+    #[test]
+    fn default_options_write_like_display() {
+        let options = FormatOptions::default();
+
+        assert_eq!("0.9141592653589999", options.format(0.9141592653589999));
+        assert_eq!("4", options.format(4.0));
+        assert_eq!("inf", options.format(f64::INFINITY));
+    }
+
+    #[test]
+    fn format_rounds_to_decimals_and_significant_digits() {
+        let decimals = options(Notation::Plain, Some(Precision::Decimals(2)));
+        let significant = options(Notation::Plain, Some(Precision::Significant(3)));
+
+        assert_eq!("0.91", decimals.format(0.9141592653589999));
+        assert_eq!("3.00", decimals.format(3.0));
+        assert_eq!("0.914", significant.format(0.9141592653589999));
+        assert_eq!("123000", significant.format(123456.0));
+        assert_eq!("0.000123", significant.format(0.000123456));
+    }
+
+    #[test]
+    fn format_writes_scientific_and_engineering_notation() {
+        let scientific = options(Notation::Scientific, Some(Precision::Significant(3)));
+        let engineering = options(Notation::Engineering, Some(Precision::Significant(3)));
+
+        assert_eq!("1.23e4", scientific.format(12345.0));
+        assert_eq!("-4.50e-3", scientific.format(-0.0045));
+        assert_eq!("12.3e3", engineering.format(12345.0));
+        assert_eq!("4.50e-3", engineering.format(0.0045));
+        assert_eq!("123e-6", engineering.format(0.000123));
+        assert_eq!("1.5e3", options(Notation::Scientific, None).format(1500.0));
+        assert_eq!("100e-3", options(Notation::Engineering, None).format(0.1));
+        assert_eq!(
+            "12.5e3",
+            options(Notation::Engineering, None).format(12500.0)
+        );
+        assert_eq!(
+            "1.23e3",
+            options(Notation::Engineering, Some(Precision::Decimals(2))).format(1234.0)
+        );
+    }
+
+    #[test]
+    fn format_complex_leaves_out_zero_parts() {
+        let plain = FormatOptions::default();
+        let decimals = options(Notation::Plain, Some(Precision::Decimals(3)));
+
+        assert_eq!("11-2i", plain.format_complex(11.0, -2.0));
+        assert_eq!("1i", plain.format_complex(0.0, 1.0));
+        assert_eq!("0", plain.format_complex(0.0, 0.0));
+        assert_eq!(
+            "-1.000",
+            decimals.format_complex(-1.0, 1.2246467991473532e-16)
+        );
+    }
+
+    #[test]
+    fn format_groups_thousands() {
+        let options = FormatOptions {
+            thousands_separator: Some(','),
+            ..FormatOptions::default()
+        };
+
+        assert_eq!("1,234,567.25", options.format(1234567.25));
+        assert_eq!("-123", options.format(-123.0));
+        assert_eq!("-1,000", options.format(-1000.0));
+    }
+
+    #[test]
+    fn format_finds_nearest_simple_fraction() {
+        let fraction = options(Notation::Fraction, None);
+
+        assert_eq!("3/4", fraction.format(0.75));
+        assert_eq!("-1/3", fraction.format(-1.0 / 3.0));
+        assert_eq!("355/113", fraction.format(std::f64::consts::PI));
+        assert_eq!("2", fraction.format(2.0));
+        assert_eq!("0", fraction.format(0.0001));
+        assert_eq!(
+            "22/7",
+            FormatOptions {
+                max_denominator: 10,
+                ..fraction
+            }
+            .format(std::f64::consts::PI)
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::Numeric;
+use crate::span::with_span;
+use crate::tokenizer::tokens::IFunctions;
+use std::cmp::Ordering;
+use std::io::{Error, ErrorKind};
+
+/// The most terms sum and prod evaluate
+pub const MAX_TERMS: usize = 1_000_000;
+/// The most times integrate evaluates its integrand
+pub const MAX_EVALUATIONS: usize = 1_000_000;
+/// The error integrate allows relative to the size of the integral
+const TOLERANCE: f64 = 1e-10;
+/// The most times integrate halves an interval
+const MAX_DEPTH: u32 = 50;
+/// The most steps of Newton's method and of the search for a sign
+/// change solve takes
+const MAX_ITERATIONS: usize = 100;
+/// The most times solve halves an interval with a sign change
+const MAX_BISECTIONS: usize = 200;
+/// The largest value of the function at a root solve accepts, relative
+/// to its value at the guess, so that poles like the 0 of 1/x are no roots
+const RESIDUAL: f64 = 1e-6;
+
+/// Evaluates a tree with the arithmetic of T and values of the variables
+pub type Evaluator<'a, T> = dyn Fn(&dyn AstNode, &Variables<T>) -> Result<T, Error> + 'a;
+
+/// Evaluates node, a call of a function binding a variable like
+/// sum(i^2, i, 1, 10), with evaluate, the evaluator of the arithmetic of
+/// T. The arguments after the variable are evaluated once, and the first
+/// argument for every value the function gives the variable, which hides
+/// a variable of the same name in variables. integrate and solve require
+/// types convertible to and from f64.
+pub fn evaluate_binding<T: Numeric>(
+    node: &dyn AstNode,
+    function: IFunctions,
+    variables: &Variables<T>,
+    evaluate: &Evaluator<'_, T>,
+) -> Result<T, Error> {
+    let children = node.children();
+    let name = children[1].token().lexeme.unwrap();
+    let arguments = children[2..]
+        .iter()
+        .map(|child| evaluate(child.as_ref(), variables))
+        .collect::<Result<Vec<T>, Error>>()?;
+    let mut scope = variables.clone();
+    let mut body = |value: T| {
+        scope.insert(name.clone(), value);
+        evaluate(children[0].as_ref(), &scope)
+    };
+
+    let result = match function {
+        IFunctions::Sum | IFunctions::Prod => {
+            accumulate(function, &mut body, &arguments[0], &arguments[1])
+        }
+        _ => {
+            let reals = arguments
+                .iter()
+                .map(|argument| argument.to_real().ok_or_else(|| not_real(function)))
+                .collect::<Result<Vec<f64>, Error>>()?;
+            let mut real_body = |x: f64| {
+                body(T::from_real(x).ok_or_else(|| not_real(function))?)?
+                    .to_real()
+                    .ok_or_else(|| not_real(function))
+            };
+            let result = match function {
+                IFunctions::Integrate => integrate(&mut real_body, reals[0], reals[1]),
+                _ => solve(&mut real_body, reals[0]),
+            };
+
+            result.and_then(|result| T::from_real(result).ok_or_else(|| not_real(function)))
+        }
+    };
+
+    result.map_err(|error| with_span(error, node.token().span))
+}
+
+/// Adds or, for prod, multiplies the values of body for from, from + 1
+/// and so on up to to. Empty ranges give 0 and 1. The total starts from
+/// the first value, so it keeps the context of the values of body.
+fn accumulate<T: Numeric>(
+    function: IFunctions,
+    body: &mut dyn FnMut(T) -> Result<T, Error>,
+    from: &T,
+    to: &T,
+) -> Result<T, Error> {
+    let one = T::from_literal("1")?;
+    let mut total: Option<T> = None;
+    let mut index = from.clone();
+    let mut terms = 0;
+
+    if from.compare(to).is_none() {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("The bounds of {} must be ordered", function.name()),
+        ));
+    }
+
+    while matches!(index.compare(to), Some(Ordering::Less | Ordering::Equal)) {
+        if terms == MAX_TERMS {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{} has more than {} terms", function.name(), MAX_TERMS),
+            ));
+        }
+
+        let term = body(index.clone())?;
+
+        total = Some(match (function, total) {
+            (_, None) => term,
+            (IFunctions::Sum, Some(total)) => total.add(&term)?,
+            (_, Some(total)) => total.mul(&term)?,
+        });
+        index = index.add(&one)?;
+        terms += 1;
+    }
+
+    match (function, total) {
+        (_, Some(total)) => Ok(total),
+        (IFunctions::Sum, None) => T::from_literal("0"),
+        (_, None) => Ok(one),
+    }
+}
+
+/// Integrates f from a to b with adaptive Simpson quadrature
+fn integrate(f: &mut dyn FnMut(f64) -> Result<f64, Error>, a: f64, b: f64) -> Result<f64, Error> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "integrate requires finite bounds",
+        ));
+    }
+
+    let m = (a + b) / 2.0;
+    let (fa, fm, fb) = (f(a)?, f(m)?, f(b)?);
+    let whole = simpson(a, b, fa, fm, fb);
+    let tolerance = TOLERANCE * whole.abs().max(1.0);
+    let mut evaluations = 3;
+    let integral = adaptive_simpson(
+        f,
+        [a, b],
+        [fa, fm, fb],
+        whole,
+        tolerance,
+        MAX_DEPTH,
+        &mut evaluations,
+    )?;
+
+    match integral.is_finite() {
+        true => Ok(integral),
+        false => Err(not_converged(IFunctions::Integrate)),
+    }
+}
+
+/// Integrates f over bounds given its values at the bounds and their
+/// midpoint and the Simpson estimate whole, halving the interval until
+/// the halves agree with whole up to tolerance
+fn adaptive_simpson(
+    f: &mut dyn FnMut(f64) -> Result<f64, Error>,
+    [a, b]: [f64; 2],
+    [fa, fm, fb]: [f64; 3],
+    whole: f64,
+    tolerance: f64,
+    depth: u32,
+    evaluations: &mut usize,
+) -> Result<f64, Error> {
+    let m = (a + b) / 2.0;
+    let (left_m, right_m) = ((a + m) / 2.0, (m + b) / 2.0);
+
+    *evaluations += 2;
+
+    if *evaluations > MAX_EVALUATIONS {
+        return Err(not_converged(IFunctions::Integrate));
+    }
+
+    let (f_left, f_right) = (f(left_m)?, f(right_m)?);
+    let left = simpson(a, m, fa, f_left, fm);
+    let right = simpson(m, b, fm, f_right, fb);
+    let delta = left + right - whole;
+
+    if !delta.is_finite() {
+        return Err(not_converged(IFunctions::Integrate));
+    }
+
+    if depth == 0 || delta.abs() <= 15.0 * tolerance {
+        return Ok(left + right + delta / 15.0);
+    }
+
+    Ok(adaptive_simpson(
+        f,
+        [a, m],
+        [fa, f_left, fm],
+        left,
+        tolerance / 2.0,
+        depth - 1,
+        evaluations,
+    )? + adaptive_simpson(
+        f,
+        [m, b],
+        [fm, f_right, fb],
+        right,
+        tolerance / 2.0,
+        depth - 1,
+        evaluations,
+    )?)
+}
+
+fn simpson(a: f64, b: f64, fa: f64, fm: f64, fb: f64) -> f64 {
+    (b - a) / 6.0 * (fa + 4.0 * fm + fb)
+}
+
+/// Finds a root of f with Newton's method starting at guess, with the
+/// slope estimated by central differences. When it does not converge
+/// the root is found by bisection of an interval around guess over
+/// which f changes sign. Points where f is not close to 0, like the
+/// poles bisection also finds sign changes at, are rejected.
+fn solve(f: &mut dyn FnMut(f64) -> Result<f64, Error>, guess: f64) -> Result<f64, Error> {
+    let root = find_root(f, guess)?;
+
+    match f(root)?.abs() <= RESIDUAL * (1.0 + f(guess)?.abs()) {
+        true => Ok(root),
+        false => Err(no_root(guess)),
+    }
+}
+
+fn find_root(f: &mut dyn FnMut(f64) -> Result<f64, Error>, guess: f64) -> Result<f64, Error> {
+    let mut x = guess;
+
+    for _ in 0..MAX_ITERATIONS {
+        let fx = f(x)?;
+
+        if fx == 0.0 {
+            return Ok(x);
+        }
+
+        let h = 1e-7 * (1.0 + x.abs());
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        let next = x - fx / slope;
+
+        if !next.is_finite() {
+            break;
+        }
+
+        if (next - x).abs() <= 1e-12 * (1.0 + x.abs()) {
+            return Ok(next);
+        }
+
+        x = next;
+    }
+
+    let (a, b) = bracket(f, guess)?;
+
+    bisect(f, a, b)
+}
+
+/// Finds an interval with guess at one end over which f changes sign,
+/// doubling its width until it does
+fn bracket(f: &mut dyn FnMut(f64) -> Result<f64, Error>, guess: f64) -> Result<(f64, f64), Error> {
+    let at_guess = f(guess)?;
+    let mut step = 0.1 * (1.0 + guess.abs());
+
+    for _ in 0..MAX_ITERATIONS {
+        for end in [guess - step, guess + step] {
+            if f(end)?.signum() * at_guess.signum() < 0.0 {
+                return Ok((guess.min(end), guess.max(end)));
+            }
+        }
+
+        step *= 2.0;
+    }
+
+    Err(no_root(guess))
+}
+
+/// Halves the interval [a, b] over which f changes sign until it is as
+/// narrow as f64 allows
+fn bisect(
+    f: &mut dyn FnMut(f64) -> Result<f64, Error>,
+    mut a: f64,
+    mut b: f64,
+) -> Result<f64, Error> {
+    let mut fa = f(a)?;
+
+    for _ in 0..MAX_BISECTIONS {
+        let m = (a + b) / 2.0;
+
+        if m <= a || m >= b {
+            break;
+        }
+
+        let fm = f(m)?;
+
+        if fm == 0.0 {
+            return Ok(m);
+        }
+
+        match fm.signum() == fa.signum() {
+            true => (a, fa) = (m, fm),
+            false => b = m,
+        }
+    }
+
+    Ok((a + b) / 2.0)
+}
+
+fn not_real(function: IFunctions) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("{} requires real floating point numbers", function.name()),
+    )
+}
+
+fn no_root(guess: f64) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("solve found no root near {}", guess),
+    )
+}
+
+fn not_converged(function: IFunctions) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("{} did not converge", function.name()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numeric::evaluate_numeric;
+
+    fn evaluate<T: Numeric>(expr: &str) -> Result<T, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr)?;
+
+        evaluate_numeric(parser.ast(), &Variables::new())
+    }
+
+    #[test]
+    fn sum_and_prod_bind_their_variable() {
+        assert_eq!(385.0, evaluate::<f64>("sum(i^2, i, 1, 10)").unwrap());
+        assert_eq!(120, evaluate::<i64>("prod(k, k, 1, 5)").unwrap());
+        assert_eq!(0.0, evaluate::<f64>("sum(i, i, 5, 1)").unwrap());
+        assert_eq!(
+            30.0,
+            evaluate::<f64>("sum(sum(i * j, j, 1, 2), i, 1, 4)").unwrap()
+        );
+        assert!(evaluate::<f64>("sum(i, i, 1, 1 / 0)").is_err());
+        // imaginary numbers like 2i read as 2 * i while i is bound
+        assert_eq!(12.0, evaluate::<f64>("sum(2i, i, 1, 3)").unwrap());
+        assert_eq!(4.5, evaluate::<f64>("prod(1.5i, i, 1, 2)").unwrap());
+    }
+
+    #[test]
+    fn integrate_and_solve_approximate_numerically() {
+        let integral = evaluate::<f64>("integrate(x^2, x, 0, 3)").unwrap();
+        let area = evaluate::<f64>("integrate(sin(t), t, 0, pi)").unwrap();
+        let root = evaluate::<f64>("solve(x^2 - 2, x, 1)").unwrap();
+        let kink = evaluate::<f64>("solve(abs(x) - 1, x, 0)").unwrap();
+
+        assert!((integral - 9.0).abs() < 1e-9);
+        assert!((area - 2.0).abs() < 1e-9);
+        assert!((root - 2f64.sqrt()).abs() < 1e-12);
+        assert!((kink.abs() - 1.0).abs() < 1e-12);
+        assert!(evaluate::<f64>("solve(x^2 + 1, x, 0)").is_err());
+        assert_eq!(
+            "solve found no root near 1 at 0..5",
+            evaluate::<f64>("solve(1/x, x, 1)").unwrap_err().to_string()
+        );
+        assert!(
+            (evaluate::<f64>("solve(10000000000 * (x - 1), x, 0)").unwrap() - 1.0).abs() < 1e-12
+        );
+        assert!(evaluate::<i64>("integrate(x, x, 0, 1)").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::{apply_in, evaluate_numeric_in};
+use crate::span::{with_span, Span};
+use crate::tokenizer::tokens::{IAngleUnit, IFunctions, IToken, Token};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// An IEEE 754 exception floating point evaluation can raise. Only the
+/// operation producing the special value raises it, not the operations
+/// the value propagates through.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Exception {
+    /// A division by zero, including 0 / 0, or zero raised to a negative power
+    DivisionByZero,
+    /// A function or ^ applied outside its real domain, like sqrt(-1) or
+    /// (-8) ^ (1/3)
+    Domain,
+    /// Any other operation producing NaN, like inf - inf
+    Nan,
+    /// A finite operation overflowing to an infinite result, like 10 ^ 400
+    Infinity,
+}
+
+/// What to do when an exception is raised
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Action {
+    /// Continue with the special value as IEEE 754 does
+    #[default]
+    Allow,
+    /// Fail with an error of kind Unsupported carrying the span of the
+    /// operation
+    Error,
+    /// Continue with the special value and report a warning
+    Warn,
+}
+
+/// The action to take for each exception. The default allows every
+/// exception like plain f64 evaluation does.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct IeeePolicy {
+    pub division_by_zero: Action,
+    pub domain: Action,
+    pub nan: Action,
+    pub infinity: Action,
+}
+
+/// An exception raised under Action::Warn
+#[derive(Debug, Clone, PartialEq)]
+pub struct IeeeWarning {
+    pub exception: Exception,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+/// The result of evaluate_ieee
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub value: f64,
+    pub warnings: Vec<IeeeWarning>,
+}
+
+impl Exception {
+    pub const ALL: [Exception; 4] = [
+        Exception::DivisionByZero,
+        Exception::Domain,
+        Exception::Nan,
+        Exception::Infinity,
+    ];
+
+    /// The name of the exception in policies and JSON output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Exception::DivisionByZero => "division-by-zero",
+            Exception::Domain => "domain",
+            Exception::Nan => "nan",
+            Exception::Infinity => "infinity",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "allow" => Ok(Action::Allow),
+            "error" => Ok(Action::Error),
+            "warn" => Ok(Action::Warn),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown action: {}", action),
+            )),
+        }
+    }
+}
+
+impl IeeePolicy {
+    /// The policy taking the same action for every exception
+    pub fn uniform(action: Action) -> Self {
+        IeeePolicy {
+            division_by_zero: action,
+            domain: action,
+            nan: action,
+            infinity: action,
+        }
+    }
+
+    pub fn action(&self, exception: Exception) -> Action {
+        match exception {
+            Exception::DivisionByZero => self.division_by_zero,
+            Exception::Domain => self.domain,
+            Exception::Nan => self.nan,
+            Exception::Infinity => self.infinity,
+        }
+    }
+
+    fn action_mut(&mut self, exception: Exception) -> &mut Action {
+        match exception {
+            Exception::DivisionByZero => &mut self.division_by_zero,
+            Exception::Domain => &mut self.domain,
+            Exception::Nan => &mut self.nan,
+            Exception::Infinity => &mut self.infinity,
+        }
+    }
+}
+
+impl FromStr for IeeePolicy {
+    type Err = Error;
+
+    /// Parses a comma separated list of actions. An action on its own
+    /// applies to every exception and exception=action to one, so
+    /// "warn,division-by-zero=error" warns for all exceptions but
+    /// division by zero.
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        let mut parsed = IeeePolicy::default();
+
+        for item in policy.split(',').map(str::trim) {
+            match item.split_once('=') {
+                Some((name, action)) => {
+                    let exception = Exception::ALL
+                        .into_iter()
+                        .find(|exception| exception.name() == name.trim())
+                        .ok_or_else(|| {
+                            Error::new(
+                                ErrorKind::InvalidInput,
+                                format!("Unknown exception: {}", name),
+                            )
+                        })?;
+
+                    *parsed.action_mut(exception) = action.trim().parse()?;
+                }
+                None => parsed = IeeePolicy::uniform(item.parse()?),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for IeeeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at {}", self.message, span),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Evaluates the tree rooted at node with f64 arithmetic and angles in
+/// angle_unit, taking the action of policy for the exceptions the
+/// operations raise
+pub fn evaluate_ieee(
+    node: &dyn AstNode,
+    variables: &Variables,
+    policy: &IeeePolicy,
+    angle_unit: IAngleUnit,
+) -> Result<Evaluation, Error> {
+    let mut warnings = vec![];
+    let value = evaluate(node, variables, policy, angle_unit, &mut warnings)?;
+
+    Ok(Evaluation { value, warnings })
+}
+
+fn evaluate(
+    node: &dyn AstNode,
+    variables: &Variables,
+    policy: &IeeePolicy,
+    angle_unit: IAngleUnit,
+    warnings: &mut Vec<IeeeWarning>,
+) -> Result<f64, Error> {
+    let token_type = node.token_type();
+    let span = node.token().span;
+
+    // the first argument is evaluated by the function for values of the
+    // variable it binds, without its exceptions being classified
+    if matches!(token_type, IToken::Fun(function) if function.binds_variable()) {
+        return evaluate_numeric_in(node, variables, angle_unit);
+    }
+
+    let operands = node
+        .children()
+        .iter()
+        .map(|child| evaluate(child.as_ref(), variables, policy, angle_unit, warnings))
+        .collect::<Result<Vec<f64>, Error>>()?;
+    let value = match operands.is_empty() {
+        true => evaluate_numeric_in(node, variables, angle_unit),
+        false => apply_in(token_type, &operands, angle_unit),
+    }
+    .map_err(|error| with_span(error, span))?;
+
+    if let Some((exception, message)) = classify(&node.token(), &operands, value) {
+        match policy.action(exception) {
+            Action::Allow => (),
+            Action::Error => {
+                return Err(with_span(Error::new(ErrorKind::Unsupported, message), span))
+            }
+            Action::Warn => warnings.push(IeeeWarning {
+                exception,
+                message,
+                span,
+            }),
+        }
+    }
+
+    Ok(value)
+}
+
+/// Returns the exception raised by the operation of token producing
+/// value from operands, if any. Operations on NaN only propagate it and
+/// operations on infinities only raise exceptions for NaN results.
+fn classify(token: &Token, operands: &[f64], value: f64) -> Option<(Exception, String)> {
+    let token_type = token.token_type;
+
+    // variables and constants hold values rather than produce them
+    if operands.iter().any(|operand| operand.is_nan())
+        || (operands.is_empty() && token_type != IToken::Num)
+    {
+        return None;
+    }
+
+    let operand = |value: &f64| match value.is_sign_negative() {
+        true => format!("({})", value),
+        false => value.to_string(),
+    };
+    let describe = || match (token_type, operands) {
+        (IToken::Fun(function), _) => format!(
+            "{}({})",
+            function.name(),
+            operands
+                .iter()
+                .map(|operand| operand.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        (IToken::Neg, [arg_1]) => format!("-{}", operand(arg_1)),
+        (_, [arg_1, arg_2]) => format!("{} {} {}", operand(arg_1), token, operand(arg_2)),
+        _ => token.lexeme.clone().unwrap_or_else(|| token.to_string()),
+    };
+    let finite = operands.iter().all(|operand| operand.is_finite());
+
+    match (token_type, operands) {
+        (IToken::Div, [dividend, divisor]) if dividend.is_finite() && *divisor == 0.0 => Some((
+            Exception::DivisionByZero,
+            format!("Division by zero in {}", describe()),
+        )),
+        (IToken::Pow, [base, exponent]) if *base == 0.0 && *exponent < 0.0 => Some((
+            Exception::DivisionByZero,
+            format!("Division by zero in {}", describe()),
+        )),
+        (IToken::Pow | IToken::Fun(IFunctions::Sqrt | IFunctions::Asin | IFunctions::Acos), _)
+            if value.is_nan() =>
+        {
+            Some((
+                Exception::Domain,
+                format!("{} is outside the real domain", describe()),
+            ))
+        }
+        _ if value.is_nan() => Some((Exception::Nan, format!("{} is NaN", describe()))),
+        _ if finite && value.is_infinite() => Some((
+            Exception::Infinity,
+            format!("{} overflows to {}", describe(), value),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::span_of;
+
+    fn evaluate(expr: &str, policy: &str) -> Result<Evaluation, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        evaluate_ieee(
+            parser.ast(),
+            &Variables::new(),
+            &policy.parse().unwrap(),
+            IAngleUnit::Radians,
+        )
+    }
+
+    #[test]
+    fn allow_keeps_special_values() {
+        assert_eq!(f64::INFINITY, evaluate("1 / 0", "allow").unwrap().value);
+        assert!(evaluate("(0 - 8) ^ (1 / 3)", "allow")
+            .unwrap()
+            .value
+            .is_nan());
+    }
+
+    #[test]
+    fn error_reports_operation_producing_special_value() {
+        let error = evaluate("2 + 1 / 0 * 3", "error").unwrap_err();
+
+        assert_eq!("Division by zero in 1 / 0 at 6..7", error.to_string());
+        assert_eq!(Some(Span::new(6, 7)), span_of(&error));
+
+        for (expr, message) in [
+            ("0 / 0", "Division by zero in 0 / 0 at 2..3"),
+            (
+                "(0 - 8) ^ 0.5",
+                "(-8) ^ 0.5 is outside the real domain at 8..9",
+            ),
+            ("sqrt(0 - 1)", "sqrt(-1) is outside the real domain at 0..4"),
+            ("10 ^ 400", "10 ^ 400 overflows to inf at 3..4"),
+            (
+                &"9".repeat(400),
+                &format!("{} overflows to inf at 0..400", "9".repeat(400)),
+            ),
+            ("0 ^ -1", "Division by zero in 0 ^ (-1) at 2..3"),
+        ] {
+            assert_eq!(message, evaluate(expr, "error").unwrap_err().to_string());
+        }
+    }
+
+    #[test]
+    fn warn_reports_only_where_special_values_originate() {
+        let evaluation = evaluate("1 / 0 - 1 / 0", "warn,division-by-zero=allow").unwrap();
+
+        assert!(evaluation.value.is_nan());
+        assert_eq!(1, evaluation.warnings.len());
+        assert_eq!(Exception::Nan, evaluation.warnings[0].exception);
+        assert_eq!(Some(Span::new(6, 7)), evaluation.warnings[0].span);
+    }
+
+    #[test]
+    fn policy_parses_actions_per_exception() {
+        assert_eq!(
+            IeeePolicy {
+                division_by_zero: Action::Error,
+                domain: Action::Warn,
+                nan: Action::Warn,
+                infinity: Action::Allow,
+            },
+            "warn, division-by-zero=error, infinity=allow"
+                .parse()
+                .unwrap()
+        );
+        assert!("nan=ignore".parse::<IeeePolicy>().is_err());
+        assert!("overflow=error".parse::<IeeePolicy>().is_err());
+    }
+}
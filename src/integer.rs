@@ -0,0 +1,169 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::higher_order::evaluate_binding;
+use crate::numeric::{apply, evaluate_numeric, Numeric};
+use crate::span::with_span;
+use crate::tokenizer::tokens::IToken;
+use num_traits::{CheckedDiv, CheckedRem, PrimInt, Signed};
+use std::fmt::Display;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// What / does when the quotient of two integers is not an integer
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Division {
+    /// Fail with an error of kind Unsupported
+    #[default]
+    Error,
+    /// Round the quotient down, so 7 / 2 is 3 and -7 / 2 is -4
+    Floor,
+}
+
+impl FromStr for Division {
+    type Err = Error;
+
+    fn from_str(division: &str) -> Result<Self, Self::Err> {
+        match division {
+            "error" => Ok(Division::Error),
+            "floor" => Ok(Division::Floor),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown division behaviour: {}", division),
+            )),
+        }
+    }
+}
+
+/// Evaluates the tree rooted at node with checked arithmetic of the
+/// integer type T, like i64 or i128. Literals with a decimal point, pi
+/// and overflows are errors carrying the span of the token they were
+/// raised at, which can be read with span::span_of.
+pub fn evaluate_integer<T>(
+    node: &dyn AstNode,
+    variables: &Variables<T>,
+    division: Division,
+) -> Result<T, Error>
+where
+    T: Numeric + PrimInt + Signed + CheckedDiv + CheckedRem + Display,
+{
+    let token_type = node.token_type();
+    let result = match token_type {
+        IToken::Div if division == Division::Floor => {
+            let operands = evaluate_operands(node, variables, division)?;
+
+            floor_div(operands[0], operands[1])
+        }
+        IToken::Fun(function) if function.binds_variable() => {
+            evaluate_binding(node, function, variables, &|node, variables| {
+                evaluate_integer(node, variables, division)
+            })
+        }
+        _ if node.children().is_empty() => evaluate_numeric(node, variables),
+        _ => apply(token_type, &evaluate_operands(node, variables, division)?),
+    };
+
+    result.map_err(|error| with_span(error, node.token().span))
+}
+
+fn evaluate_operands<T>(
+    node: &dyn AstNode,
+    variables: &Variables<T>,
+    division: Division,
+) -> Result<Vec<T>, Error>
+where
+    T: Numeric + PrimInt + Signed + CheckedDiv + CheckedRem + Display,
+{
+    node.children()
+        .iter()
+        .map(|child| evaluate_integer(child.as_ref(), variables, division))
+        .collect()
+}
+
+/// Divides rounding the quotient towards negative infinity
+fn floor_div<T>(dividend: T, divisor: T) -> Result<T, Error>
+where
+    T: PrimInt + Signed + CheckedDiv + CheckedRem + Display,
+{
+    if divisor.is_zero() {
+        return Err(Error::new(ErrorKind::Unsupported, "Division by zero"));
+    }
+
+    let overflow = || {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("Integer overflow in {} / {}", dividend, divisor),
+        )
+    };
+    let quotient = dividend.checked_div(&divisor).ok_or_else(overflow)?;
+    let remainder = dividend.checked_rem(&divisor).ok_or_else(overflow)?;
+
+    if !remainder.is_zero() && remainder.is_negative() != divisor.is_negative() {
+        Ok(quotient - T::one())
+    } else {
+        Ok(quotient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{span_of, Span};
+
+    fn evaluate<T>(expr: &str, division: Division) -> Result<T, Error>
+    where
+        T: Numeric + PrimInt + Signed + CheckedDiv + CheckedRem + Display,
+    {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        evaluate_integer(parser.ast(), &Variables::new(), division)
+    }
+
+    #[test]
+    fn division_errors_or_floors_when_inexact() {
+        assert_eq!(4, evaluate::<i64>("8 / 2", Division::Error).unwrap());
+        assert_eq!(
+            ErrorKind::Unsupported,
+            evaluate::<i64>("7 / 2", Division::Error)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(3, evaluate::<i64>("7 / 2", Division::Floor).unwrap());
+        assert_eq!(-4, evaluate::<i64>("-7 / 2", Division::Floor).unwrap());
+        assert_eq!(-4, evaluate::<i64>("7 / -2", Division::Floor).unwrap());
+        assert_eq!(3, evaluate::<i64>("-7 / -2", Division::Floor).unwrap());
+        assert_eq!(
+            55,
+            evaluate::<i64>("sum(i, i, 1, 10)", Division::Error).unwrap()
+        );
+        assert_eq!(
+            2,
+            evaluate::<i64>("sum(k / 2, k, 1, 3)", Division::Floor).unwrap()
+        );
+    }
+
+    #[test]
+    fn overflow_reports_span_of_operator() {
+        let error = evaluate::<i64>("1 + 2 ^ 62 * 2", Division::Error).unwrap_err();
+
+        assert_eq!(
+            "Integer overflow in 4611686018427387904 * 2 at 11..12",
+            error.to_string()
+        );
+        assert_eq!(Some(Span::new(11, 12)), span_of(&error));
+        assert_eq!(
+            2_i128.pow(63),
+            evaluate::<i128>("2 ^ 62 * 2", Division::Error).unwrap()
+        );
+    }
+
+    #[test]
+    fn only_integer_literals_are_accepted() {
+        let error = evaluate::<i64>("2 * 2.5", Division::Floor).unwrap_err();
+
+        assert_eq!(Some(Span::new(4, 7)), span_of(&error));
+        assert!(evaluate::<i64>("2 * pi", Division::Floor).is_err());
+        assert_eq!(
+            Some(Span::new(0, 20)),
+            span_of(&evaluate::<i64>("99999999999999999999", Division::Error).unwrap_err())
+        );
+    }
+}
@@ -0,0 +1,620 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::{evaluate_numeric, unsupported_function, Numeric};
+use crate::rational::parse_decimal;
+use crate::tokenizer::tokens::{IConstants, IFunctions};
+use num_rational::BigRational;
+use std::cmp::Ordering;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// A closed interval of reals [lo, hi] with f64 bounds. The operations
+/// round their bounds outward, so the result of an evaluation contains
+/// the exact result for every combination of values of its inputs.
+///
+/// Bounds computed by +, -, *, / and sqrt are only widened when the f64
+/// operation was inexact. Those computed by exp and fractional powers
+/// are always widened by one unit in the last place.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    /// Returns the interval [lo, hi], failing when lo is greater than hi
+    /// or either bound is NaN
+    pub fn new(lo: f64, hi: f64) -> Result<Self, Error> {
+        if lo <= hi {
+            Ok(Interval { lo, hi })
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid interval: [{}, {}]", lo, hi),
+            ))
+        }
+    }
+
+    /// Returns the interval holding only value
+    pub fn point(value: f64) -> Self {
+        Interval {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    /// Returns the smallest interval with the bounds, failing when a
+    /// bound is NaN which happens for operations like 0 * inf
+    fn hull(bounds: &[f64]) -> Result<Self, Error> {
+        if bounds.iter().any(|bound| bound.is_nan()) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "The operation has no interval result",
+            ));
+        }
+
+        Ok(Interval {
+            lo: bounds.iter().copied().fold(f64::INFINITY, f64::min),
+            hi: bounds.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+
+    /// Raises the interval to an integer power by multiplying its bounds
+    fn powi(&self, exponent: i64) -> Result<Self, Error> {
+        if exponent == 0 {
+            return Ok(Interval::point(1.0));
+        }
+
+        if exponent < 0 {
+            return Interval::point(1.0).div(&self.powi(-exponent)?);
+        }
+
+        let lo = power(self.lo, exponent as u64, Round::Down);
+        let hi = power(self.hi, exponent as u64, Round::Up);
+
+        if exponent % 2 == 1 || self.lo >= 0.0 {
+            Interval::new(lo, hi)
+        } else if self.hi <= 0.0 {
+            Interval::new(
+                power(self.hi, exponent as u64, Round::Down),
+                power(self.lo, exponent as u64, Round::Up),
+            )
+        } else {
+            Interval::new(0.0, power(self.lo, exponent as u64, Round::Up).max(hi))
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl FromStr for Interval {
+    type Err = Error;
+
+    /// Parses an interval written as [lo, hi] or a single number. Bounds
+    /// which are not exact in f64, like 9.9, are rounded outward.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let bounds = match text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            Some(bounds) => bounds,
+            None => return Interval::from_literal(text),
+        };
+        let (lo, hi) = bounds.split_once(',').ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid interval: {}", text),
+            )
+        })?;
+
+        Interval::new(
+            Interval::from_literal(lo.trim())?.lo,
+            Interval::from_literal(hi.trim())?.hi,
+        )
+    }
+}
+
+impl Numeric for Interval {
+    /// Returns the literal as a point when f64 represents it exactly and
+    /// otherwise as the f64 nearest to it and its neighbour on the other side
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        let value: f64 = text
+            .parse()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Invalid number: {}", text)))?;
+        let exact = parse_decimal(text);
+
+        Ok(
+            match exact.map(|exact| BigRational::from_float(value).map(|value| value.cmp(&exact))) {
+                Some(Some(Ordering::Equal)) => Interval::point(value),
+                Some(Some(Ordering::Greater)) => Interval {
+                    lo: value.next_down(),
+                    hi: value,
+                },
+                Some(Some(Ordering::Less)) => Interval {
+                    lo: value,
+                    hi: value.next_up(),
+                },
+                _ => Interval {
+                    lo: value.next_down(),
+                    hi: value.next_up(),
+                },
+            },
+        )
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        // consts::PI is the f64 just below pi
+        match constant {
+            IConstants::Pi => Ok(Interval {
+                lo: PI,
+                hi: PI.next_up(),
+            }),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        Interval::hull(&[
+            add(self.lo, other.lo, Round::Down),
+            add(self.hi, other.hi, Round::Up),
+        ])
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        Interval::hull(&[
+            add(self.lo, -other.hi, Round::Down),
+            add(self.hi, -other.lo, Round::Up),
+        ])
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        let mut bounds = Vec::with_capacity(8);
+
+        for a in [self.lo, self.hi] {
+            for b in [other.lo, other.hi] {
+                bounds.push(mul(a, b, Round::Down));
+                bounds.push(mul(a, b, Round::Up));
+            }
+        }
+
+        Interval::hull(&bounds)
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        if other.contains(0.0) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Division by an interval containing zero: {}", other),
+            ));
+        }
+
+        let mut bounds = Vec::with_capacity(8);
+
+        for a in [self.lo, self.hi] {
+            for b in [other.lo, other.hi] {
+                bounds.push(div(a, b, Round::Down));
+                bounds.push(div(a, b, Round::Up));
+            }
+        }
+
+        Interval::hull(&bounds)
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        if exponent.lo == exponent.hi
+            && exponent.lo.fract() == 0.0
+            && exponent.lo.abs() <= i32::MAX as f64
+        {
+            return self.powi(exponent.lo as i64);
+        }
+
+        // x ^ y is monotonic in x and in y for positive x, so its bounds
+        // are at the corners
+        if self.lo < 0.0 || (self.lo == 0.0 && exponent.lo <= 0.0) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{} ^ {} has no real interval value", self, exponent),
+            ));
+        }
+
+        let mut bounds = Vec::with_capacity(4);
+
+        for base in [self.lo, self.hi] {
+            for power in [exponent.lo, exponent.hi] {
+                bounds.push(base.powf(power));
+            }
+        }
+
+        let corners = Interval::hull(&bounds)?;
+
+        Ok(Interval {
+            lo: corners.lo.next_down().max(0.0),
+            hi: corners.hi.next_up(),
+        })
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        if self.hi < other.lo {
+            Some(Ordering::Less)
+        } else if self.lo > other.hi {
+            Some(Ordering::Greater)
+        } else if self.lo == self.hi && self == other {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        Ok(Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        })
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = arguments[0];
+
+        match function {
+            IFunctions::Max => Ok(Interval {
+                lo: argument.lo.max(arguments[1].lo),
+                hi: argument.hi.max(arguments[1].hi),
+            }),
+            IFunctions::Min => Ok(Interval {
+                lo: argument.lo.min(arguments[1].lo),
+                hi: argument.hi.min(arguments[1].hi),
+            }),
+            IFunctions::Sqrt if argument.lo < 0.0 => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "sqrt is not defined for the negative values of {}",
+                    argument
+                ),
+            )),
+            IFunctions::Sqrt => Ok(Interval {
+                lo: sqrt(argument.lo, Round::Down),
+                hi: sqrt(argument.hi, Round::Up),
+            }),
+            IFunctions::Abs if argument.lo >= 0.0 => Ok(argument),
+            IFunctions::Abs if argument.hi <= 0.0 => argument.neg(),
+            IFunctions::Abs => Ok(Interval {
+                lo: 0.0,
+                hi: argument.hi.max(-argument.lo),
+            }),
+            IFunctions::Arg if argument.lo >= 0.0 => Ok(Interval::point(0.0)),
+            IFunctions::Arg if argument.hi < 0.0 => Interval::constant(IConstants::Pi),
+            IFunctions::Arg => Ok(Interval {
+                lo: 0.0,
+                hi: PI.next_up(),
+            }),
+            IFunctions::Re | IFunctions::Conj => Ok(argument),
+            IFunctions::Im => Ok(Interval::point(0.0)),
+            IFunctions::Exp => Ok(Interval {
+                lo: argument.lo.exp().next_down().max(0.0),
+                hi: argument.hi.exp().next_up(),
+            }),
+            // sin is largest at pi / 2 and smallest at -pi / 2 modulo 2 pi
+            IFunctions::Sin => Ok(periodic(argument, f64::sin, FRAC_PI_2, -FRAC_PI_2)),
+            IFunctions::Cos => Ok(periodic(argument, f64::cos, 0.0, PI)),
+            IFunctions::Tan
+                if argument.width() >= PI || contains_period(argument, FRAC_PI_2, PI) =>
+            {
+                Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("tan is not defined at the poles within {}", argument),
+                ))
+            }
+            IFunctions::Tan => Ok(Interval {
+                lo: argument.lo.tan().next_down(),
+                hi: argument.hi.tan().next_up(),
+            }),
+            IFunctions::Asin | IFunctions::Acos if argument.lo < -1.0 || argument.hi > 1.0 => {
+                Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "{} is only defined from -1 to 1, not for all of {}",
+                        function.name(),
+                        argument
+                    ),
+                ))
+            }
+            IFunctions::Asin => Ok(Interval {
+                lo: argument.lo.asin().next_down().max(-FRAC_PI_2.next_up()),
+                hi: argument.hi.asin().next_up().min(FRAC_PI_2.next_up()),
+            }),
+            IFunctions::Acos => Ok(Interval {
+                lo: argument.hi.acos().next_down().max(0.0),
+                hi: argument.lo.acos().next_up().min(PI.next_up()),
+            }),
+            IFunctions::Atan => Ok(Interval {
+                lo: argument.lo.atan().next_down().max(-FRAC_PI_2.next_up()),
+                hi: argument.hi.atan().next_up().min(FRAC_PI_2.next_up()),
+            }),
+            IFunctions::Date
+            | IFunctions::Duration
+            | IFunctions::DaysBetween
+            | IFunctions::Integrate
+            | IFunctions::Solve
+            | IFunctions::Sum
+            | IFunctions::Prod => Err(unsupported_function(function)),
+        }
+    }
+}
+
+/// Encloses the values of sin or cos, given as function, over interval,
+/// which are largest at maximum and smallest at minimum modulo 2 pi
+fn periodic(interval: Interval, function: fn(f64) -> f64, maximum: f64, minimum: f64) -> Interval {
+    if interval.width() >= 2.0 * PI {
+        return Interval { lo: -1.0, hi: 1.0 };
+    }
+
+    let (at_lo, at_hi) = (function(interval.lo), function(interval.hi));
+    let lo = match contains_period(interval, minimum, 2.0 * PI) {
+        true => -1.0,
+        false => at_lo.min(at_hi).next_down().max(-1.0),
+    };
+    let hi = match contains_period(interval, maximum, 2.0 * PI) {
+        true => 1.0,
+        false => at_lo.max(at_hi).next_up().min(1.0),
+    };
+
+    Interval { lo, hi }
+}
+
+/// Whether interval contains offset + k * period for some integer k. The
+/// interval is widened slightly first, since the multiples of pi are
+/// rounded, so points just outside it may be reported as inside, which
+/// only makes enclosures wider.
+fn contains_period(interval: Interval, offset: f64, period: f64) -> bool {
+    let slack = 1e-12 * (1.0 + interval.lo.abs().max(interval.hi.abs()));
+    let k = ((interval.lo - slack - offset) / period).ceil();
+
+    offset + k * period <= interval.hi + slack
+}
+
+/// Evaluates the tree rooted at node with interval arithmetic, looking
+/// up the intervals of variables in variables
+pub fn evaluate_interval(
+    node: &dyn AstNode,
+    variables: &Variables<Interval>,
+) -> Result<Interval, Error> {
+    evaluate_numeric(node, variables)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Round {
+    Down,
+    Up,
+}
+
+/// Moves an f64 result which differs from the exact result by error
+/// to the side of the exact result given by round
+fn round(result: f64, error: f64, round: Round) -> f64 {
+    match round {
+        Round::Down if error < 0.0 => result.next_down(),
+        Round::Up if error > 0.0 => result.next_up(),
+        _ => result,
+    }
+}
+
+/// Returns whether the error of an f64 operation cannot be computed
+/// exactly, which is when it overflowed or may have lost subnormal digits
+fn is_extreme(result: f64) -> bool {
+    !result.is_finite() || result.abs() < f64::MIN_POSITIVE
+}
+
+/// Widens a result whose error is unknown by one unit in the last place
+fn widen(result: f64, direction: Round) -> f64 {
+    match direction {
+        Round::Down => result.next_down(),
+        Round::Up => result.next_up(),
+    }
+}
+
+fn add(a: f64, b: f64, direction: Round) -> f64 {
+    let sum = a + b;
+
+    if !sum.is_finite() {
+        return widen(sum, direction);
+    }
+
+    // the error of the sum, exact by the TwoSum algorithm
+    let b_part = sum - a;
+    let error = (a - (sum - b_part)) + (b - b_part);
+
+    round(sum, error, direction)
+}
+
+fn mul(a: f64, b: f64, direction: Round) -> f64 {
+    let product = a * b;
+
+    if a == 0.0 || b == 0.0 {
+        return product;
+    }
+
+    if is_extreme(product) {
+        return widen(product, direction);
+    }
+
+    round(product, a.mul_add(b, -product), direction)
+}
+
+fn div(a: f64, b: f64, direction: Round) -> f64 {
+    let quotient = a / b;
+
+    if a == 0.0 {
+        return quotient;
+    }
+
+    if is_extreme(quotient) {
+        return widen(quotient, direction);
+    }
+
+    // a / b - quotient has the sign of the remainder divided by b
+    let remainder = -quotient.mul_add(b, -a);
+
+    round(quotient, remainder * b.signum(), direction)
+}
+
+fn sqrt(value: f64, direction: Round) -> f64 {
+    let root = value.sqrt();
+
+    if value == 0.0 || value.is_infinite() {
+        return root;
+    }
+
+    if is_extreme(value) {
+        return widen(root, direction).max(0.0);
+    }
+
+    round(root, -root.mul_add(root, -value), direction)
+}
+
+/// Raises base to a non-negative integer power by squaring, rounding
+/// every product in the direction which bounds the exact power
+fn power(base: f64, exponent: u64, direction: Round) -> f64 {
+    if base < 0.0 {
+        let magnitude = match direction {
+            Round::Down if exponent % 2 == 1 => power(-base, exponent, Round::Up),
+            Round::Up if exponent % 2 == 1 => power(-base, exponent, Round::Down),
+            _ => return power(-base, exponent, direction),
+        };
+
+        return -magnitude;
+    }
+
+    let mut result = 1.0;
+    let mut square = base;
+    let mut exponent = exponent;
+
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = mul(result, square, direction);
+        }
+
+        exponent /= 2;
+
+        if exponent > 0 {
+            square = mul(square, square, direction);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(expr: &str, variables: &[(&str, &str)]) -> Result<Interval, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+        let variables = variables
+            .iter()
+            .map(|(name, interval)| (name.to_string(), interval.parse().unwrap()))
+            .collect();
+
+        evaluate_interval(parser.ast(), &variables)
+    }
+
+    fn contains_exactly(interval: Interval, numer: i64, denom: i64) -> bool {
+        let exact = BigRational::new(numer.into(), denom.into());
+
+        BigRational::from_float(interval.lo()).unwrap() <= exact
+            && exact <= BigRational::from_float(interval.hi()).unwrap()
+    }
+
+    #[test]
+    fn exact_operations_keep_points() {
+        assert_eq!(Interval::point(7.0), evaluate("1 + 2 * 3", &[]).unwrap());
+        assert_eq!(Interval::point(0.25), evaluate("1 / 2 ^ 2", &[]).unwrap());
+        assert_eq!(Interval::point(3.0), evaluate("sqrt(9)", &[]).unwrap());
+    }
+
+    #[test]
+    fn inexact_operations_round_outward() {
+        let sum = evaluate("0.1 + 0.2", &[]).unwrap();
+        let third = evaluate("1 / 3", &[]).unwrap();
+
+        assert!(contains_exactly(sum, 3, 10));
+        assert!(contains_exactly(third, 1, 3));
+        assert!(third.width() > 0.0);
+        assert!(evaluate("pi", &[]).unwrap().contains(std::f64::consts::PI));
+    }
+
+    #[test]
+    fn evaluate_with_interval_bindings() {
+        let area = evaluate("x * y", &[("x", "[9.9, 10.1]"), ("y", "[2, 3]")]).unwrap();
+
+        assert!(contains_exactly(area, 198, 10));
+        assert!(contains_exactly(area, 303, 10));
+        assert!(area.lo() > 19.7 && area.hi() < 30.4);
+        assert_eq!(
+            Interval::new(0.0, 4.0).unwrap(),
+            evaluate("x ^ 2", &[("x", "[-1, 2]")]).unwrap()
+        );
+        assert_eq!(
+            Interval::new(-1.0, 8.0).unwrap(),
+            evaluate("x ^ 3", &[("x", "[-1, 2]")]).unwrap()
+        );
+        assert_eq!(
+            Interval::new(2.0, 3.0).unwrap(),
+            evaluate("max(x, y)", &[("x", "[-1, 2]"), ("y", "[2, 3]")]).unwrap()
+        );
+        assert_eq!(
+            Interval::new(-1.0, 2.0).unwrap(),
+            evaluate("min(x, y)", &[("x", "[-1, 2]"), ("y", "[2, 3]")]).unwrap()
+        );
+    }
+
+    #[test]
+    fn trigonometric_functions_enclose_extrema() {
+        let sin = evaluate("sin(x)", &[("x", "[1, 2]")]).unwrap();
+        let cos = evaluate("cos(x)", &[("x", "[-0.5, 0.5]")]).unwrap();
+        let tan = evaluate("tan(x)", &[("x", "[-1, 1]")]).unwrap();
+
+        assert_eq!(1.0, sin.hi());
+        assert!(sin.lo() <= 1_f64.sin() && sin.lo() > 0.84);
+        assert_eq!(1.0, cos.hi());
+        assert!(cos.lo() <= 0.5_f64.cos());
+        assert!(tan.lo() <= -(1_f64.tan()) && tan.hi() >= 1_f64.tan());
+        assert_eq!(
+            Interval::new(-1.0, 1.0).unwrap(),
+            evaluate("sin(x)", &[("x", "[0, 7]")]).unwrap()
+        );
+        assert!(evaluate("asin(1)", &[]).unwrap().contains(FRAC_PI_2));
+    }
+
+    #[test]
+    fn operations_without_interval_result_fail() {
+        for expr in ["1 / x", "sqrt(x)", "x ^ 0.5", "tan(x * 2)", "asin(x * 2)"] {
+            assert_eq!(
+                ErrorKind::Unsupported,
+                evaluate(expr, &[("x", "[-1, 1]")]).unwrap_err().kind()
+            );
+        }
+    }
+
+    #[test]
+    fn from_str_parses_intervals_and_numbers() {
+        let interval: Interval = "[9.9, 10.1]".parse().unwrap();
+
+        assert!(interval.lo() < 9.9 && interval.hi() > 10.1);
+        assert_eq!(Interval::point(2.5), "2.5".parse().unwrap());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            "[2, 1]".parse::<Interval>().unwrap_err().kind()
+        );
+    }
+}
@@ -1,10 +1,38 @@
+use crate::ast::dot::to_dot;
+use crate::ast::nodes::Variables;
+use crate::modular::evaluate_modular;
 use crate::shunting_yard_parser::parse::ShuntingYardParser;
+use crate::shunting_yard_parser::trace::render_table;
 use crate::tokenizer::tokenize::Tokenizer;
+use num_bigint::BigInt;
+use std::io::Error;
 use std::process;
 
 pub mod ast;
+pub mod batch;
+pub mod calculus;
+pub mod complex;
+pub mod csv;
+pub mod currency;
+pub mod datetime;
+pub mod decimal;
+pub mod dual;
+pub mod format;
+pub mod higher_order;
+pub mod ieee;
+pub mod integer;
+pub mod interval;
+pub mod linear;
+pub mod modular;
+pub mod numeric;
+pub mod polynomial;
+pub mod rational;
+pub mod repl;
 pub mod shunting_yard_parser;
+pub mod simplify;
+pub mod span;
 pub mod tokenizer;
+pub mod units;
 
 /// Evaluates the string slice representing the expression using the
 /// ShuntingYardParser type from the shunting_yard_parser module
@@ -17,3 +45,41 @@ pub fn evaluate_expression_shunting_yard(expr: &str) -> f64 {
 
     ShuntingYardParser::build(tokens).evaluate()
 }
+
+/// Tokenizes and parses the expression returning the ShuntingYardParser
+/// holding its AST or the first error encountered
+pub fn parse_expression_shunting_yard(expr: &str) -> Result<ShuntingYardParser, Error> {
+    ShuntingYardParser::try_build(&mut Tokenizer::build(expr).into_tokens()?)
+}
+
+/// Evaluates the expression with arithmetic modulo modulus, returning
+/// the residue from 0 to modulus - 1 or the first error encountered
+pub fn evaluate_mod(expr: &str, modulus: impl Into<BigInt>) -> Result<BigInt, Error> {
+    let parser = parse_expression_shunting_yard(expr)?;
+
+    evaluate_modular(parser.evaluable()?, &Variables::new(), &modulus.into())
+}
+
+/// Renders the AST the ShuntingYardParser builds for the expression
+/// as a Graphviz DOT graph
+pub fn dot_graph_shunting_yard(expr: &str) -> String {
+    let mut binding = Tokenizer::build(expr);
+    let tokens = binding.tokens().as_mut().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    to_dot(ShuntingYardParser::build(tokens).ast())
+}
+
+/// Parses the expression while tracing the ShuntingYardParser and
+/// renders every step of the algorithm as a table
+pub fn explain_expression_shunting_yard(expr: &str) -> String {
+    let mut binding = Tokenizer::build(expr);
+    let tokens = binding.tokens().as_mut().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    render_table(ShuntingYardParser::build_traced(tokens).trace())
+}
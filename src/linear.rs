@@ -0,0 +1,272 @@
+use crate::ast::infix::to_infix;
+use crate::ast::nodes::AstNode;
+use crate::polynomial::Polynomial;
+use crate::rational::RationalValue;
+use crate::span::with_span;
+use crate::tokenizer::tokens::IToken;
+use num_rational::BigRational;
+use num_traits::Zero;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+/// The solutions of a system of linear equations
+#[derive(Debug, Clone, PartialEq)]
+pub enum Solution {
+    /// The only value of every unknown, the unknowns in alphabetical order
+    Unique(Vec<(String, BigRational)>),
+    /// Infinitely many solutions: the unknowns which can take any value,
+    /// and every other unknown as a polynomial of degree one in them
+    Infinite {
+        free: Vec<String>,
+        dependent: Vec<(String, Polynomial)>,
+    },
+    /// The equations contradict each other
+    Inconsistent,
+}
+
+impl fmt::Display for Solution {
+    /// Writes one line per unknown, like x = 6, followed by the free
+    /// unknowns of infinitely many solutions, or no solution
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = match self {
+            Solution::Unique(values) => values
+                .iter()
+                .map(|(name, value)| format!("{} = {}", name, RationalValue::Exact(value.clone())))
+                .collect(),
+            Solution::Infinite { free, dependent } => dependent
+                .iter()
+                .map(|(name, value)| format!("{} = {}", name, to_infix(value.to_ast().as_ref())))
+                .chain(free.iter().map(|name| format!("{} is free", name)))
+                .collect(),
+            Solution::Inconsistent => vec![String::from("no solution")],
+        };
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Solves the equation or system of equations separated by semicolons
+/// rooted at node, like x + y = 10; x - y = 2, by Gauss-Jordan
+/// elimination with exact rational arithmetic. Every side must be a
+/// polynomial of degree at most one in the variables, the unknowns.
+/// Errors carry the span of the equation they were raised at.
+pub fn solve_linear(node: &dyn AstNode) -> Result<Solution, Error> {
+    let equations = match node.token_type() {
+        IToken::Semicolon => node
+            .children()
+            .iter()
+            .map(|equation| read_equation(equation.as_ref()))
+            .collect::<Result<Vec<Polynomial>, Error>>()?,
+        _ => vec![read_equation(node)?],
+    };
+    let mut unknowns = vec![];
+
+    add_unknowns(node, &mut unknowns);
+    unknowns.sort();
+    unknowns.dedup();
+
+    if unknowns.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The equations have no unknowns",
+        ));
+    }
+
+    // one row per equation, the coefficients of the unknowns followed by
+    // the constant moved to the right hand side
+    let mut matrix: Vec<Vec<BigRational>> = equations
+        .iter()
+        .map(|equation| {
+            let mut row = vec![BigRational::zero(); unknowns.len() + 1];
+
+            for (monomial, coefficient) in equation.terms() {
+                match monomial.first() {
+                    Some((name, _)) => {
+                        row[unknowns.binary_search(name).unwrap()] = coefficient.clone()
+                    }
+                    None => row[unknowns.len()] = -coefficient,
+                }
+            }
+
+            row
+        })
+        .collect();
+    let pivots = eliminate(&mut matrix, unknowns.len());
+
+    if matrix[pivots.len()..]
+        .iter()
+        .any(|row| !row[unknowns.len()].is_zero())
+    {
+        return Ok(Solution::Inconsistent);
+    }
+
+    if pivots.len() == unknowns.len() {
+        return Ok(Solution::Unique(
+            unknowns
+                .into_iter()
+                .zip(matrix)
+                .map(|(name, row)| (name, row[row.len() - 1].clone()))
+                .collect(),
+        ));
+    }
+
+    let free: Vec<usize> = (0..unknowns.len())
+        .filter(|column| !pivots.contains(column))
+        .collect();
+    let dependent = pivots
+        .iter()
+        .zip(&matrix)
+        .map(|(pivot, row)| {
+            let value = free.iter().fold(
+                Polynomial::constant(row[unknowns.len()].clone()),
+                |value, column| {
+                    value.sub(&Polynomial::variable(&unknowns[*column]).scale(&row[*column]))
+                },
+            );
+
+            (unknowns[*pivot].clone(), value)
+        })
+        .collect();
+
+    Ok(Solution::Infinite {
+        free: free
+            .into_iter()
+            .map(|column| unknowns[column].clone())
+            .collect(),
+        dependent,
+    })
+}
+
+/// Reads the equation rooted at node as the polynomial of its left hand
+/// side minus its right hand side, which must be of degree one at most
+fn read_equation(node: &dyn AstNode) -> Result<Polynomial, Error> {
+    let token = node.token();
+
+    if token.token_type != IToken::Eq {
+        return Err(with_span(
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Expected an equation with =, like 2*x + 3 = 11",
+            ),
+            token.span,
+        ));
+    }
+
+    let children = node.children();
+    let equation = Polynomial::from_ast(children[0].as_ref())?
+        .sub(&Polynomial::from_ast(children[1].as_ref())?);
+    let linear = equation
+        .terms()
+        .into_iter()
+        .all(|(monomial, _)| monomial.len() < 2 && monomial.iter().all(|(_, power)| *power == 1));
+
+    match linear {
+        true => Ok(equation),
+        false => Err(with_span(
+            Error::new(ErrorKind::Unsupported, "The equation is not linear"),
+            token.span,
+        )),
+    }
+}
+
+/// Adds the names of the variables in the tree rooted at node to names,
+/// including those which cancel out like the x of x = x + 1
+fn add_unknowns(node: &dyn AstNode, names: &mut Vec<String>) {
+    match node.token_type() {
+        IToken::Var => names.push(node.token().lexeme.unwrap()),
+        _ => {
+            for child in node.children() {
+                add_unknowns(child.as_ref(), names);
+            }
+        }
+    }
+}
+
+/// Brings matrix, whose last column is the right hand side of the
+/// equations, into reduced row echelon form and returns the columns of
+/// the pivots of its leading rows
+fn eliminate(matrix: &mut [Vec<BigRational>], unknowns: usize) -> Vec<usize> {
+    let mut pivots = vec![];
+
+    for column in 0..unknowns {
+        let row = pivots.len();
+        let found = match (row..matrix.len()).find(|found| !matrix[*found][column].is_zero()) {
+            Some(found) => found,
+            None => continue,
+        };
+
+        matrix.swap(row, found);
+
+        let pivot = matrix[row][column].clone();
+
+        for value in matrix[row].iter_mut() {
+            *value = &*value / &pivot;
+        }
+
+        let pivot_row = matrix[row].clone();
+
+        for (other, values) in matrix.iter_mut().enumerate() {
+            let factor = values[column].clone();
+
+            if other == row || factor.is_zero() {
+                continue;
+            }
+
+            for (value, pivot_value) in values.iter_mut().zip(&pivot_row) {
+                *value -= pivot_value * &factor;
+            }
+        }
+
+        pivots.push(column);
+    }
+
+    pivots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{span_of, Span};
+
+    fn solve(expr: &str) -> Result<String, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        solve_linear(parser.ast()).map(|solution| solution.to_string())
+    }
+
+    #[test]
+    fn solve_linear_finds_unique_solutions() {
+        assert_eq!("x = 4", solve("2x + 3 = 11").unwrap());
+        assert_eq!("x = 6\ny = 4", solve("x + y = 10; x - y = 2").unwrap());
+        assert_eq!(
+            "a = 1/3\nb = -0.5",
+            solve("3*a = 1; 2*(b + 1) = 1; a + b = 1/3 - 1/2").unwrap()
+        );
+    }
+
+    #[test]
+    fn solve_linear_reports_infinite_and_no_solutions() {
+        assert_eq!(
+            "x = -y + 10\ny is free",
+            solve("x + y = 10; 2*x + 2*y = 20").unwrap()
+        );
+        assert_eq!(
+            "x = -2*y - 3*z + 1\ny is free\nz is free",
+            solve("x + 2y + 3z = 1").unwrap()
+        );
+        assert_eq!("no solution", solve("x + y = 10; x + y = 2").unwrap());
+        assert_eq!("no solution", solve("x = x + 1").unwrap());
+        assert_eq!("x is free", solve("2*x = x + x").unwrap());
+    }
+
+    #[test]
+    fn solve_linear_fails_for_non_linear_equations() {
+        let error = solve("x + y = 1; x*y = 2").unwrap_err();
+
+        assert_eq!("The equation is not linear at 15..16", error.to_string());
+        assert_eq!(Some(Span::new(15, 16)), span_of(&error));
+        assert!(solve("x^2 = 4").is_err());
+        assert!(solve("x + 1").is_err());
+        assert!(solve("1 = 1").is_err());
+    }
+}
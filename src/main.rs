@@ -1,28 +1,1093 @@
-use shunting_parser::evaluate_expression_shunting_yard;
+use num_bigint::BigInt;
+use num_traits::{One, Signed};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use shunting_parser::ast::dot::to_dot;
+use shunting_parser::ast::infix::to_infix;
+use shunting_parser::ast::json;
+use shunting_parser::ast::nodes::Variables;
+use shunting_parser::ast::rpn::to_rpn;
+use shunting_parser::ast::tree::to_tree;
+use shunting_parser::batch::evaluate_lines;
+use shunting_parser::calculus::differentiate;
+use shunting_parser::complex::Complex64;
+use shunting_parser::csv::{evaluate_records, write_record};
+use shunting_parser::currency::{has_currency, RateTable};
+use shunting_parser::datetime::{has_dates, Temporal};
+use shunting_parser::decimal::{DecimalContext, Rounding};
+use shunting_parser::format::{FormatOptions, Notation, Precision};
+use shunting_parser::ieee::{IeeePolicy, IeeeWarning};
+use shunting_parser::integer::Division;
+use shunting_parser::linear::{solve_linear, Solution};
+use shunting_parser::parse_expression_shunting_yard;
+use shunting_parser::polynomial::{collect, expand};
+use shunting_parser::rational::{Inexact, RationalValue};
+use shunting_parser::repl::{Reply, Session};
+use shunting_parser::shunting_yard_parser::parse::ShuntingYardParser;
+use shunting_parser::shunting_yard_parser::trace::render_table;
+use shunting_parser::simplify::{simplify_with, Rules};
+use shunting_parser::span::span_of;
+use shunting_parser::tokenizer::tokenize::Tokenizer;
+use shunting_parser::tokenizer::tokens::IAngleUnit;
+use shunting_parser::units::has_units;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+use std::path::PathBuf;
 use std::{env, error::Error, process};
 
-pub mod ast;
-pub mod shunting_yard_parser;
-pub mod tokenizer;
+const EVAL_OPTION: &str = "eval";
+const TOKENS_OPTION: &str = "tokens";
+const AST_OPTION: &str = "ast";
+const RPN_OPTION: &str = "rpn";
+const CHECK_OPTION: &str = "check";
+const DOT_OPTION: &str = "dot";
+const EXPLAIN_OPTION: &str = "explain";
+const DIFF_OPTION: &str = "diff";
+const SIMPLIFY_OPTION: &str = "simplify";
+const EXPAND_OPTION: &str = "expand";
+const COLLECT_OPTION: &str = "collect";
+const SOLVE_OPTION: &str = "solve";
+const REPL_OPTION: &str = "repl";
+const BATCH_OPTION: &str = "batch";
+const CSV_OPTION: &str = "csv";
+const FORMAT_FLAG: &str = "--format";
+const FILE_FLAG: &str = "--file";
+const EXPR_FLAG: &str = "--expr";
+const OUT_FLAG: &str = "--out";
+const DECIMALS_FLAG: &str = "--decimals";
+const SIGNIFICANT_FLAG: &str = "--significant";
+const NOTATION_FLAG: &str = "--notation";
+const THOUSANDS_FLAG: &str = "--thousands";
+const MAX_DENOMINATOR_FLAG: &str = "--max-denominator";
+const ARITHMETIC_FLAG: &str = "--arithmetic";
+const INEXACT_FLAG: &str = "--inexact";
+const PRECISION_FLAG: &str = "--precision";
+const ROUNDING_FLAG: &str = "--rounding";
+const BITS_FLAG: &str = "--bits";
+const DIVISION_FLAG: &str = "--division";
+const MODULUS_FLAG: &str = "--modulus";
+const IEEE_FLAG: &str = "--ieee";
+const ANGLE_FLAG: &str = "--angle";
+const RATES_FLAG: &str = "--rates";
+const VAR_FLAG: &str = "--var";
+const RULES_FLAG: &str = "--rules";
+/// The variable diff differentiates with respect to and collect groups
+/// the powers of when --var is not given
+const DEFAULT_VARIABLE: &str = "x";
+/// The name of the computed column when --out is not given
+const DEFAULT_OUT_COLUMN: &str = "result";
+const HISTORY_FILE: &str = ".shunting_parser_history";
 
-const PARSER_OPTION: &str = "eval";
+/// Options printing values which can be written with the number format flags
+const NUMBER_OPTIONS: [&str; 4] = [EVAL_OPTION, BATCH_OPTION, CSV_OPTION, REPL_OPTION];
+
+/// Options taking an expression which can also be written as JSON
+const FORMATTED_OPTIONS: [&str; 10] = [
+    EVAL_OPTION,
+    TOKENS_OPTION,
+    AST_OPTION,
+    RPN_OPTION,
+    CHECK_OPTION,
+    DIFF_OPTION,
+    SIMPLIFY_OPTION,
+    EXPAND_OPTION,
+    COLLECT_OPTION,
+    SOLVE_OPTION,
+];
+
+const USAGE: &str = "Usage: <cargo run>|<./executable> eval|tokens|ast|rpn|check [--format text|json] \"<expression>\"
+       <cargo run>|<./executable> dot|explain \"<expression>\"
+       <cargo run>|<./executable> diff|collect [--format text|json] [--var <name>] \"<expression>\"
+       <cargo run>|<./executable> simplify [--format text|json] [--rules <rule sets>] \"<expression>\"
+       <cargo run>|<./executable> expand [--format text|json] \"<expression>\"
+       <cargo run>|<./executable> solve [--format text|json] \"<equation>[; <equation>...]\"
+       <cargo run>|<./executable> batch [--format text|json] [--file <path>]
+       <cargo run>|<./executable> csv <path> --expr \"<expression>\" [--out <column>]
+       <cargo run>|<./executable> repl
+
+Values printed by eval, batch, csv and repl are written as set by
+       --notation plain|scientific|engineering|fraction
+       --decimals <digits> | --significant <digits>
+       --thousands <separator>
+       --max-denominator <denominator>
+
+eval computes with floating point, exact fractions, decimals, complex numbers, integers or integers modulo a number as set by
+       --arithmetic float|rational|decimal|complex|integer|modular
+       --ieee allow|error|warn[,<exception>=<action>...] for float, the exceptions
+              being division-by-zero, domain, nan and infinity
+       --angle rad|deg|grad for float and complex, the unit of the arguments of
+              sin, cos and tan and of the results of asin, acos, atan and arg
+       --rates <path> for float, the exchange rates amounts of money like 100 USD
+              are converted with, one <from> <to> <rate> line per rate
+       --inexact error|float for rational
+       --precision <digits> and --rounding <mode> for decimal
+       --bits 64|128 and --division error|floor for integer
+       --modulus <modulus> for modular
+
+simplify applies every rule set or those given with
+       --rules fold|identities|like-terms|order[,...]";
+
+/// Exit code for invalid command line arguments
+const USAGE_EXIT_CODE: i32 = 2;
+/// Exit code for a batch or CSV in which at least one line or row failed
+const PARTIAL_FAILURE_EXIT_CODE: i32 = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// The numbers the eval option computes with
+#[derive(Debug, Clone, PartialEq)]
+enum Arithmetic {
+    /// f64 taking the actions of the policy for IEEE 754 exceptions
+    Float(IeeePolicy),
+    Rational(Inexact),
+    Decimal(DecimalContext),
+    Complex,
+    /// Checked integers of 64 or 128 bits
+    Integer {
+        bits: u32,
+        division: Division,
+    },
+    /// Integers modulo the modulus
+    Modular(BigInt),
+}
+
+struct Config<'a> {
+    option: &'a str,
+    format: Format,
+    expr: Option<&'a str>,
+    file: Option<&'a str>,
+    out: Option<&'a str>,
+    number_format: FormatOptions,
+    arithmetic: Arithmetic,
+    /// The unit of angles in float and complex arithmetic
+    angle_unit: IAngleUnit,
+    /// The file of exchange rates for amounts of money
+    rates: Option<&'a str>,
+    /// The variable diff differentiates with respect to and collect
+    /// groups the powers of
+    variable: &'a str,
+    /// The rule sets simplify applies
+    rules: Rules,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    let expr = parse_config(&args).unwrap_or_else(|error| {
+    let config = parse_config(&args)
+        .and_then(|config| check_special_values(&config).map(|_| config))
+        .unwrap_or_else(|error| {
+            eprintln!("{}", USAGE);
+            eprintln!("{}", error);
+            process::exit(USAGE_EXIT_CODE);
+        });
+
+    let result = match config.option {
+        REPL_OPTION => {
+            return run_repl(&config.number_format).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                process::exit(1);
+            })
+        }
+        BATCH_OPTION => run_batch(&config),
+        CSV_OPTION => run_csv(&config),
+        _ => return run_expression(&config),
+    };
+
+    if let Err(error) = result {
         eprintln!("{}", error);
-        process::exit(1);
-    });
+        process::exit(exit_code(&error));
+    }
+}
+
+/// Rejects --angle and --ieee for expressions with units, dates or
+/// amounts of money, whose evaluation would ignore them. Expressions
+/// which do not parse are left for the command to report.
+fn check_special_values(config: &Config) -> Result<(), Box<dyn Error>> {
+    let flags_given = match config.arithmetic {
+        Arithmetic::Float(policy) => {
+            policy != IeeePolicy::default() || config.angle_unit != IAngleUnit::Radians
+        }
+        _ => false,
+    };
 
-    println!("> {}", evaluate_expression_shunting_yard(expr));
+    if config.option != EVAL_OPTION || !flags_given {
+        return Ok(());
+    }
+
+    match parse_expression_shunting_yard(config.expr.unwrap()) {
+        Ok(parser)
+            if has_units(parser.ast())
+                || has_dates(parser.ast())
+                || has_currency(parser.ast()) =>
+        {
+            Err("Error: --angle and --ieee are not supported with units, dates or amounts of money.".into())
+        }
+        _ => Ok(()),
+    }
 }
 
-fn parse_config(args: &Vec<String>) -> Result<&str, Box<dyn Error>> {
-    if args.len() != 3 || args[1] != PARSER_OPTION {
-        eprintln!("Usage: <cargo run>|<./executable> eval \"<expression to be evaluated>\"");
+fn parse_config(args: &[String]) -> Result<Config<'_>, Box<dyn Error>> {
+    let mut format = None;
+    let mut file = None;
+    let mut expr = None;
+    let mut out = None;
+    let mut number_format = FormatOptions::default();
+    let mut number_format_given = false;
+    let mut arithmetic = None;
+    let mut inexact = None;
+    let mut precision = None;
+    let mut rounding = None;
+    let mut bits = None;
+    let mut division = None;
+    let mut modulus = None;
+    let mut ieee = None;
+    let mut angle_unit = None;
+    let mut rates = None;
+    let mut variable = None;
+    let mut rules = None;
+    let mut positional = vec![];
+    let mut rest = args.iter().skip(1);
+
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            FORMAT_FLAG => {
+                format = match rest.next().map(|value| value.as_str()) {
+                    Some("text") => Some(Format::Text),
+                    Some("json") => Some(Format::Json),
+                    _ => return Err("Error: --format takes text or json.".into()),
+                };
+            }
+            FILE_FLAG => {
+                file = Some(rest.next().ok_or("Error: --file takes a path.")?.as_str());
+            }
+            EXPR_FLAG => {
+                expr = Some(
+                    rest.next()
+                        .ok_or("Error: --expr takes an expression.")?
+                        .as_str(),
+                );
+            }
+            OUT_FLAG => {
+                out = Some(
+                    rest.next()
+                        .ok_or("Error: --out takes a column name.")?
+                        .as_str(),
+                );
+            }
+            DECIMALS_FLAG | SIGNIFICANT_FLAG => {
+                let digits = rest
+                    .next()
+                    .and_then(|digits| digits.parse().ok())
+                    .ok_or(format!("Error: {} takes a number of digits.", arg))?;
+
+                number_format.precision = Some(if arg == DECIMALS_FLAG {
+                    Precision::Decimals(digits)
+                } else {
+                    Precision::Significant(digits)
+                });
+                number_format_given = true;
+            }
+            NOTATION_FLAG => {
+                number_format.notation = rest
+                    .next()
+                    .ok_or("Error: --notation takes a notation.")?
+                    .parse()?;
+                number_format_given = true;
+            }
+            THOUSANDS_FLAG => {
+                let mut separator = rest.next().map(|separator| separator.chars());
+
+                number_format.thousands_separator =
+                    match separator.as_mut().map(|chars| (chars.next(), chars.next())) {
+                        Some((Some(separator), None)) => Some(separator),
+                        _ => return Err("Error: --thousands takes a single character.".into()),
+                    };
+                number_format_given = true;
+            }
+            MAX_DENOMINATOR_FLAG => {
+                number_format.max_denominator = rest
+                    .next()
+                    .and_then(|denominator| denominator.parse().ok())
+                    .filter(|denominator| *denominator > 0)
+                    .ok_or("Error: --max-denominator takes a positive number.")?;
+                number_format_given = true;
+            }
+            ARITHMETIC_FLAG => {
+                arithmetic = match rest.next().map(|value| value.as_str()) {
+                    Some("float") => Some(Arithmetic::Float(IeeePolicy::default())),
+                    Some("rational") => Some(Arithmetic::Rational(Inexact::default())),
+                    Some("decimal") => Some(Arithmetic::Decimal(DecimalContext::default())),
+                    Some("complex") => Some(Arithmetic::Complex),
+                    Some("integer") => Some(Arithmetic::Integer {
+                        bits: 64,
+                        division: Division::default(),
+                    }),
+                    Some("modular") => Some(Arithmetic::Modular(BigInt::one())),
+                    _ => {
+                        return Err("Error: --arithmetic takes float, rational, decimal, complex, integer or modular.".into())
+                    }
+                };
+            }
+            INEXACT_FLAG => {
+                inexact = Some(
+                    rest.next()
+                        .ok_or("Error: --inexact takes error or float.")?
+                        .parse::<Inexact>()?,
+                );
+            }
+            PRECISION_FLAG => {
+                precision = Some(
+                    rest.next()
+                        .and_then(|digits| digits.parse::<u32>().ok())
+                        .filter(|digits| *digits > 0)
+                        .ok_or("Error: --precision takes a positive number of digits.")?,
+                );
+            }
+            ROUNDING_FLAG => {
+                rounding = Some(
+                    rest.next()
+                        .ok_or("Error: --rounding takes a rounding mode.")?
+                        .parse::<Rounding>()?,
+                );
+            }
+            BITS_FLAG => {
+                bits = match rest.next().map(|value| value.as_str()) {
+                    Some("64") => Some(64),
+                    Some("128") => Some(128),
+                    _ => return Err("Error: --bits takes 64 or 128.".into()),
+                };
+            }
+            IEEE_FLAG => {
+                ieee = Some(
+                    rest.next()
+                        .ok_or("Error: --ieee takes a policy like error or warn,nan=allow.")?
+                        .parse::<IeeePolicy>()?,
+                );
+            }
+            ANGLE_FLAG => {
+                angle_unit = Some(
+                    rest.next()
+                        .and_then(|unit| IAngleUnit::from_suffix(unit))
+                        .ok_or("Error: --angle takes rad, deg or grad.")?,
+                );
+            }
+            RATES_FLAG => {
+                rates = Some(
+                    rest.next()
+                        .ok_or("Error: --rates takes the path of a rate table.")?
+                        .as_str(),
+                );
+            }
+            VAR_FLAG => {
+                variable = Some(
+                    rest.next()
+                        .ok_or("Error: --var takes the name of a variable.")?
+                        .as_str(),
+                );
+            }
+            RULES_FLAG => {
+                rules = Some(
+                    rest.next()
+                        .ok_or("Error: --rules takes a list of rule sets like fold,identities.")?
+                        .parse::<Rules>()?,
+                );
+            }
+            MODULUS_FLAG => {
+                modulus = Some(
+                    rest.next()
+                        .and_then(|modulus| modulus.parse::<BigInt>().ok())
+                        .filter(|modulus| modulus.is_positive())
+                        .ok_or("Error: --modulus takes a positive integer.")?,
+                );
+            }
+            DIVISION_FLAG => {
+                division = Some(
+                    rest.next()
+                        .ok_or("Error: --division takes error or floor.")?
+                        .parse::<Division>()?,
+                );
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let option = match positional.as_slice() {
+        [option @ (REPL_OPTION | BATCH_OPTION)] => *option,
+        [CSV_OPTION, path] if file.is_none() => {
+            file = Some(*path);
+            CSV_OPTION
+        }
+        [option, argument] if expr.is_none() => {
+            expr = Some(*argument);
+            *option
+        }
+        _ => return Err("Error: Invalid option provided.".into()),
+    };
+
+    if expr.is_some()
+        && !FORMATTED_OPTIONS.contains(&option)
+        && ![DOT_OPTION, EXPLAIN_OPTION, CSV_OPTION].contains(&option)
+    {
         return Err("Error: Invalid option provided.".into());
     }
 
-    Ok(&args[2])
+    if option == CSV_OPTION && expr.is_none() {
+        return Err("Error: csv requires --expr.".into());
+    }
+
+    if out.is_some() && option != CSV_OPTION {
+        return Err(format!("Error: {} does not support --out.", option).into());
+    }
+
+    if format.is_some() && !FORMATTED_OPTIONS.contains(&option) && option != BATCH_OPTION {
+        return Err(format!("Error: {} does not support --format.", option).into());
+    }
+
+    if number_format_given && !NUMBER_OPTIONS.contains(&option) {
+        return Err(format!("Error: {} does not print values to format.", option).into());
+    }
+
+    if arithmetic.is_some() && option != EVAL_OPTION {
+        return Err(format!("Error: {} does not support --arithmetic.", option).into());
+    }
+
+    let arithmetic = match arithmetic.unwrap_or(Arithmetic::Float(IeeePolicy::default())) {
+        Arithmetic::Float(default) => Arithmetic::Float(ieee.unwrap_or(default)),
+        Arithmetic::Rational(default) => Arithmetic::Rational(inexact.unwrap_or(default)),
+        Arithmetic::Decimal(default) => Arithmetic::Decimal(DecimalContext {
+            precision: precision.unwrap_or(default.precision),
+            rounding: rounding.unwrap_or(default.rounding),
+        }),
+        Arithmetic::Integer {
+            bits: default_bits,
+            division: default_division,
+        } => Arithmetic::Integer {
+            bits: bits.unwrap_or(default_bits),
+            division: division.unwrap_or(default_division),
+        },
+        Arithmetic::Modular(_) => Arithmetic::Modular(
+            modulus
+                .clone()
+                .ok_or("Error: --arithmetic modular requires --modulus.")?,
+        ),
+        arithmetic => arithmetic,
+    };
+
+    if inexact.is_some() && !matches!(arithmetic, Arithmetic::Rational(_)) {
+        return Err("Error: --inexact requires --arithmetic rational.".into());
+    }
+
+    if (precision.is_some() || rounding.is_some()) && !matches!(arithmetic, Arithmetic::Decimal(_))
+    {
+        return Err("Error: --precision and --rounding require --arithmetic decimal.".into());
+    }
+
+    if (bits.is_some() || division.is_some()) && !matches!(arithmetic, Arithmetic::Integer { .. }) {
+        return Err("Error: --bits and --division require --arithmetic integer.".into());
+    }
+
+    if ieee.is_some() && (option != EVAL_OPTION || !matches!(arithmetic, Arithmetic::Float(_))) {
+        return Err("Error: --ieee requires eval with float arithmetic.".into());
+    }
+
+    if angle_unit.is_some()
+        && (option != EVAL_OPTION
+            || !matches!(arithmetic, Arithmetic::Float(_) | Arithmetic::Complex))
+    {
+        return Err("Error: --angle requires eval with float or complex arithmetic.".into());
+    }
+
+    if rates.is_some() && (option != EVAL_OPTION || !matches!(arithmetic, Arithmetic::Float(_))) {
+        return Err("Error: --rates requires eval with float arithmetic.".into());
+    }
+
+    if variable.is_some() && option != DIFF_OPTION && option != COLLECT_OPTION {
+        return Err(format!("Error: {} does not support --var.", option).into());
+    }
+
+    if rules.is_some() && option != SIMPLIFY_OPTION {
+        return Err(format!("Error: {} does not support --rules.", option).into());
+    }
+
+    if modulus.is_some() && !matches!(arithmetic, Arithmetic::Modular(_)) {
+        return Err("Error: --modulus requires --arithmetic modular.".into());
+    }
+
+    if file.is_some() && option != BATCH_OPTION && option != CSV_OPTION {
+        return Err(format!("Error: {} does not support --file.", option).into());
+    }
+
+    Ok(Config {
+        option,
+        format: format.unwrap_or(Format::Text),
+        expr,
+        file,
+        out,
+        number_format,
+        arithmetic,
+        angle_unit: angle_unit.unwrap_or_default(),
+        rates,
+        variable: variable.unwrap_or(DEFAULT_VARIABLE),
+        rules: rules.unwrap_or_default(),
+    })
+}
+
+/// Runs an option taking a single expression and exits with the code
+/// for the category of the error when it fails
+fn run_expression(config: &Config) {
+    match run_command(config) {
+        Ok(output) => println!("{}", output.trim_end()),
+        Err(error) => {
+            match config.format {
+                Format::Text => {
+                    eprintln!("{}", error);
+
+                    if let Some(span) = span_of(&error) {
+                        eprintln!("{}\n{}", config.expr.unwrap(), span.underline());
+                    }
+                }
+                Format::Json => println!("{{\"error\":{}}}", error_json(&error)),
+            }
+
+            process::exit(exit_code(&error));
+        }
+    }
+}
+
+/// Runs the option on the expression in config and returns the output
+/// in the requested format
+fn run_command(config: &Config) -> Result<String, io::Error> {
+    let (option, format, expr) = (config.option, config.format, config.expr.unwrap());
+
+    if option == TOKENS_OPTION {
+        let tokens: Vec<_> = Tokenizer::build(expr).into_tokens()?.collect();
+
+        return Ok(match format {
+            Format::Text => tokens
+                .iter()
+                .map(|token| format!("{:<17} {}", token.token_type.kind(), token))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Format::Json => format!(
+                "[{}]",
+                tokens
+                    .iter()
+                    .map(json::token_to_json)
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        });
+    }
+
+    if option == EXPLAIN_OPTION {
+        let mut tokens = Tokenizer::build(expr).into_tokens()?;
+
+        return Ok(render_table(
+            ShuntingYardParser::try_build_traced(&mut tokens)?.trace(),
+        ));
+    }
+
+    let parser = parse_expression_shunting_yard(expr)?;
+
+    if option == DIFF_OPTION {
+        let derivative = to_infix(differentiate(parser.ast(), config.variable)?.as_ref());
+
+        return Ok(match format {
+            Format::Text => derivative,
+            Format::Json => format!("{{\"derivative\":{}}}", json::string(&derivative)),
+        });
+    }
+
+    if option == SIMPLIFY_OPTION {
+        let simplified = to_infix(simplify_with(parser.ast(), &config.rules).as_ref());
+
+        return Ok(match format {
+            Format::Text => simplified,
+            Format::Json => format!("{{\"simplified\":{}}}", json::string(&simplified)),
+        });
+    }
+
+    if option == EXPAND_OPTION || option == COLLECT_OPTION {
+        let (key, polynomial) = match option {
+            EXPAND_OPTION => ("expanded", expand(parser.ast())?),
+            _ => ("collected", collect(parser.ast(), config.variable)?),
+        };
+        let polynomial = to_infix(polynomial.as_ref());
+
+        return Ok(match format {
+            Format::Text => polynomial,
+            Format::Json => format!("{{\"{}\":{}}}", key, json::string(&polynomial)),
+        });
+    }
+
+    if option == SOLVE_OPTION {
+        let solution = solve_linear(parser.ast())?;
+
+        return Ok(match format {
+            Format::Text => solution.to_string(),
+            Format::Json => solution_json(&solution),
+        });
+    }
+
+    match (option, &config.arithmetic) {
+        (EVAL_OPTION, Arithmetic::Float(_)) if has_dates(parser.ast()) => {
+            let value = parser.try_evaluate_temporal(&Variables::new())?;
+
+            return Ok(match (format, value) {
+                (Format::Text, value) => format!("> {}", value),
+                (Format::Json, Temporal::Number(number)) => {
+                    format!(
+                        "{{\"result\":{},\"kind\":\"number\"}}",
+                        json::number(number)
+                    )
+                }
+                (Format::Json, value) => format!(
+                    "{{\"result\":{},\"kind\":{}}}",
+                    json::string(&value.to_string()),
+                    json::string(value.kind())
+                ),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Float(_)) if has_currency(parser.ast()) => {
+            let rates = match config.rates {
+                Some(path) => {
+                    let mut table = String::new();
+
+                    open(path)?.read_to_string(&mut table)?;
+                    table
+                        .parse()
+                        .map_err(|error| io::Error::other(format!("{}: {}", path, error)))?
+                }
+                None => RateTable::new(),
+            };
+            let money = parser.try_evaluate_currency(
+                &Variables::new(),
+                &rates,
+                &DecimalContext::default(),
+            )?;
+
+            return Ok(match format {
+                Format::Text => format!("> {}", money),
+                Format::Json => format!(
+                    "{{\"result\":{},\"amount\":{},\"currency\":{}}}",
+                    json::number(money.amount.to_f64()),
+                    json::string(&money.amount.to_string()),
+                    money
+                        .currency
+                        .as_deref()
+                        .map_or(String::from("null"), json::string)
+                ),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Float(_)) if has_units(parser.ast()) => {
+            let quantity = parser.try_evaluate_quantity(&Variables::new())?;
+            let magnitude = config.number_format.format(quantity.magnitude());
+
+            return Ok(match (format, quantity.unit_name().as_str()) {
+                (Format::Text, "") => format!("> {}", magnitude),
+                (Format::Text, unit) => format!("> {} {}", magnitude, unit),
+                (Format::Json, unit) => format!(
+                    "{{\"result\":{},\"unit\":{}}}",
+                    json::number(quantity.magnitude()),
+                    json::string(unit)
+                ),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Rational(inexact)) => {
+            let value = parser.try_evaluate_rational(&Variables::new(), *inexact)?;
+
+            return Ok(match format {
+                Format::Text => format!("> {}", format_rational(&value, &config.number_format)),
+                Format::Json => format!(
+                    "{{\"result\":{},\"exact\":{}}}",
+                    json::number(value.to_f64()),
+                    match &value {
+                        RationalValue::Exact(exact) => json::string(&exact.to_string()),
+                        RationalValue::Approximate(_) => String::from("null"),
+                    }
+                ),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Decimal(context)) => {
+            let value = parser.try_evaluate_decimal(&Variables::new(), context)?;
+
+            return Ok(match format {
+                Format::Text if config.number_format == FormatOptions::default() => {
+                    format!("> {}", value)
+                }
+                Format::Text => format!("> {}", config.number_format.format(value.to_f64())),
+                Format::Json => format!(
+                    "{{\"result\":{},\"decimal\":{}}}",
+                    json::number(value.to_f64()),
+                    json::string(&value.to_string())
+                ),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Integer { bits, division }) => {
+            let value = match *bits {
+                64 => parser
+                    .try_evaluate_integer::<i64>(&Variables::new(), *division)?
+                    .to_string(),
+                _ => parser
+                    .try_evaluate_integer::<i128>(&Variables::new(), *division)?
+                    .to_string(),
+            };
+            let approximate = value.parse::<f64>().unwrap();
+
+            return Ok(match format {
+                Format::Text if config.number_format == FormatOptions::default() => {
+                    format!("> {}", value)
+                }
+                Format::Text => format!("> {}", config.number_format.format(approximate)),
+                Format::Json => format!(
+                    "{{\"result\":{},\"integer\":{}}}",
+                    json::number(approximate),
+                    json::string(&value)
+                ),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Modular(modulus)) => {
+            let value = parser.try_evaluate_modular(&Variables::new(), modulus)?;
+
+            return Ok(match format {
+                Format::Text => format!("> {}", value),
+                Format::Json => format!("{{\"result\":{}}}", json::string(&value.to_string())),
+            });
+        }
+        (EVAL_OPTION, Arithmetic::Complex) => {
+            let value = parser
+                .try_evaluate_numeric_in::<Complex64>(&Variables::new(), config.angle_unit)?;
+
+            return Ok(match format {
+                Format::Text => format!(
+                    "> {}",
+                    config.number_format.format_complex(value.re, value.im)
+                ),
+                Format::Json => format!(
+                    "{{\"result\":{{\"re\":{},\"im\":{}}}}}",
+                    json::number(value.re),
+                    json::number(value.im)
+                ),
+            });
+        }
+        _ => (),
+    }
+
+    Ok(match (option, format) {
+        (DOT_OPTION, _) => to_dot(parser.ast()),
+        (EVAL_OPTION, _) => {
+            let policy = match config.arithmetic {
+                Arithmetic::Float(policy) => policy,
+                _ => IeeePolicy::default(),
+            };
+            let evaluation =
+                parser.try_evaluate_ieee(&Variables::new(), &policy, config.angle_unit)?;
+
+            match format {
+                Format::Text => {
+                    for warning in &evaluation.warnings {
+                        eprintln!("Warning: {}", warning);
+
+                        if let Some(span) = warning.span {
+                            eprintln!("{}\n{}", expr, span.underline());
+                        }
+                    }
+
+                    format!("> {}", config.number_format.format(evaluation.value))
+                }
+                Format::Json if evaluation.warnings.is_empty() => {
+                    format!("{{\"result\":{}}}", json::number(evaluation.value))
+                }
+                Format::Json => format!(
+                    "{{\"result\":{},\"warnings\":[{}]}}",
+                    json::number(evaluation.value),
+                    evaluation
+                        .warnings
+                        .iter()
+                        .map(warning_json)
+                        .collect::<Vec<String>>()
+                        .join(",")
+                ),
+            }
+        }
+        (AST_OPTION, Format::Text) => to_tree(parser.ast()),
+        (AST_OPTION, Format::Json) => json::to_json(parser.ast()),
+        (RPN_OPTION, Format::Text) => to_rpn(parser.ast()),
+        (RPN_OPTION, Format::Json) => {
+            format!("{{\"rpn\":{}}}", json::string(&to_rpn(parser.ast())))
+        }
+        (_, Format::Text) => String::from("ok"),
+        (_, Format::Json) => format!(
+            "{{\"valid\":true,\"variables\":[{}]}}",
+            parser
+                .variables()
+                .iter()
+                .map(|name| json::string(name))
+                .collect::<Vec<String>>()
+                .join(",")
+        ),
+    })
+}
+
+/// Writes an exact value as a decimal or fraction unless it is to be
+/// rounded, which happens on its nearest f64
+fn format_rational(value: &RationalValue, number_format: &FormatOptions) -> String {
+    match value {
+        RationalValue::Exact(exact) if number_format.notation == Notation::Fraction => {
+            exact.to_string()
+        }
+        RationalValue::Exact(_) if *number_format == FormatOptions::default() => value.to_string(),
+        _ => number_format.format(value.to_f64()),
+    }
+}
+
+/// Evaluates one expression per line read from the file in config or
+/// stdin. Failing lines are reported with their line number and the
+/// process exits with PARTIAL_FAILURE_EXIT_CODE when any line failed.
+fn run_batch(config: &Config) -> Result<(), io::Error> {
+    let reader: Box<dyn BufRead> = match config.file {
+        Some(path) => Box::new(BufReader::new(open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut evaluated = 0;
+    let mut failed = 0;
+
+    for result in evaluate_lines(reader) {
+        let result = result?;
+
+        evaluated += 1;
+
+        match (&result.value, config.format) {
+            (Ok(value), Format::Text) => println!(
+                "{}: {}",
+                result.line_number,
+                config.number_format.format(*value)
+            ),
+            (Ok(value), Format::Json) => println!(
+                "{{\"line\":{},\"expr\":{},\"result\":{}}}",
+                result.line_number,
+                json::string(&result.expr),
+                json::number(*value)
+            ),
+            (Err(error), Format::Text) => eprintln!("{}: {}", result.line_number, error),
+            (Err(error), Format::Json) => println!(
+                "{{\"line\":{},\"expr\":{},\"error\":{}}}",
+                result.line_number,
+                json::string(&result.expr),
+                error_json(error)
+            ),
+        }
+
+        if result.value.is_err() {
+            failed += 1;
+        }
+    }
+
+    match config.format {
+        Format::Text => eprintln!("{} evaluated, {} failed", evaluated, failed),
+        Format::Json => println!(
+            "{{\"summary\":{{\"evaluated\":{},\"failed\":{}}}}}",
+            evaluated, failed
+        ),
+    }
+
+    if failed > 0 {
+        process::exit(PARTIAL_FAILURE_EXIT_CODE);
+    }
+
+    Ok(())
+}
+
+/// Evaluates the expression in config for every record of the CSV file
+/// in config with the columns bound to the variables named by the header.
+/// The records are written to stdout with the result appended as a new
+/// column, left empty for records which failed. Failures are reported
+/// on stderr and the process exits with PARTIAL_FAILURE_EXIT_CODE when
+/// any record failed.
+fn run_csv(config: &Config) -> Result<(), io::Error> {
+    let parser = parse_expression_shunting_yard(config.expr.unwrap())?;
+    let reader = BufReader::new(open(config.file.unwrap())?);
+    let (mut header, rows) = evaluate_records(reader, &parser)?;
+    let mut evaluated = 0;
+    let mut failed = 0;
+
+    header.push(config.out.unwrap_or(DEFAULT_OUT_COLUMN).to_string());
+    println!("{}", write_record(&header));
+
+    for row in rows {
+        let mut row = row?;
+
+        evaluated += 1;
+
+        match row.value {
+            Ok(value) => row.record.push(config.number_format.format(value)),
+            Err(error) => {
+                failed += 1;
+                eprintln!("row {}: {}", row.row_number, error);
+                row.record.push(String::new());
+            }
+        }
+
+        println!("{}", write_record(&row.record));
+    }
+
+    eprintln!("{} rows evaluated, {} failed", evaluated, failed);
+
+    if failed > 0 {
+        process::exit(PARTIAL_FAILURE_EXIT_CODE);
+    }
+
+    Ok(())
+}
+
+/// Opens the file at path. Failures are I/O errors even when the file is
+/// missing, which the evaluator reports with NotFound for unbound
+/// variables.
+fn open(path: &str) -> Result<File, io::Error> {
+    File::open(path).map_err(|error| io::Error::other(format!("{}: {}", path, error)))
+}
+
+/// Writes the category and message of error as a JSON object
+fn error_json(error: &io::Error) -> String {
+    let span = match span_of(error) {
+        Some(span) => format!(
+            ",\"span\":{{\"start\":{},\"end\":{}}}",
+            span.start, span.end
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        "{{\"category\":{},\"message\":{}{}}}",
+        json::string(error_category(error)),
+        json::string(&error.to_string()),
+        span
+    )
+}
+
+/// Writes the solutions of a system of linear equations as a JSON object
+/// with the values of the unknowns as strings, exact fractions or
+/// expressions in the free unknowns
+fn solution_json(solution: &Solution) -> String {
+    let values = |values: Vec<(&String, String)>| {
+        values
+            .iter()
+            .map(|(name, value)| format!("{}:{}", json::string(name), json::string(value)))
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    match solution {
+        Solution::Unique(unique) => format!(
+            "{{\"solution\":\"unique\",\"values\":{{{}}}}}",
+            values(
+                unique
+                    .iter()
+                    .map(|(name, value)| (name, RationalValue::Exact(value.clone()).to_string()))
+                    .collect()
+            )
+        ),
+        Solution::Infinite { free, dependent } => format!(
+            "{{\"solution\":\"infinite\",\"values\":{{{}}},\"free\":[{}]}}",
+            values(
+                dependent
+                    .iter()
+                    .map(|(name, value)| (name, to_infix(value.to_ast().as_ref())))
+                    .collect()
+            ),
+            free.iter()
+                .map(|name| json::string(name))
+                .collect::<Vec<String>>()
+                .join(",")
+        ),
+        Solution::Inconsistent => String::from("{\"solution\":\"none\"}"),
+    }
+}
+
+/// Writes a warning of IEEE evaluation as a JSON object
+fn warning_json(warning: &IeeeWarning) -> String {
+    let span = match warning.span {
+        Some(span) => format!(
+            ",\"span\":{{\"start\":{},\"end\":{}}}",
+            span.start, span.end
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        "{{\"exception\":{},\"message\":{}{}}}",
+        json::string(warning.exception.name()),
+        json::string(&warning.message),
+        span
+    )
+}
+
+/// The stage of processing an error was raised in. The tokenizer reports
+/// invalid data, the parser invalid input and the evaluator missing values
+/// or operations it cannot evaluate.
+fn error_category(error: &io::Error) -> &'static str {
+    match error.kind() {
+        ErrorKind::InvalidData => "tokenize",
+        ErrorKind::InvalidInput => "parse",
+        ErrorKind::NotFound | ErrorKind::Unsupported => "evaluate",
+        _ => "io",
+    }
+}
+
+/// The exit code for each category of error
+fn exit_code(error: &io::Error) -> i32 {
+    match error_category(error) {
+        "tokenize" => 3,
+        "parse" => 4,
+        "evaluate" => 5,
+        _ => 1,
+    }
+}
+
+/// Reads lines from the terminal and executes them in a Session until
+/// :quit or end of input. The line history is kept in the home directory.
+fn run_repl(number_format: &FormatOptions) -> Result<(), Box<dyn Error>> {
+    let mut editor = DefaultEditor::new()?;
+    let mut session = Session::new();
+    let history = env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(HISTORY_FILE);
+
+    // a missing history file is expected on the first run
+    let _ = editor.load_history(&history);
+
+    loop {
+        let line = match editor.readline(">> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error.into()),
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        editor.add_history_entry(line.as_str())?;
+
+        match session.execute(&line) {
+            Ok(Reply::Value(value)) => println!("> {}", number_format.format(value)),
+            Ok(Reply::Text(text)) => {
+                if !text.is_empty() {
+                    println!("{}", text);
+                }
+            }
+            Ok(Reply::Quit) => break,
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+
+    editor.save_history(&history)?;
+
+    Ok(())
 }
@@ -0,0 +1,265 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::{apply, evaluate_numeric, unsupported_function, InContext, Numeric};
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IConstants, IFunctions, IToken};
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::io::{Error, ErrorKind};
+
+/// The largest exponent evaluated inside an exponent, where numbers are
+/// not reduced modulo the modulus
+pub const MAX_EXPONENT_POWER: u32 = 100_000;
+
+/// Evaluates the tree rooted at node with arithmetic modulo modulus and
+/// returns the result as a residue from 0 to modulus - 1.
+///
+/// a / b is a multiplied by the modular inverse of b, which fails when b
+/// and the modulus have a common factor. Exponents are evaluated as
+/// integers without reducing them, since a ^ e and a ^ (e mod m) differ,
+/// and raise by fast modular exponentiation. Literals with a decimal
+/// point, pi and functions are errors. Errors carry the span of the
+/// token they were raised at.
+pub fn evaluate_modular(
+    node: &dyn AstNode,
+    variables: &Variables<BigInt>,
+    modulus: &BigInt,
+) -> Result<BigInt, Error> {
+    if !modulus.is_positive() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("The modulus must be positive, got {}", modulus),
+        ));
+    }
+
+    evaluate(node, variables, Some(modulus)).map(|result| result.value)
+}
+
+/// Evaluates node modulo modulus, or with plain integer arithmetic when
+/// modulus is None as in exponents
+fn evaluate(
+    node: &dyn AstNode,
+    variables: &Variables<BigInt>,
+    modulus: Option<&BigInt>,
+) -> Result<InContext<BigInt, Option<BigInt>>, Error> {
+    let token = node.token();
+    let span = token.span;
+    let in_context = |value: BigInt| InContext {
+        value,
+        context: modulus.cloned(),
+    };
+
+    let result = match token.token_type {
+        IToken::Num => {
+            let text = token
+                .lexeme
+                .clone()
+                .unwrap_or_else(|| token.literal.unwrap().to_string());
+
+            InContext::from_literal(&text).map(|literal| in_context(reduce(literal.value, modulus)))
+        }
+        IToken::Var => {
+            let name = token.lexeme.clone().unwrap();
+
+            variables
+                .get(&name)
+                .map(|value| in_context(reduce(value.clone(), modulus)))
+                .ok_or_else(|| {
+                    Error::new(ErrorKind::NotFound, format!("Unbound variable: {}", name))
+                })
+        }
+        IToken::Const(_) | IToken::Imag | IToken::Angle(_) => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} is not an integer", token),
+        )),
+        IToken::Pow => {
+            let children = node.children();
+            let base = evaluate(children[0].as_ref(), variables, modulus)?;
+            let exponent = evaluate(children[1].as_ref(), variables, None)?;
+
+            base.pow(&exponent)
+        }
+        // the bounds would be reduced by the modulus
+        IToken::Fun(function) if function.binds_variable() => Err(unsupported_function(function)),
+        _ if node.children().is_empty() => evaluate_numeric(node, &Variables::new()),
+        token_type => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate(child.as_ref(), variables, modulus))
+                .collect::<Result<Vec<InContext<BigInt, Option<BigInt>>>, Error>>()?;
+
+            apply(token_type, &operands)
+        }
+    };
+
+    result.map_err(|error| with_span(error, span))
+}
+
+/// Returns value as a residue from 0 to modulus - 1, or unchanged
+/// without a modulus
+fn reduce(value: BigInt, modulus: Option<&BigInt>) -> BigInt {
+    match modulus {
+        Some(modulus) => ((value % modulus) + modulus) % modulus,
+        None => value,
+    }
+}
+
+/// Integers modulo the modulus of the context, or plain integers
+/// without one. Exponents are never reduced, so the exponent of pow
+/// must be evaluated without a modulus.
+impl Numeric for InContext<BigInt, Option<BigInt>> {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        text.parse::<BigInt>()
+            .map(|value| InContext {
+                value,
+                context: None,
+            })
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::Unsupported,
+                    format!("{} is not an integer", text),
+                )
+            })
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        match constant {
+            IConstants::Pi => Err(Error::new(ErrorKind::Unsupported, "pi is not an integer")),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        self.with(&self.value + &other.value)
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        self.with(&self.value - &other.value)
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        self.with(&self.value * &other.value)
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        self.with(divide(&self.value, &other.value, self.context.as_ref())?)
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        self.with(power(&self.value, &exponent.value, self.context.as_ref())?)
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        Some(self.value.cmp(&other.value))
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        self.with(-&self.value)
+    }
+    fn function(function: IFunctions, _arguments: &[Self]) -> Result<Self, Error> {
+        Err(unsupported_function(function))
+    }
+}
+
+impl InContext<BigInt, Option<BigInt>> {
+    /// Returns value reduced modulo the modulus of self
+    fn with(&self, value: BigInt) -> Result<Self, Error> {
+        Ok(InContext {
+            value: reduce(value, self.context.as_ref()),
+            context: self.context.clone(),
+        })
+    }
+}
+
+fn divide(dividend: &BigInt, divisor: &BigInt, modulus: Option<&BigInt>) -> Result<BigInt, Error> {
+    match modulus {
+        Some(modulus) => Ok(dividend * inverse(divisor, modulus)? % modulus),
+        None if divisor.is_zero() => Err(Error::new(ErrorKind::Unsupported, "Division by zero")),
+        None if !(dividend % divisor).is_zero() => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} / {} is not an integer", dividend, divisor),
+        )),
+        None => Ok(dividend / divisor),
+    }
+}
+
+fn inverse(value: &BigInt, modulus: &BigInt) -> Result<BigInt, Error> {
+    value.modinv(modulus).ok_or_else(|| {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("{} has no inverse modulo {}", value, modulus),
+        )
+    })
+}
+
+fn power(base: &BigInt, exponent: &BigInt, modulus: Option<&BigInt>) -> Result<BigInt, Error> {
+    match modulus {
+        Some(modulus) if exponent.is_negative() => {
+            Ok(inverse(base, modulus)?.modpow(&-exponent, modulus))
+        }
+        Some(modulus) => Ok(base.modpow(exponent, modulus)),
+        // 1 and -1 are the only integers with an integer inverse
+        None if exponent.is_negative() && base.is_one() => Ok(BigInt::one()),
+        None if exponent.is_negative() && (-base).is_one() => match (exponent % 2_i32).is_zero() {
+            true => Ok(BigInt::one()),
+            false => Ok(-BigInt::one()),
+        },
+        None if exponent.is_negative() => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} ^ {} is not an integer", base, exponent),
+        )),
+        None => match exponent.to_u32() {
+            Some(exponent) if exponent <= MAX_EXPONENT_POWER => Ok(base.pow(exponent)),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Exponent {} is too large to evaluate exactly", exponent),
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{span_of, Span};
+
+    fn evaluate(expr: &str, modulus: i64) -> Result<BigInt, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        evaluate_modular(parser.ast(), &Variables::new(), &BigInt::from(modulus))
+    }
+
+    #[test]
+    fn evaluate_modular_reduces_every_operation() {
+        assert_eq!(BigInt::from(2), evaluate("5 + 4", 7).unwrap());
+        assert_eq!(BigInt::from(5), evaluate("2 - 4", 7).unwrap());
+        assert_eq!(BigInt::from(4), evaluate("-3", 7).unwrap());
+        assert_eq!(BigInt::from(5), evaluate("1 / 3", 7).unwrap());
+        assert_eq!(BigInt::from(2), evaluate("2 ^ 10", 7).unwrap());
+        assert_eq!(BigInt::from(4), evaluate("2 ^ -1", 7).unwrap());
+        assert_eq!(BigInt::from(2), evaluate("2 ^ (5 * 2)", 7).unwrap());
+    }
+
+    #[test]
+    fn evaluate_modular_supports_large_moduli() {
+        let prime: BigInt = "170141183460469231731687303715884105727".parse().unwrap();
+        let parser = crate::parse_expression_shunting_yard("a ^ (p - 1)").unwrap();
+        let variables = Variables::from([
+            (String::from("a"), BigInt::from(123456789)),
+            (String::from("p"), prime.clone()),
+        ]);
+
+        assert_eq!(
+            BigInt::one(),
+            evaluate_modular(parser.ast(), &variables, &prime).unwrap()
+        );
+    }
+
+    #[test]
+    fn evaluate_modular_fails_without_inverse() {
+        let error = evaluate("1 + 5 / 4", 12).unwrap_err();
+
+        assert_eq!("4 has no inverse modulo 12 at 6..7", error.to_string());
+        assert_eq!(Some(Span::new(6, 7)), span_of(&error));
+        assert_eq!(
+            ErrorKind::Unsupported,
+            evaluate("2.5 * 2", 12).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            evaluate("1", 0).unwrap_err().kind()
+        );
+    }
+}
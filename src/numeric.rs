@@ -0,0 +1,575 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::higher_order::evaluate_binding;
+use crate::tokenizer::tokens::{IAngleUnit, IConstants, IFunctions, IToken, PI_LITERAL};
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+/// A number type the AST can be evaluated with by evaluate_numeric.
+/// Operations which have no result in the type return an error,
+/// conventionally of kind Unsupported.
+pub trait Numeric: Sized + Clone {
+    /// Parses the lexeme of a number token, digits with an optional
+    /// decimal point
+    fn from_literal(text: &str) -> Result<Self, Error>;
+    /// Returns the value of a named constant like pi
+    fn constant(constant: IConstants) -> Result<Self, Error>;
+    fn add(&self, other: &Self) -> Result<Self, Error>;
+    fn sub(&self, other: &Self) -> Result<Self, Error>;
+    fn mul(&self, other: &Self) -> Result<Self, Error>;
+    fn div(&self, other: &Self) -> Result<Self, Error>;
+    fn pow(&self, exponent: &Self) -> Result<Self, Error>;
+    /// Orders the values for max and min. None for unordered values
+    /// like NaN.
+    fn compare(&self, other: &Self) -> Option<Ordering>;
+    /// Negates the value for the unary minus
+    fn neg(&self) -> Result<Self, Error> {
+        Self::from_literal("0")?.sub(self)
+    }
+    /// Parses the digits of an imaginary number, the lexeme of an Imag
+    /// token without the i suffix. Only complex types support these.
+    fn imaginary(_text: &str) -> Result<Self, Error> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "Imaginary numbers require complex arithmetic",
+        ))
+    }
+    /// Converts an angle in unit to radians
+    fn angle_to_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        match unit.half_turn() {
+            Some(half_turn) => self
+                .mul(&Self::constant(IConstants::Pi)?)?
+                .div(&Self::from_literal(half_turn)?),
+            None => Ok(self.clone()),
+        }
+    }
+    /// Converts an angle in radians to unit
+    fn angle_from_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        match unit.half_turn() {
+            Some(half_turn) => self
+                .mul(&Self::from_literal(half_turn)?)?
+                .div(&Self::constant(IConstants::Pi)?),
+            None => Ok(self.clone()),
+        }
+    }
+    /// Converts the value to f64 for the numerical methods of integrate
+    /// and solve. None for values without an f64 equivalent, which is
+    /// every value by default.
+    fn to_real(&self) -> Option<f64> {
+        None
+    }
+    /// Converts a value computed by integrate or solve from f64
+    fn from_real(_value: f64) -> Option<Self> {
+        None
+    }
+    /// Applies the function to its arguments. The default supports max
+    /// and min through compare.
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        ordered_function(function, arguments)
+    }
+}
+
+/// Evaluates the tree rooted at node with the arithmetic of T looking
+/// up the values of variables in variables. Angles are in radians.
+pub fn evaluate_numeric<T: Numeric>(
+    node: &dyn AstNode,
+    variables: &Variables<T>,
+) -> Result<T, Error> {
+    evaluate_numeric_in(node, variables, IAngleUnit::Radians)
+}
+
+/// Evaluates the tree rooted at node like evaluate_numeric with angles
+/// in unit. Trigonometric functions take their arguments and inverse
+/// trigonometric functions and arg return their results in unit, while
+/// angle literals like 90deg are converted from their own unit.
+pub fn evaluate_numeric_in<T: Numeric>(
+    node: &dyn AstNode,
+    variables: &Variables<T>,
+    unit: IAngleUnit,
+) -> Result<T, Error> {
+    let token_type = node.token_type();
+
+    match token_type {
+        IToken::Num => {
+            let token = node.token();
+
+            T::from_literal(
+                &token
+                    .lexeme
+                    .unwrap_or_else(|| token.literal.unwrap().to_string()),
+            )
+        }
+        IToken::Const(constant) => T::constant(constant),
+        IToken::Imag => {
+            let lexeme = node.token().lexeme.unwrap();
+
+            match lexeme.trim_end_matches('i') {
+                "" => T::imaginary("1"),
+                digits => T::imaginary(digits),
+            }
+        }
+        IToken::Angle(angle_unit) => {
+            let lexeme = node.token().lexeme.unwrap();
+            let angle = T::from_literal(lexeme.trim_end_matches(angle_unit.suffix()))?;
+
+            // converting between degrees and gradians directly avoids
+            // rounding pi
+            match (angle_unit.half_turn(), unit.half_turn()) {
+                _ if angle_unit == unit => Ok(angle),
+                (Some(from), Some(to)) => angle
+                    .mul(&T::from_literal(to)?)?
+                    .div(&T::from_literal(from)?),
+                _ => angle.angle_to_radians(angle_unit)?.angle_from_radians(unit),
+            }
+        }
+        IToken::Quantity | IToken::Unit => Err(unsupported_units()),
+        IToken::Currency => Err(unsupported_currency()),
+        IToken::Str => Err(unsupported_dates()),
+        IToken::Var => {
+            let name = node.token().lexeme.unwrap();
+
+            variables.get(&name).cloned().ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("Unbound variable: {}", name))
+            })
+        }
+        IToken::Fun(function) if function.binds_variable() => {
+            evaluate_binding(node, function, variables, &|node, variables| {
+                evaluate_numeric_in(node, variables, unit)
+            })
+        }
+        _ => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate_numeric_in(child.as_ref(), variables, unit))
+                .collect::<Result<Vec<T>, Error>>()?;
+
+            apply_in(token_type, &operands, unit)
+        }
+    }
+}
+
+/// Applies the operator or function of token_type to its operands
+pub fn apply<T: Numeric>(token_type: IToken, operands: &[T]) -> Result<T, Error> {
+    apply_in(token_type, operands, IAngleUnit::Radians)
+}
+
+/// Applies the operator or function of token_type to its operands like
+/// apply with angles in unit
+pub fn apply_in<T: Numeric>(
+    token_type: IToken,
+    operands: &[T],
+    unit: IAngleUnit,
+) -> Result<T, Error> {
+    match token_type {
+        IToken::Fun(function) if function.takes_angle() && unit != IAngleUnit::Radians => {
+            T::function(function, &[operands[0].angle_to_radians(unit)?])
+        }
+        IToken::Fun(function) if function.returns_angle() && unit != IAngleUnit::Radians => {
+            T::function(function, operands)?.angle_from_radians(unit)
+        }
+        IToken::Add => operands[0].add(&operands[1]),
+        IToken::Sub => operands[0].sub(&operands[1]),
+        IToken::Mul => operands[0].mul(&operands[1]),
+        IToken::Div => operands[0].div(&operands[1]),
+        IToken::Pow => operands[0].pow(&operands[1]),
+        IToken::Neg => operands[0].neg(),
+        IToken::Fun(function) => T::function(function, operands),
+        IToken::To => Err(unsupported_units()),
+        IToken::Eq | IToken::Semicolon => Err(unsupported_equations()),
+        _ => unreachable!("only operators and functions have operands"),
+    }
+}
+
+/// A value and the context its operations depend on, like the precision
+/// decimals are rounded to. Values created without one, like by
+/// Numeric::from_literal, are in the default context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InContext<T, C> {
+    pub value: T,
+    pub context: C,
+}
+
+/// Applies max and min ordering the arguments with compare. When they
+/// are unordered the second argument is returned. Other functions are
+/// unsupported.
+pub fn ordered_function<T: Numeric>(function: IFunctions, arguments: &[T]) -> Result<T, Error> {
+    let ordering = match function {
+        IFunctions::Max => Ordering::Greater,
+        IFunctions::Min => Ordering::Less,
+        _ => return Err(unsupported_function(function)),
+    };
+
+    if arguments[0].compare(&arguments[1]) == Some(ordering) {
+        Ok(arguments[0].clone())
+    } else {
+        Ok(arguments[1].clone())
+    }
+}
+
+/// The error for quantities with units and conversions, which only
+/// units::evaluate_quantity supports
+pub fn unsupported_units() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "Units are only supported by floating point arithmetic",
+    )
+}
+
+/// The error for equations and systems of equations, which only
+/// linear::solve_linear supports
+pub fn unsupported_equations() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "Equations cannot be evaluated, only solved",
+    )
+}
+
+/// The error for amounts of money, which only
+/// currency::evaluate_currency supports
+pub fn unsupported_currency() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "Amounts of money require currency evaluation",
+    )
+}
+
+/// The error for strings, the arguments of date and duration, which
+/// only datetime::evaluate_temporal supports
+pub fn unsupported_dates() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "Dates and durations require date evaluation",
+    )
+}
+
+/// The error for a function a Numeric type does not support
+pub fn unsupported_function(function: IFunctions) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("{} is not supported by this arithmetic", function.name()),
+    )
+}
+
+/// The error for a number literal a Numeric type cannot read
+pub fn invalid_literal(text: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid number: {}", text))
+}
+
+impl Numeric for f64 {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        text.parse().map_err(|_| invalid_literal(text))
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        match constant {
+            IConstants::Pi => Ok(PI_LITERAL),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self + other)
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self - other)
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self * other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self / other)
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        Ok(self.powf(*exponent))
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        self.partial_cmp(other)
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        Ok(-self)
+    }
+    fn to_real(&self) -> Option<f64> {
+        Some(*self)
+    }
+    fn from_real(value: f64) -> Option<Self> {
+        Some(value)
+    }
+    // std::f64::consts::PI rather than the rounded PI_LITERAL, so that
+    // asin(1) is exactly 90 degrees
+    fn angle_to_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        Ok(match unit {
+            IAngleUnit::Radians => *self,
+            IAngleUnit::Degrees => self.to_radians(),
+            IAngleUnit::Gradians => self * (std::f64::consts::PI / 200.0),
+        })
+    }
+    fn angle_from_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        Ok(match unit {
+            IAngleUnit::Radians => *self,
+            IAngleUnit::Degrees => self.to_degrees(),
+            IAngleUnit::Gradians => self * (200.0 / std::f64::consts::PI),
+        })
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = arguments[0];
+
+        Ok(match function {
+            IFunctions::Sqrt => argument.sqrt(),
+            IFunctions::Abs => argument.abs(),
+            IFunctions::Arg => (0.0 as Self).atan2(argument),
+            IFunctions::Conj | IFunctions::Re => argument,
+            IFunctions::Im => 0.0,
+            IFunctions::Exp => argument.exp(),
+            IFunctions::Sin => argument.sin(),
+            IFunctions::Cos => argument.cos(),
+            IFunctions::Tan => argument.tan(),
+            IFunctions::Asin => argument.asin(),
+            IFunctions::Acos => argument.acos(),
+            IFunctions::Atan => argument.atan(),
+            _ => return ordered_function(function, arguments),
+        })
+    }
+}
+
+impl Numeric for f32 {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        text.parse().map_err(|_| invalid_literal(text))
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        match constant {
+            IConstants::Pi => Ok(PI_LITERAL as f32),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self + other)
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self - other)
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self * other)
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self / other)
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        Ok(self.powf(*exponent))
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        self.partial_cmp(other)
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        Ok(-self)
+    }
+    fn to_real(&self) -> Option<f64> {
+        Some(*self as f64)
+    }
+    fn from_real(value: f64) -> Option<Self> {
+        Some(value as f32)
+    }
+    // std::f32::consts::PI rather than the rounded PI_LITERAL, so that
+    // asin(1) is exactly 90 degrees
+    fn angle_to_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        Ok(match unit {
+            IAngleUnit::Radians => *self,
+            IAngleUnit::Degrees => self.to_radians(),
+            IAngleUnit::Gradians => self * (std::f32::consts::PI / 200.0),
+        })
+    }
+    fn angle_from_radians(&self, unit: IAngleUnit) -> Result<Self, Error> {
+        Ok(match unit {
+            IAngleUnit::Radians => *self,
+            IAngleUnit::Degrees => self.to_degrees(),
+            IAngleUnit::Gradians => self * (200.0 / std::f32::consts::PI),
+        })
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = arguments[0];
+
+        Ok(match function {
+            IFunctions::Sqrt => argument.sqrt(),
+            IFunctions::Abs => argument.abs(),
+            IFunctions::Arg => (0.0 as Self).atan2(argument),
+            IFunctions::Conj | IFunctions::Re => argument,
+            IFunctions::Im => 0.0,
+            IFunctions::Exp => argument.exp(),
+            IFunctions::Sin => argument.sin(),
+            IFunctions::Cos => argument.cos(),
+            IFunctions::Tan => argument.tan(),
+            IFunctions::Asin => argument.asin(),
+            IFunctions::Acos => argument.acos(),
+            IFunctions::Atan => argument.atan(),
+            _ => return ordered_function(function, arguments),
+        })
+    }
+}
+
+/// Implements Numeric for a primitive signed integer type with checked
+/// arithmetic. Overflows, division by zero and divisions or powers
+/// without an integer result are errors.
+macro_rules! checked_integer {
+    ($integer:ty, $bits:expr) => {
+        impl Numeric for $integer {
+            fn from_literal(text: &str) -> Result<Self, Error> {
+                if text.contains('.') {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("{} is not an integer", text),
+                    ));
+                }
+
+                text.parse().map_err(|_| {
+                    Error::new(
+                        ErrorKind::Unsupported,
+                        format!("{} does not fit in a {} bit integer", text, $bits),
+                    )
+                })
+            }
+            fn constant(constant: IConstants) -> Result<Self, Error> {
+                match constant {
+                    IConstants::Pi => {
+                        Err(Error::new(ErrorKind::Unsupported, "pi is not an integer"))
+                    }
+                }
+            }
+            fn add(&self, other: &Self) -> Result<Self, Error> {
+                self.checked_add(*other)
+                    .ok_or_else(|| overflow(*self, "+", *other))
+            }
+            fn sub(&self, other: &Self) -> Result<Self, Error> {
+                self.checked_sub(*other)
+                    .ok_or_else(|| overflow(*self, "-", *other))
+            }
+            fn mul(&self, other: &Self) -> Result<Self, Error> {
+                self.checked_mul(*other)
+                    .ok_or_else(|| overflow(*self, "*", *other))
+            }
+            fn div(&self, other: &Self) -> Result<Self, Error> {
+                if *other == 0 {
+                    return Err(Error::new(ErrorKind::Unsupported, "Division by zero"));
+                }
+
+                match self.checked_rem(*other) {
+                    Some(0) => Ok(self / other),
+                    Some(_) => Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("{} / {} is not an integer", self, other),
+                    )),
+                    None => Err(overflow(*self, "/", *other)),
+                }
+            }
+            fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+                if *exponent < 0 {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("{} ^ {} is not an integer", self, exponent),
+                    ));
+                }
+
+                u32::try_from(*exponent)
+                    .ok()
+                    .and_then(|exponent| self.checked_pow(exponent))
+                    .ok_or_else(|| overflow(*self, "^", *exponent))
+            }
+            fn compare(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+            fn neg(&self) -> Result<Self, Error> {
+                self.checked_neg().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::Unsupported,
+                        format!("Integer overflow in -{}", self),
+                    )
+                })
+            }
+            fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+                let argument = arguments[0];
+
+                match function {
+                    IFunctions::Abs => argument.checked_abs().ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::Unsupported,
+                            format!("Integer overflow in abs({})", argument),
+                        )
+                    }),
+                    IFunctions::Conj | IFunctions::Re => Ok(argument),
+                    IFunctions::Im => Ok(0),
+                    _ => ordered_function(function, arguments),
+                }
+            }
+        }
+    };
+}
+
+checked_integer!(i64, 64);
+checked_integer!(i128, 128);
+
+fn overflow(arg_1: impl fmt::Display, operator: &str, arg_2: impl fmt::Display) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("Integer overflow in {} {} {}", arg_1, operator, arg_2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+
+    fn parse(expr: &str) -> ShuntingYardParser {
+        crate::parse_expression_shunting_yard(expr).unwrap()
+    }
+
+    #[test]
+    fn evaluate_numeric_with_floats_matches_evaluate() {
+        for expr in [
+            "3 + 4 * 2 / ( 1 - 5 ) ^ 2 ^ 3",
+            "((2*3) + pi) / 10",
+            "max(1, 2) / 0",
+        ] {
+            let parser = parse(expr);
+
+            assert_eq!(
+                parser.evaluate(),
+                evaluate_numeric::<f64>(parser.ast(), &Variables::new()).unwrap()
+            );
+        }
+
+        assert_eq!(
+            0.1_f32 + 0.2_f32,
+            evaluate_numeric::<f32>(parse("0.1 + 0.2").ast(), &Variables::new()).unwrap()
+        );
+    }
+
+    #[test]
+    fn evaluate_numeric_in_converts_angles_to_and_from_unit() {
+        let evaluate = |expr: &str, unit: IAngleUnit| {
+            evaluate_numeric_in::<f64>(parse(expr).ast(), &Variables::new(), unit).unwrap()
+        };
+
+        assert_eq!(1.0, evaluate("sin(90)", IAngleUnit::Degrees));
+        assert_eq!(1.0, evaluate("sin(100)", IAngleUnit::Gradians));
+        assert_eq!(90.0, evaluate("asin(1)", IAngleUnit::Degrees));
+        assert_eq!(180.0, evaluate("arg(-1)", IAngleUnit::Degrees));
+        assert_eq!(1.0, evaluate("sin(90deg)", IAngleUnit::Radians));
+        assert_eq!(90.0, evaluate("100grad", IAngleUnit::Degrees));
+        assert_eq!(
+            evaluate("sin(1.5rad)", IAngleUnit::Radians),
+            evaluate("sin(1.5rad)", IAngleUnit::Degrees)
+        );
+    }
+
+    #[test]
+    fn evaluate_numeric_with_integers_checks_every_operation() {
+        let variables = Variables::from([(String::from("x"), i64::MAX)]);
+        let evaluate = |expr: &str| evaluate_numeric::<i64>(parse(expr).ast(), &variables);
+
+        assert_eq!(42, evaluate("6 * 7 / 2 ^ 0 - min(x, 0)").unwrap());
+        assert_eq!(
+            "Integer overflow in 9223372036854775807 + 1",
+            evaluate("x + 1").unwrap_err().to_string()
+        );
+
+        for expr in ["7 / 2", "2 ^ 64", "1.5", "pi", "y"] {
+            assert!(evaluate(expr).is_err(), "{}", expr);
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use crate::ast::nodes::{copy_node, new_node, AstNode};
+use crate::rational::parse_decimal;
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IToken, Token};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+
+/// The largest exponent a power of a polynomial is expanded to
+pub const MAX_EXPONENT: u32 = 1_000;
+
+/// The most terms a product of polynomials may have before like terms
+/// are collected, enough to raise a binomial to MAX_EXPONENT
+pub const MAX_TERMS: usize = 300_000;
+
+/// A product of variables raised to positive powers, with the variables
+/// in alphabetical order. Empty for the constant term.
+pub type Monomial = Vec<(String, u32)>;
+
+/// A polynomial in any number of variables with rational coefficients,
+/// a map from monomials to their coefficients which holds no zeros
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, BigRational>,
+}
+
+impl Polynomial {
+    /// Returns the polynomial with no terms
+    pub fn zero() -> Self {
+        Polynomial::default()
+    }
+
+    pub fn constant(value: BigRational) -> Self {
+        let mut polynomial = Polynomial::zero();
+
+        polynomial.add_term(vec![], value);
+
+        polynomial
+    }
+
+    pub fn variable(name: &str) -> Self {
+        let mut polynomial = Polynomial::zero();
+
+        polynomial.add_term(vec![(String::from(name), 1)], BigRational::one());
+
+        polynomial
+    }
+
+    /// Reads the tree rooted at node as a polynomial, multiplying out
+    /// products and powers. Numbers, variables, sums, differences,
+    /// products, negations, divisions by constants and powers with a
+    /// whole exponent up to MAX_EXPONENT are supported. Errors carry the
+    /// span of the token they were raised at.
+    pub fn from_ast(node: &dyn AstNode) -> Result<Self, Error> {
+        Polynomial::read(node).map_err(|error| with_span(error, node.token().span))
+    }
+
+    fn read(node: &dyn AstNode) -> Result<Self, Error> {
+        let token = node.token();
+        let children = node.children();
+        let operand = |position: usize| Polynomial::from_ast(children[position].as_ref());
+
+        match token.token_type {
+            IToken::Num => {
+                let text = token
+                    .lexeme
+                    .clone()
+                    .unwrap_or_else(|| token.literal.unwrap().to_string());
+
+                parse_decimal(&text)
+                    .map(Polynomial::constant)
+                    .ok_or_else(|| not_polynomial(&token))
+            }
+            IToken::Var => Ok(Polynomial::variable(token.lexeme.as_ref().unwrap())),
+            IToken::Add => Ok(operand(0)?.add(&operand(1)?)),
+            IToken::Sub => Ok(operand(0)?.sub(&operand(1)?)),
+            IToken::Mul => operand(0)?.mul(&operand(1)?),
+            IToken::Neg => Ok(operand(0)?.neg()),
+            IToken::Div => {
+                let divisor = operand(1)?.to_constant().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::Unsupported,
+                        "Cannot expand a division by a polynomial with variables",
+                    )
+                })?;
+
+                match divisor.is_zero() {
+                    true => Err(Error::new(ErrorKind::Unsupported, "Division by zero")),
+                    false => Ok(operand(0)?.scale(&divisor.recip())),
+                }
+            }
+            IToken::Pow => {
+                let exponent = operand(1)?
+                    .to_constant()
+                    .filter(|exponent| exponent.is_integer() && !exponent.is_negative())
+                    .and_then(|exponent| exponent.to_integer().to_u32())
+                    .filter(|exponent| *exponent <= MAX_EXPONENT)
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::Unsupported,
+                            format!(
+                                "Cannot expand a power with an exponent other than a whole number up to {}",
+                                MAX_EXPONENT
+                            ),
+                        )
+                    })?;
+
+                operand(0)?.pow(exponent)
+            }
+            _ => Err(not_polynomial(&token)),
+        }
+    }
+
+    /// The terms of the polynomial from the highest to the lowest total
+    /// degree, terms of equal degree ordered by the powers of their
+    /// variables in alphabetical order, like x^2 + x*y + y^2
+    pub fn terms(&self) -> Vec<(&Monomial, &BigRational)> {
+        let mut terms: Vec<_> = self.terms.iter().collect();
+
+        terms.sort_by(|(a, _), (b, _)| compare_monomials(a, b));
+
+        terms
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The value of the polynomial if it has no variables
+    pub fn to_constant(&self) -> Option<BigRational> {
+        match self.terms.len() {
+            0 => Some(BigRational::zero()),
+            1 => self.terms.get(&vec![]).cloned(),
+            _ => None,
+        }
+    }
+
+    /// The highest power of variable in the polynomial, 0 if it does not
+    /// appear
+    pub fn degree_in(&self, variable: &str) -> u32 {
+        self.terms
+            .keys()
+            .map(|monomial| power(monomial, variable))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let mut sum = self.clone();
+
+        for (monomial, coefficient) in &other.terms {
+            sum.add_term(monomial.clone(), coefficient.clone());
+        }
+
+        sum
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn neg(&self) -> Self {
+        self.scale(&-BigRational::one())
+    }
+
+    /// Multiplies out the product, failing before any work if it would
+    /// have more than MAX_TERMS terms
+    pub fn mul(&self, other: &Self) -> Result<Self, Error> {
+        let mut product = Polynomial::zero();
+
+        if self.terms.len().saturating_mul(other.terms.len()) > MAX_TERMS {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Cannot expand a product with more than {} terms", MAX_TERMS),
+            ));
+        }
+
+        for (a, a_coefficient) in &self.terms {
+            for (b, b_coefficient) in &other.terms {
+                product.add_term(multiply_monomials(a, b)?, a_coefficient * b_coefficient);
+            }
+        }
+
+        Ok(product)
+    }
+
+    pub fn pow(&self, exponent: u32) -> Result<Self, Error> {
+        let mut power = Polynomial::constant(BigRational::one());
+        let mut base = self.clone();
+        let mut exponent = exponent;
+
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                power = power.mul(&base)?;
+            }
+
+            exponent /= 2;
+
+            if exponent > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+
+        Ok(power)
+    }
+
+    /// Multiplies every coefficient by factor
+    pub fn scale(&self, factor: &BigRational) -> Self {
+        let mut scaled = Polynomial::zero();
+
+        for (monomial, coefficient) in &self.terms {
+            scaled.add_term(monomial.clone(), coefficient * factor);
+        }
+
+        scaled
+    }
+
+    /// Splits the polynomial by the powers of variable, returning the
+    /// polynomial in the other variables multiplying each power from the
+    /// highest to the lowest
+    pub fn coefficients_in(&self, variable: &str) -> Vec<(u32, Polynomial)> {
+        let mut powers: BTreeMap<u32, Polynomial> = BTreeMap::new();
+
+        for (monomial, coefficient) in &self.terms {
+            let rest = monomial
+                .iter()
+                .filter(|(name, _)| name != variable)
+                .cloned()
+                .collect();
+
+            powers
+                .entry(power(monomial, variable))
+                .or_default()
+                .add_term(rest, coefficient.clone());
+        }
+
+        powers.into_iter().rev().collect()
+    }
+
+    /// Builds the tree of the expanded polynomial, a sum of terms in the
+    /// order of terms()
+    pub fn to_ast(&self) -> Box<dyn AstNode> {
+        sum(self
+            .terms()
+            .into_iter()
+            .map(|(monomial, coefficient)| term(coefficient, monomial))
+            .collect())
+    }
+
+    fn add_term(&mut self, monomial: Monomial, coefficient: BigRational) {
+        let total = self.terms.remove(&monomial).unwrap_or_default() + coefficient;
+
+        if !total.is_zero() {
+            self.terms.insert(monomial, total);
+        }
+    }
+}
+
+/// Expands the tree rooted at node into a sum of terms with rational
+/// coefficients, like (x + 1)^2 into x^2 + 2*x + 1
+pub fn expand(node: &dyn AstNode) -> Result<Box<dyn AstNode>, Error> {
+    Ok(Polynomial::from_ast(node)?.to_ast())
+}
+
+/// Expands the tree rooted at node and groups its terms by the powers of
+/// variable from the highest to the lowest, like a*x + b*x + 1 into
+/// (a + b)*x + 1
+pub fn collect(node: &dyn AstNode, variable: &str) -> Result<Box<dyn AstNode>, Error> {
+    let polynomial = Polynomial::from_ast(node)?;
+    let mut terms = vec![];
+
+    for (power, coefficient) in polynomial.coefficients_in(variable) {
+        let base = Polynomial::variable(variable).pow(power)?;
+
+        match coefficient.terms.len() == 1 || power == 0 {
+            true => terms.extend(
+                coefficient
+                    .mul(&base)?
+                    .terms()
+                    .into_iter()
+                    .map(|(monomial, coefficient)| term(coefficient, monomial)),
+            ),
+            false => terms.push((
+                false,
+                binary(IToken::Mul, coefficient.to_ast(), base.to_ast()),
+            )),
+        }
+    }
+
+    Ok(sum(terms))
+}
+
+/// Orders monomials by falling total degree, then by falling powers of
+/// their variables taken in alphabetical order
+fn compare_monomials(a: &Monomial, b: &Monomial) -> Ordering {
+    let degree = |monomial: &Monomial| monomial.iter().map(|(_, power)| power).sum::<u32>();
+    let mut names: Vec<&String> = a.iter().chain(b).map(|(name, _)| name).collect();
+
+    names.sort();
+    names.dedup();
+
+    degree(b).cmp(&degree(a)).then_with(|| {
+        names
+            .into_iter()
+            .map(|name| power(b, name).cmp(&power(a, name)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    })
+}
+
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Result<Monomial, Error> {
+    let mut powers: BTreeMap<String, u32> = a.iter().cloned().collect();
+
+    for (name, power) in b {
+        let total = powers.entry(name.clone()).or_default();
+
+        *total = total.checked_add(*power).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Unsupported,
+                format!("The exponent of {} is too large to expand", name),
+            )
+        })?;
+    }
+
+    Ok(powers.into_iter().collect())
+}
+
+fn power(monomial: &Monomial, variable: &str) -> u32 {
+    monomial
+        .iter()
+        .find(|(name, _)| name == variable)
+        .map_or(0, |(_, power)| *power)
+}
+
+/// The tree of the term coefficient * monomial without its sign, and
+/// whether the coefficient is negative
+fn term(coefficient: &BigRational, monomial: &Monomial) -> (bool, Box<dyn AstNode>) {
+    let magnitude = coefficient.abs();
+    let factors = monomial.iter().map(|(name, power)| {
+        let variable = new_node(Token::variable(name), vec![]);
+
+        match power {
+            1 => variable,
+            _ => binary(IToken::Pow, variable, integer(&BigInt::from(*power))),
+        }
+    });
+    let factors: Vec<Box<dyn AstNode>> = match magnitude.is_one() && !monomial.is_empty() {
+        true => factors.collect(),
+        false => std::iter::once(rational(&magnitude))
+            .chain(factors)
+            .collect(),
+    };
+    let product = factors
+        .into_iter()
+        .reduce(|product, factor| binary(IToken::Mul, product, factor))
+        .unwrap();
+
+    (coefficient.is_negative(), product)
+}
+
+/// Adds up terms given as by term(), subtracting the negative ones. The
+/// sum of no terms is 0.
+fn sum(terms: Vec<(bool, Box<dyn AstNode>)>) -> Box<dyn AstNode> {
+    let mut terms = terms.into_iter();
+    let first = match terms.next() {
+        Some((true, first)) => negate(first.as_ref()),
+        Some((false, first)) => first,
+        None => return integer(&BigInt::zero()),
+    };
+
+    terms.fold(first, |sum, (negative, term)| match negative {
+        true => binary(IToken::Sub, sum, term),
+        false => binary(IToken::Add, sum, term),
+    })
+}
+
+/// Negates the term rooted at node by negating its leftmost factor, so
+/// that -2*x is written without parentheses
+fn negate(node: &dyn AstNode) -> Box<dyn AstNode> {
+    let token = node.token();
+    let children = node.children();
+
+    match token.token_type {
+        IToken::Mul | IToken::Div => binary(
+            token.token_type,
+            negate(children[0].as_ref()),
+            copy_node(children[1].as_ref()),
+        ),
+        IToken::Num => integer(&-token.lexeme.unwrap().parse::<BigInt>().unwrap()),
+        _ => new_node(Token::operator(IToken::Neg), vec![copy_node(node)]),
+    }
+}
+
+/// The tree of a non-negative rational, a number or a quotient of two
+fn rational(value: &BigRational) -> Box<dyn AstNode> {
+    match value.is_integer() {
+        true => integer(value.numer()),
+        false => binary(IToken::Div, integer(value.numer()), integer(value.denom())),
+    }
+}
+
+/// A number node whose lexeme holds every digit of value
+fn integer(value: &BigInt) -> Box<dyn AstNode> {
+    let mut token = Token::number(value.to_f64().unwrap_or(f64::INFINITY));
+
+    token.lexeme = Some(value.to_string());
+
+    new_node(token, vec![])
+}
+
+fn binary(token_type: IToken, left: Box<dyn AstNode>, right: Box<dyn AstNode>) -> Box<dyn AstNode> {
+    new_node(Token::operator(token_type), vec![left, right])
+}
+
+fn not_polynomial(token: &Token) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("{} is not a polynomial", token),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::infix::to_infix;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::span::{span_of, Span};
+
+    fn parse(expr: &str) -> ShuntingYardParser {
+        crate::parse_expression_shunting_yard(expr).unwrap()
+    }
+
+    fn expanded(expr: &str) -> Result<String, Error> {
+        expand(parse(expr).ast()).map(|node| to_infix(node.as_ref()))
+    }
+
+    fn collected(expr: &str, variable: &str) -> Result<String, Error> {
+        collect(parse(expr).ast(), variable).map(|node| to_infix(node.as_ref()))
+    }
+
+    #[test]
+    fn expand_multiplies_out_products_and_powers() {
+        assert_eq!(
+            "x^3 + 2*x^2 + 5*x + 1",
+            expanded("(x+1)^3 - x*(x-2)").unwrap()
+        );
+        assert_eq!("x^2 - y^2", expanded("(x - y) * (x + y)").unwrap());
+        assert_eq!(
+            "a^2 + 2*a*b + b^2 - 1/2*c",
+            expanded("(a + b)^2 - c / 2").unwrap()
+        );
+        assert_eq!("-x + 1/2", expanded("0.5 - x").unwrap());
+        assert_eq!("-2*x*y - 1/3*y", expanded("-y * (2*x + 1/3)").unwrap());
+        assert_eq!("0", expanded("(x + 1)^2 - x^2 - 2*x - 1").unwrap());
+        assert_eq!(
+            2,
+            Polynomial::from_ast(parse("x^2*y + y").ast())
+                .unwrap()
+                .degree_in("x")
+        );
+    }
+
+    #[test]
+    fn collect_groups_terms_by_powers_of_the_variable() {
+        assert_eq!(
+            "(a + b)*x^2 - 2*x + a*b",
+            collected("a*x^2 + b*x^2 - 2*x + a*b", "x").unwrap()
+        );
+        assert_eq!(
+            "(a + 1)*x - y + 3",
+            collected("x*(a + 1) - y + 3", "x").unwrap()
+        );
+        assert_eq!("2*x*y - z", collected("x*y + y*x - z", "y").unwrap());
+    }
+
+    #[test]
+    fn expand_fails_for_non_polynomials() {
+        let error = expanded("x / (x + 1)").unwrap_err();
+
+        assert_eq!(Some(Span::new(2, 3)), span_of(&error));
+        assert!(expanded("x^y").is_err());
+        assert!(expanded("x^-1").is_err());
+        assert!(expanded("sin(x)").is_err());
+        assert!(expanded("x / 0").is_err());
+    }
+
+    #[test]
+    fn expand_fails_for_products_with_too_many_terms() {
+        let error = expanded("(a+b+c+d)^60").unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert_eq!(
+            "Cannot expand a product with more than 300000 terms at 9..10",
+            error.to_string()
+        );
+        assert!(expanded("(a+b)^3 * (c+d)^3").is_ok());
+
+        let error = expanded("(((x^1000)^1000)^1000)^1000").unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert_eq!(
+            "The exponent of x is too large to expand at 22..23",
+            error.to_string()
+        );
+    }
+}
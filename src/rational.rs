@@ -0,0 +1,552 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::higher_order::evaluate_binding;
+use crate::numeric::{
+    apply, evaluate_numeric, invalid_literal, unsupported_function, InContext, Numeric,
+};
+use crate::tokenizer::tokens::{IAngleUnit, IConstants, IFunctions, IToken, PI_LITERAL};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// The largest magnitude of an integer exponent evaluated exactly
+pub const MAX_EXACT_EXPONENT: i32 = 100_000;
+
+/// The largest number of bits of a power evaluated exactly, estimated as
+/// the bits of its base times the magnitude of its exponent
+pub const MAX_EXACT_BITS: u64 = 1 << 22;
+
+/// Whether a base of the given number of bits raised to exponent is
+/// small enough to be evaluated exactly
+pub fn fits_exactly(base_bits: u64, exponent: i32) -> bool {
+    exponent.abs() <= MAX_EXACT_EXPONENT
+        && base_bits.saturating_mul(exponent.unsigned_abs() as u64) <= MAX_EXACT_BITS
+}
+
+/// What to do when an operation has no exact rational result, like
+/// pi, a fractional exponent or a division by zero
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Inexact {
+    /// Fail with an error of kind Unsupported
+    #[default]
+    Error,
+    /// Continue the evaluation with f64
+    Float,
+}
+
+/// The result of an exact evaluation
+#[derive(Debug, Clone, PartialEq)]
+pub enum RationalValue {
+    Exact(BigRational),
+    /// An operation had no exact result and Inexact::Float was requested
+    Approximate(f64),
+}
+
+impl FromStr for Inexact {
+    type Err = Error;
+
+    fn from_str(inexact: &str) -> Result<Self, Self::Err> {
+        match inexact {
+            "error" => Ok(Inexact::Error),
+            "float" => Ok(Inexact::Float),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown inexact behaviour: {}", inexact),
+            )),
+        }
+    }
+}
+
+impl RationalValue {
+    /// Returns the nearest f64 to the value
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            RationalValue::Exact(value) => value.to_f64().unwrap_or(f64::NAN),
+            RationalValue::Approximate(value) => *value,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, RationalValue::Exact(_))
+    }
+}
+
+impl fmt::Display for RationalValue {
+    /// Writes exact values with a terminating decimal expansion as
+    /// decimals, e.g. 0.3, and any other exact value as a fraction, e.g. 1/3
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            RationalValue::Exact(value) => value,
+            RationalValue::Approximate(value) => return write!(f, "{}", value),
+        };
+        let places = match decimal_places(value.denom()) {
+            Some(places) => places,
+            None => return write!(f, "{}", value),
+        };
+        let scaled = (value * BigRational::from_integer(BigInt::from(10).pow(places))).to_integer();
+        let mut digits = scaled.abs().to_string();
+
+        if places > 0 {
+            while digits.len() <= places as usize {
+                digits.insert(0, '0');
+            }
+
+            digits.insert(digits.len() - places as usize, '.');
+        }
+
+        if scaled.is_negative() {
+            digits.insert(0, '-');
+        }
+
+        write!(f, "{}", digits)
+    }
+}
+
+/// Parses a decimal written with digits, an optional decimal point and
+/// an optional leading minus sign, like the lexeme of a number token
+pub fn parse_decimal(text: &str) -> Option<BigRational> {
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, text),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let digits = format!("{}{}", integer, fraction);
+
+    if digits.is_empty() || !digits.chars().all(|char| char.is_ascii_digit()) {
+        return None;
+    }
+
+    let value = BigRational::new(
+        digits.parse().ok()?,
+        BigInt::from(10).pow(fraction.len() as u32),
+    );
+
+    Some(if negative { -value } else { value })
+}
+
+/// Evaluates the tree rooted at node with exact rational arithmetic.
+/// Number literals are read from their lexemes and variables from the
+/// shortest decimal which reads back as their value, so 0.1 is 1/10.
+pub fn evaluate_rational(
+    node: &dyn AstNode,
+    variables: &Variables,
+    inexact: Inexact,
+) -> Result<RationalValue, Error> {
+    evaluate(node, variables, &Variables::new(), inexact).map(|result| result.value)
+}
+
+/// Evaluates like evaluate_rational, reading the variables bound by sum
+/// and prod from bound before variables
+fn evaluate(
+    node: &dyn AstNode,
+    variables: &Variables,
+    bound: &Variables<InContext<RationalValue, Inexact>>,
+    inexact: Inexact,
+) -> Result<InContext<RationalValue, Inexact>, Error> {
+    let token_type = node.token_type();
+    let in_context = |value| InContext {
+        value,
+        context: inexact,
+    };
+
+    match token_type {
+        IToken::Num => {
+            let token = node.token();
+            let text = token
+                .lexeme
+                .unwrap_or_else(|| token.literal.unwrap().to_string());
+
+            InContext::from_literal(&text).map(|literal| in_context(literal.value))
+        }
+        IToken::Const(_) => {
+            let token = node.token();
+
+            fallback(
+                inexact,
+                token.literal.unwrap(),
+                format!("{} has no exact rational value", token),
+            )
+            .map(in_context)
+        }
+        IToken::Angle(IAngleUnit::Radians) => {
+            let lexeme = node.token().lexeme.unwrap();
+            let text = lexeme.trim_end_matches(IAngleUnit::Radians.suffix());
+
+            InContext::from_literal(text).map(|literal| in_context(literal.value))
+        }
+        IToken::Angle(_) => {
+            let token = node.token();
+
+            fallback(
+                inexact,
+                node.evaluate(),
+                format!("{} has no exact rational value in radians", token),
+            )
+            .map(in_context)
+        }
+        IToken::Var if bound.contains_key(&node.token().lexeme.unwrap()) => {
+            Ok(bound[&node.token().lexeme.unwrap()].clone())
+        }
+        IToken::Var => {
+            let name = node.token().lexeme.unwrap();
+            let value = *variables.get(&name).ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("Unbound variable: {}", name))
+            })?;
+
+            match parse_decimal(&value.to_string()) {
+                Some(exact) if value.is_finite() => Ok(in_context(RationalValue::Exact(exact))),
+                _ => fallback(
+                    inexact,
+                    value,
+                    format!("{} = {} has no exact rational value", name, value),
+                )
+                .map(in_context),
+            }
+        }
+        // their results are approximations even for exact arguments
+        IToken::Fun(function @ (IFunctions::Integrate | IFunctions::Solve)) => {
+            Err(unsupported_function(function))
+        }
+        IToken::Fun(function) if function.binds_variable() => {
+            evaluate_binding(node, function, bound, &|node, bound| {
+                evaluate(node, variables, bound, inexact)
+            })
+        }
+        _ if node.children().is_empty() => evaluate_numeric(node, &Variables::new()),
+        _ => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate(child.as_ref(), variables, bound, inexact))
+                .collect::<Result<Vec<InContext<RationalValue, Inexact>>, Error>>()?;
+
+            apply(token_type, &operands)
+        }
+    }
+}
+
+impl InContext<RationalValue, Inexact> {
+    /// Applies the operator or function of token_type to the operands,
+    /// exactly when they are all exact and with f64 otherwise
+    fn apply(token_type: IToken, operands: &[&Self]) -> Result<Self, Error> {
+        let inexact = operands[0].context;
+        let exact: Vec<&BigRational> = operands
+            .iter()
+            .filter_map(|operand| match &operand.value {
+                RationalValue::Exact(value) => Some(value),
+                RationalValue::Approximate(_) => None,
+            })
+            .collect();
+        let value = if exact.len() == operands.len() {
+            apply_exact(token_type, &exact, inexact)?
+        } else {
+            let approximate: Vec<f64> = operands
+                .iter()
+                .map(|operand| operand.value.to_f64())
+                .collect();
+
+            RationalValue::Approximate(apply(token_type, &approximate)?)
+        };
+
+        Ok(InContext {
+            value,
+            context: inexact,
+        })
+    }
+}
+
+impl Numeric for InContext<RationalValue, Inexact> {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        parse_decimal(text)
+            .map(|value| InContext {
+                value: RationalValue::Exact(value),
+                context: Inexact::default(),
+            })
+            .ok_or_else(|| invalid_literal(text))
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        match constant {
+            IConstants::Pi => fallback(
+                Inexact::default(),
+                PI_LITERAL,
+                String::from("pi has no exact rational value"),
+            )
+            .map(|value| InContext {
+                value,
+                context: Inexact::default(),
+            }),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        Self::apply(IToken::Add, &[self, other])
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        Self::apply(IToken::Sub, &[self, other])
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        Self::apply(IToken::Mul, &[self, other])
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        Self::apply(IToken::Div, &[self, other])
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        Self::apply(IToken::Pow, &[self, exponent])
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (&self.value, &other.value) {
+            (RationalValue::Exact(value), RationalValue::Exact(other)) => Some(value.cmp(other)),
+            (value, other) => value.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        Self::apply(IToken::Neg, &[self])
+    }
+    fn to_real(&self) -> Option<f64> {
+        Some(self.value.to_f64())
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let arguments: Vec<&Self> = arguments.iter().collect();
+
+        Self::apply(IToken::Fun(function), &arguments)
+    }
+}
+
+fn apply_exact(
+    token_type: IToken,
+    operands: &[&BigRational],
+    inexact: Inexact,
+) -> Result<RationalValue, Error> {
+    let approximate = || {
+        let operands: Vec<f64> = operands
+            .iter()
+            .map(|operand| operand.to_f64().unwrap_or(f64::NAN))
+            .collect();
+
+        apply(token_type, &operands)
+    };
+    let arg_1 = operands[0];
+    let arg_2 = operands.get(1).copied().unwrap_or(arg_1);
+
+    Ok(RationalValue::Exact(match token_type {
+        IToken::Add => arg_1 + arg_2,
+        IToken::Sub => arg_1 - arg_2,
+        IToken::Mul => arg_1 * arg_2,
+        IToken::Div if arg_2.is_zero() => {
+            return fallback(inexact, approximate()?, String::from("Division by zero"))
+        }
+        IToken::Div => arg_1 / arg_2,
+        IToken::Pow if !arg_2.is_integer() => {
+            return fallback(
+                inexact,
+                approximate()?,
+                format!("Fractional exponent {} has no exact rational value", arg_2),
+            )
+        }
+        IToken::Pow => match arg_2.to_integer().to_i32() {
+            Some(exponent) if exponent < 0 && arg_1.is_zero() => {
+                return fallback(inexact, approximate()?, String::from("Division by zero"))
+            }
+            Some(exponent)
+                if fits_exactly(arg_1.numer().bits().max(arg_1.denom().bits()), exponent) =>
+            {
+                arg_1.pow(exponent)
+            }
+            _ => {
+                return fallback(
+                    inexact,
+                    approximate()?,
+                    format!("Exponent {} is too large to evaluate exactly", arg_2),
+                )
+            }
+        },
+        IToken::Neg => -arg_1,
+        IToken::Fun(IFunctions::Max) => arg_1.max(arg_2).clone(),
+        IToken::Fun(IFunctions::Min) => arg_1.min(arg_2).clone(),
+        IToken::Fun(IFunctions::Abs) => arg_1.abs(),
+        IToken::Fun(IFunctions::Re | IFunctions::Conj) => arg_1.clone(),
+        IToken::Fun(IFunctions::Im) => BigRational::zero(),
+        IToken::Fun(IFunctions::Arg) if !arg_1.is_negative() => BigRational::zero(),
+        IToken::Fun(IFunctions::Exp) if arg_1.is_zero() => BigRational::one(),
+        IToken::Fun(IFunctions::Sqrt) => match exact_sqrt(arg_1) {
+            Some(root) => root,
+            None => {
+                return fallback(
+                    inexact,
+                    approximate()?,
+                    format!("sqrt({}) has no exact rational value", arg_1),
+                )
+            }
+        },
+        IToken::Fun(function) => {
+            return fallback(
+                inexact,
+                approximate()?,
+                format!("{}({}) has no exact rational value", function.name(), arg_1),
+            )
+        }
+        _ => unreachable!("only operators and functions have operands"),
+    }))
+}
+
+/// Returns the square root of value when both its numerator and
+/// denominator are perfect squares
+fn exact_sqrt(value: &BigRational) -> Option<BigRational> {
+    if value.is_negative() {
+        return None;
+    }
+
+    let root = |integer: &BigInt| {
+        let root = integer.sqrt();
+
+        (&root * &root == *integer).then_some(root)
+    };
+
+    Some(BigRational::new(root(value.numer())?, root(value.denom())?))
+}
+
+fn fallback(inexact: Inexact, value: f64, reason: String) -> Result<RationalValue, Error> {
+    match inexact {
+        Inexact::Float => Ok(RationalValue::Approximate(value)),
+        Inexact::Error => Err(Error::new(ErrorKind::Unsupported, reason)),
+    }
+}
+
+/// Returns the number of decimal places of the fractions with the
+/// denominator when they terminate, which is when the denominator has
+/// no prime factors other than 2 and 5
+fn decimal_places(denominator: &BigInt) -> Option<u32> {
+    let mut rest = denominator.clone();
+    let mut places = [0, 0];
+
+    for (index, factor) in [2, 5].into_iter().enumerate() {
+        let factor = BigInt::from(factor);
+
+        while (&rest % &factor).is_zero() {
+            rest /= &factor;
+            places[index] += 1;
+        }
+    }
+
+    rest.is_one().then(|| places[0].max(places[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+
+    fn parse(expr: &str) -> ShuntingYardParser {
+        crate::parse_expression_shunting_yard(expr).unwrap()
+    }
+
+    fn evaluate(expr: &str, inexact: Inexact) -> Result<RationalValue, Error> {
+        let parser = parse(expr);
+
+        evaluate_rational(parser.ast(), &Variables::new(), inexact)
+    }
+
+    #[test]
+    fn evaluate_rational_keeps_decimals_exact() {
+        let value = evaluate("0.1 + 0.2", Inexact::Error).unwrap();
+
+        assert_eq!(
+            RationalValue::Exact(BigRational::new(3.into(), 10.into())),
+            value
+        );
+        assert_eq!("0.3", value.to_string());
+        assert_eq!(
+            "1/3",
+            evaluate("1 / 3", Inexact::Error).unwrap().to_string()
+        );
+        assert_eq!(
+            "-0.0625",
+            evaluate("0 - 2 ^ (0 - 4)", Inexact::Error)
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "1267650600228229401496703205376",
+            evaluate("2 ^ 100", Inexact::Error).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn evaluate_rational_reads_variables_as_shortest_decimal() {
+        let parser = parse("price * 3");
+        let variables = Variables::from([(String::from("price"), 0.1)]);
+        let value = evaluate_rational(parser.ast(), &variables, Inexact::Error).unwrap();
+
+        assert_eq!("0.3", value.to_string());
+    }
+
+    #[test]
+    fn evaluate_rational_errors_or_falls_back_for_inexact_operations() {
+        for expr in ["2 * pi", "2 ^ 0.5", "1 / (1 - 1)"] {
+            assert_eq!(
+                ErrorKind::Unsupported,
+                evaluate(expr, Inexact::Error).unwrap_err().kind()
+            );
+        }
+
+        let value = evaluate("2 ^ 0.5 + 1 / 4", Inexact::Float).unwrap();
+
+        assert!(!value.is_exact());
+        assert_eq!(2_f64.sqrt() + 0.25, value.to_f64());
+
+        for inexact in [Inexact::Error, Inexact::Float] {
+            assert_eq!(
+                "duration is not supported by this arithmetic",
+                evaluate("duration(1)", inexact).unwrap_err().to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_rational_applies_operators_through_numeric() {
+        assert_eq!(
+            "1/3",
+            evaluate("max(1 / 3, 0.25)", Inexact::Error)
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "Equations cannot be evaluated, only solved",
+            evaluate("1 = 1", Inexact::Error).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Units are only supported by floating point arithmetic",
+            evaluate("2 m", Inexact::Float).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "7381/2520",
+            evaluate("sum(1 / k, k, 1, 10)", Inexact::Error)
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "integrate is not supported by this arithmetic",
+            evaluate("integrate(x, x, 0, 1)", Inexact::Float)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn evaluate_rational_bounds_the_size_of_powers() {
+        let error = evaluate("(10 ^ 100000) ^ 100000", Inexact::Error).unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert_eq!(
+            "Exponent 100000 is too large to evaluate exactly",
+            error.to_string()
+        );
+        assert!(evaluate("(10 ^ 100000) ^ 100000", Inexact::Float)
+            .unwrap()
+            .to_f64()
+            .is_infinite());
+        assert!(evaluate("2 ^ 100000", Inexact::Error).unwrap().is_exact());
+    }
+}
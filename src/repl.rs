@@ -0,0 +1,228 @@
+use crate::ast::nodes::Variables;
+use crate::ast::tree::to_tree;
+use crate::parse_expression_shunting_yard;
+use crate::tokenizer::tokenize::Tokenizer;
+use crate::tokenizer::tokens::{IAngleUnit, IFunctions, IToken};
+use std::io::{Error, ErrorKind};
+
+/// The variable bound to the result of the previous evaluation
+pub const ANSWER_VARIABLE: &str = "ans";
+
+/// The result of executing a line in a Session
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// The value of an expression or assignment
+    Value(f64),
+    /// The output of a meta-command
+    Text(String),
+    /// The session was ended by the user
+    Quit,
+}
+
+/// The state of an interactive session. Variables assigned with
+/// `let` and the previous result bound to `ans` are kept for the
+/// lifetime of the session, as is the angle unit set with `:angle`.
+#[derive(Debug, Default)]
+pub struct Session {
+    variables: Variables,
+    angle_unit: IAngleUnit,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Executes a single line of input, which is either a meta-command
+    /// starting with a colon, a `let <name> = <expr>` assignment or an
+    /// expression to be evaluated.
+    pub fn execute(&mut self, line: &str) -> Result<Reply, Error> {
+        let line = line.trim();
+
+        if let Some(command) = line.strip_prefix(':') {
+            return self.execute_command(command);
+        }
+
+        if let Some(assignment) = line.strip_prefix("let ") {
+            let (name, expr) = assignment.split_once('=').ok_or_else(|| {
+                Error::new(ErrorKind::InvalidInput, "Expected let <name> = <expr>")
+            })?;
+            let name = Self::validate_name(name.trim())?;
+            let value = self.evaluate(expr)?;
+
+            self.variables.insert(name, value);
+
+            return Ok(Reply::Value(value));
+        }
+
+        Ok(Reply::Value(self.evaluate(line)?))
+    }
+
+    /// Returns the variables of the session
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// Evaluates expr with the variables of the session and binds the
+    /// result to ans
+    fn evaluate(&mut self, expr: &str) -> Result<f64, Error> {
+        let value = parse_expression_shunting_yard(expr)?
+            .try_evaluate_numeric_in(&self.variables, self.angle_unit)?;
+
+        self.variables.insert(String::from(ANSWER_VARIABLE), value);
+
+        Ok(value)
+    }
+
+    fn execute_command(&mut self, command: &str) -> Result<Reply, Error> {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+
+        match name {
+            "quit" | "q" => Ok(Reply::Quit),
+            "vars" => {
+                let mut names: Vec<&String> = self.variables.keys().collect();
+
+                names.sort();
+
+                Ok(Reply::Text(
+                    names
+                        .iter()
+                        .map(|name| format!("{} = {}", name, self.variables[*name]))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                ))
+            }
+            "funcs" => Ok(Reply::Text(
+                IFunctions::ALL
+                    .iter()
+                    .map(|function| {
+                        let parameters: Vec<String> = match function.arity() {
+                            _ if function.binds_variable() => {
+                                let values = match function {
+                                    IFunctions::Solve => vec!["guess"],
+                                    _ => vec!["from", "to"],
+                                };
+
+                                ["expr", "var"]
+                                    .into_iter()
+                                    .chain(values)
+                                    .map(String::from)
+                                    .collect()
+                            }
+                            1 => vec![String::from("x")],
+                            arity => (1..=arity)
+                                .map(|position| format!("x{}", position))
+                                .collect(),
+                        };
+
+                        format!("{}({})", function.name(), parameters.join(", "))
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )),
+            "angle" if argument.is_empty() => {
+                Ok(Reply::Text(String::from(self.angle_unit.suffix())))
+            }
+            "angle" => {
+                self.angle_unit = IAngleUnit::from_suffix(argument.trim()).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown angle unit: {}", argument),
+                    )
+                })?;
+
+                Ok(Reply::Text(String::from(self.angle_unit.suffix())))
+            }
+            "ast" => {
+                let parser = parse_expression_shunting_yard(argument)?;
+
+                Ok(Reply::Text(to_tree(parser.ast()).trim_end().to_string()))
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown command: :{}", name),
+            )),
+        }
+    }
+
+    /// Ensures name is tokenized as a variable so it can be referred to
+    /// in later expressions
+    fn validate_name(name: &str) -> Result<String, Error> {
+        let tokens: Vec<_> = Tokenizer::build(name).into_tokens()?.collect();
+
+        match tokens.as_slice() {
+            [token] if token.token_type == IToken::Var && name != ANSWER_VARIABLE => {
+                Ok(name.to_string())
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid variable name: {}", name),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_evaluates_expressions_and_binds_ans() {
+        let mut session = Session::new();
+
+        assert_eq!(Reply::Value(4.0), session.execute("2+2").unwrap());
+        assert_eq!(Reply::Value(8.0), session.execute("ans * 2").unwrap());
+    }
+
+    #[test]
+    fn execute_keeps_assigned_variables() {
+        let mut session = Session::new();
+
+        assert_eq!(Reply::Value(3.0), session.execute("let x = 1 + 2").unwrap());
+        assert_eq!(Reply::Value(9.0), session.execute("x ^ 2").unwrap());
+        assert_eq!(
+            Reply::Text(String::from("ans = 9\nx = 3")),
+            session.execute(":vars").unwrap()
+        );
+    }
+
+    #[test]
+    fn execute_returns_error_for_unbound_variable_and_invalid_name() {
+        let mut session = Session::new();
+
+        assert_eq!(
+            ErrorKind::NotFound,
+            session.execute("y + 1").unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            session.execute("let pi = 3").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn execute_runs_meta_commands() {
+        let mut session = Session::new();
+
+        assert_eq!(
+            Reply::Text(String::from(
+                "max(x1, x2)\nmin(x1, x2)\nsqrt(x)\nabs(x)\narg(x)\nconj(x)\nre(x)\nim(x)\nexp(x)\n\
+                 sin(x)\ncos(x)\ntan(x)\nasin(x)\nacos(x)\natan(x)\ndate(x)\nduration(x)\n\
+                 days_between(x1, x2)\nintegrate(expr, var, from, to)\nsolve(expr, var, guess)\n\
+                 sum(expr, var, from, to)\nprod(expr, var, from, to)"
+            )),
+            session.execute(":funcs").unwrap()
+        );
+        assert_eq!(
+            Reply::Text(String::from("+\n├── 1\n└── 2")),
+            session.execute(":ast 1 + 2").unwrap()
+        );
+        assert_eq!(
+            Reply::Text(String::from("deg")),
+            session.execute(":angle deg").unwrap()
+        );
+        assert_eq!(Reply::Value(1.0), session.execute("sin(90)").unwrap());
+        assert_eq!(Reply::Value(90.0), session.execute("asin(1)").unwrap());
+        assert_eq!(Reply::Quit, session.execute(":quit").unwrap());
+    }
+}
@@ -1,10 +1,31 @@
-use crate::ast::nodes::{Add, AstNode, Const, Div, Fun, LPar, Mul, Num, Pow, Sub};
-use crate::tokenizer::tokens::{IAssociativity, IConstants, IFunctions, IToken, Token};
+use crate::ast::nodes::{
+    new_node, Add, Angle, AstNode, Const, Currency, Div, Eq, Fun, Imag, LPar, Mul, Neg, Num, Pow,
+    Quantity, Str, Sub, To, Unit, Var, Variables,
+};
+use crate::currency::{evaluate_currency, ExchangeRateProvider, Money};
+use crate::datetime::{evaluate_temporal, Temporal};
+use crate::decimal::{evaluate_decimal, Decimal, DecimalContext};
+use crate::dual::evaluate_gradient;
+use crate::ieee::{evaluate_ieee, Evaluation, IeeePolicy};
+use crate::integer::{evaluate_integer, Division};
+use crate::interval::{evaluate_interval, Interval};
+use crate::modular::evaluate_modular;
+use crate::numeric::{evaluate_numeric, evaluate_numeric_in, unsupported_equations, Numeric};
+use crate::rational::{evaluate_rational, Inexact, RationalValue};
+use crate::shunting_yard_parser::trace::{TraceAction, TraceStep};
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IAngleUnit, IAssociativity, IConstants, IFunctions, IToken, Token};
+use crate::units::{self, evaluate_quantity};
+use num_bigint::BigInt;
+use num_traits::{CheckedDiv, CheckedRem, PrimInt, Signed};
+use std::fmt;
+use std::io::{Error, ErrorKind};
 use std::process;
 use std::vec::IntoIter;
 
 pub struct ShuntingYardParser {
     ast: Box<dyn AstNode>,
+    trace: Vec<TraceStep>,
 }
 
 impl ShuntingYardParser {
@@ -14,175 +35,568 @@ impl ShuntingYardParser {
     /// for expression parsing.
     /// The modification is for generating an AST(of sort) instead
     /// of producing result in Reverse Polish notation.
+    /// Exits the process when the token_stream is not a valid expression.
     pub fn build(token_stream: &mut IntoIter<Token>) -> Self {
+        Self::try_build(token_stream).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(1);
+        })
+    }
+
+    /// Builds the ShuntingYardParser like build but returns an error
+    /// instead of exiting when the token_stream is not a valid expression.
+    pub fn try_build(token_stream: &mut IntoIter<Token>) -> Result<Self, Error> {
+        Self::build_with_trace(token_stream, &mut None)
+    }
+
+    /// Builds the ShuntingYardParser like build while recording a
+    /// TraceStep for every push, pop and emit the algorithm performs.
+    /// The steps are accessible through the trace method.
+    pub fn build_traced(token_stream: &mut IntoIter<Token>) -> Self {
+        Self::try_build_traced(token_stream).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(1);
+        })
+    }
+
+    /// Builds the ShuntingYardParser like build_traced but returns an error
+    /// instead of exiting when the token_stream is not a valid expression.
+    pub fn try_build_traced(token_stream: &mut IntoIter<Token>) -> Result<Self, Error> {
+        let mut trace = Some(vec![]);
+        let mut parser = Self::build_with_trace(token_stream, &mut trace)?;
+
+        parser.trace = trace.unwrap();
+        Ok(parser)
+    }
+
+    fn build_with_trace(
+        token_stream: &mut IntoIter<Token>,
+        trace: &mut Option<Vec<TraceStep>>,
+    ) -> Result<Self, Error> {
         let mut value_stack: Vec<Box<dyn AstNode>> = Vec::new();
         let mut operator_stack: Vec<Box<dyn AstNode>> = Vec::new();
+        // the equations before the last semicolon and the first semicolon
+        let mut equations: Vec<Box<dyn AstNode>> = Vec::new();
+        let mut system = None;
+        // whether an operand or an operator is due, and for every open
+        // parenthesis the function it calls and its arguments so far
+        let mut expect_operand = true;
+        let mut calls: Vec<Option<(Token, usize)>> = Vec::new();
+        let mut previous: Option<Token> = None;
 
-        let handle_missing_value = || {
-            eprintln!("Imbalance input supplied");
-            process::exit(1);
-        };
+        for token in token_stream.by_ref() {
+            let current = Some(&token);
+
+            expect_operand = Self::check_position(&token, previous.as_ref(), expect_operand)?;
 
-        while let Some(token) = token_stream.next() {
             match token.token_type {
-                IToken::Num => value_stack.push(Box::new(Num { token })),
+                IToken::Num => value_stack.push(Box::new(Num {
+                    token: token.clone(),
+                })),
                 IToken::Const(const_type) => match const_type {
-                    IConstants::Pi => value_stack.push(Box::new(Const { token })),
-                },
-                IToken::Fun(function_type) => match function_type {
-                    IFunctions::Min => operator_stack.push(Box::new(Fun { token })),
-                    IFunctions::Max => operator_stack.push(Box::new(Fun { token })),
+                    IConstants::Pi => value_stack.push(Box::new(Const {
+                        token: token.clone(),
+                    })),
                 },
-                IToken::Add | IToken::Sub | IToken::Div | IToken::Mul | IToken::Pow => {
+                IToken::Var => value_stack.push(Box::new(Var {
+                    token: token.clone(),
+                })),
+                IToken::Imag => value_stack.push(Box::new(Imag {
+                    token: token.clone(),
+                })),
+                IToken::Angle(_) => value_stack.push(Box::new(Angle {
+                    token: token.clone(),
+                })),
+                IToken::Quantity => value_stack.push(Box::new(Quantity {
+                    token: token.clone(),
+                })),
+                IToken::Currency => value_stack.push(Box::new(Currency {
+                    token: token.clone(),
+                })),
+                IToken::Unit => value_stack.push(Box::new(Unit {
+                    token: token.clone(),
+                })),
+                IToken::Str => value_stack.push(Box::new(Str {
+                    token: token.clone(),
+                })),
+                IToken::Fun(_) => operator_stack.push(Box::new(Fun {
+                    token: token.clone(),
+                    children: vec![],
+                })),
+                IToken::Add
+                | IToken::Sub
+                | IToken::Div
+                | IToken::Mul
+                | IToken::Pow
+                | IToken::Neg
+                | IToken::To
+                | IToken::Eq => {
                     while !operator_stack.is_empty()
                         && operator_stack.last().unwrap().token_type() != IToken::LPar
                         && operator_stack.last().unwrap().precedence() >= token.precedence
                         && token.associativity.unwrap() == IAssociativity::Left
                     {
-                        Self::evaluate_operator(&mut value_stack, &mut operator_stack);
+                        Self::reduce_operator(
+                            &mut value_stack,
+                            &mut operator_stack,
+                            current,
+                            trace,
+                        )?;
                     }
 
+                    let token = token.clone();
+                    let children = vec![];
+
                     match token.token_type {
-                        IToken::Add => operator_stack.push(Box::new(Add { token })),
-                        IToken::Sub => operator_stack.push(Box::new(Sub { token })),
-                        IToken::Div => operator_stack.push(Box::new(Div { token })),
-                        IToken::Mul => operator_stack.push(Box::new(Mul { token })),
-                        IToken::Pow => operator_stack.push(Box::new(Pow { token })),
+                        IToken::Add => operator_stack.push(Box::new(Add { token, children })),
+                        IToken::Sub => operator_stack.push(Box::new(Sub { token, children })),
+                        IToken::Div => operator_stack.push(Box::new(Div { token, children })),
+                        IToken::Mul => operator_stack.push(Box::new(Mul { token, children })),
+                        IToken::Pow => operator_stack.push(Box::new(Pow { token, children })),
+                        IToken::Neg => operator_stack.push(Box::new(Neg { token, children })),
+                        IToken::To => operator_stack.push(Box::new(To { token, children })),
+                        IToken::Eq => operator_stack.push(Box::new(Eq { token, children })),
                         _ => panic!("Unidentified token {:#?}", token),
                     }
                 }
-                IToken::LPar => operator_stack.push(Box::new(LPar { token })),
+                IToken::LPar => {
+                    calls.push(
+                        previous
+                            .take()
+                            .filter(|previous| matches!(previous.token_type, IToken::Fun(_)))
+                            .map(|function| (function, 1)),
+                    );
+                    operator_stack.push(Box::new(LPar {
+                        token: token.clone(),
+                    }));
+                }
+                // the argument before the comma is complete, so its
+                // operators are reduced up to the parenthesis of the call
+                IToken::Comma => {
+                    match calls.last_mut() {
+                        Some(Some((_, arguments))) => *arguments += 1,
+                        _ => return Err(Self::misplaced(String::from("Unexpected comma"), &token)),
+                    }
+
+                    while !operator_stack.is_empty()
+                        && operator_stack.last().unwrap().token_type() != IToken::LPar
+                    {
+                        Self::reduce_operator(
+                            &mut value_stack,
+                            &mut operator_stack,
+                            current,
+                            trace,
+                        )?;
+                    }
+                }
+                // the equation before the semicolon is complete, so every
+                // operator is reduced and it is set aside for the system
+                IToken::Semicolon => {
+                    equations.push(Self::reduce_all(
+                        &mut value_stack,
+                        &mut operator_stack,
+                        current,
+                        trace,
+                    )?);
+                    system.get_or_insert(token.clone());
+                }
                 IToken::RPar => {
                     while !operator_stack.is_empty()
                         && operator_stack.last().unwrap().token_type() != IToken::LPar
                     {
-                        Self::evaluate_operator(&mut value_stack, &mut operator_stack);
+                        Self::reduce_operator(
+                            &mut value_stack,
+                            &mut operator_stack,
+                            current,
+                            trace,
+                        )?;
                     }
 
                     if !operator_stack.is_empty()
                         && operator_stack.last().unwrap().token_type() == IToken::LPar
                     {
-                        operator_stack.pop();
+                        let left_parenthesis = operator_stack.pop().unwrap();
+
+                        Self::record(
+                            trace,
+                            current,
+                            TraceAction::Discard,
+                            &left_parenthesis.token(),
+                            &operator_stack,
+                            &value_stack,
+                        );
                     } else {
-                        panic!("Expression has imbalanced parenthesis");
+                        return Err(Self::imbalanced_parenthesis());
+                    }
+
+                    if let Some(Some((function, arguments))) = calls.pop() {
+                        Self::check_arguments(&function, arguments)?;
                     }
 
                     if !operator_stack.is_empty()
-                        && operator_stack.last().unwrap().token_type()
-                            == IToken::Fun(IFunctions::Max)
-                    {
-                        let _fn_node = operator_stack.pop().unwrap();
-                        let arg_2 = value_stack.pop().unwrap_or_else(handle_missing_value);
-                        let arg_1 = value_stack.pop().unwrap_or_else(handle_missing_value);
-
-                        value_stack.push(Box::new(Num {
-                            token: Token {
-                                token_type: IToken::Num,
-                                associativity: None,
-                                precedence: None,
-                                literal: Some(if arg_1.evaluate() > arg_2.evaluate() {
-                                    arg_1.evaluate()
-                                } else {
-                                    arg_2.evaluate()
-                                }),
-                            },
-                        }));
-                    } else if !operator_stack.is_empty()
-                        && operator_stack.last().unwrap().token_type()
-                            == IToken::Fun(IFunctions::Min)
+                        && matches!(operator_stack.last().unwrap().token_type(), IToken::Fun(_))
                     {
-                        let _fn_node = operator_stack.pop().unwrap();
-                        let arg_2 = value_stack.pop().unwrap_or_else(handle_missing_value);
-                        let arg_1 = value_stack.pop().unwrap_or_else(handle_missing_value);
-
-                        value_stack.push(Box::new(Num {
-                            token: Token {
-                                token_type: IToken::Num,
-                                associativity: None,
-                                precedence: None,
-                                literal: Some(if arg_1.evaluate() < arg_2.evaluate() {
-                                    arg_1.evaluate()
-                                } else {
-                                    arg_2.evaluate()
-                                }),
-                            },
-                        }));
+                        Self::reduce_operator(
+                            &mut value_stack,
+                            &mut operator_stack,
+                            current,
+                            trace,
+                        )?;
                     }
                 }
             }
+
+            match token.token_type {
+                IToken::Num
+                | IToken::Const(_)
+                | IToken::Var
+                | IToken::Imag
+                | IToken::Angle(_)
+                | IToken::Quantity
+                | IToken::Currency
+                | IToken::Unit
+                | IToken::Str => Self::record(
+                    trace,
+                    current,
+                    TraceAction::Emit,
+                    &token,
+                    &operator_stack,
+                    &value_stack,
+                ),
+                IToken::RPar | IToken::Comma | IToken::Semicolon => (),
+                _ => Self::record(
+                    trace,
+                    current,
+                    TraceAction::Push,
+                    &token,
+                    &operator_stack,
+                    &value_stack,
+                ),
+            }
+
+            previous = Some(token);
+        }
+
+        if let Some(token) = previous.filter(|_| expect_operand) {
+            return Err(Self::misplaced(
+                format!("Expected an operand after {}", token),
+                &token,
+            ));
+        }
+
+        let ast = Self::reduce_all(&mut value_stack, &mut operator_stack, None, trace)?;
+
+        match system {
+            Some(token) => {
+                equations.push(ast);
+                Ok(Self {
+                    ast: new_node(token, equations),
+                    trace: vec![],
+                })
+            }
+            None => Ok(Self { ast, trace: vec![] }),
         }
+    }
 
+    /// Reduces every operator left on the operator_stack at the end of
+    /// an expression or equation and returns the tree of the expression
+    fn reduce_all(
+        value_stack: &mut Vec<Box<dyn AstNode>>,
+        operator_stack: &mut Vec<Box<dyn AstNode>>,
+        current: Option<&Token>,
+        trace: &mut Option<Vec<TraceStep>>,
+    ) -> Result<Box<dyn AstNode>, Error> {
         while !operator_stack.is_empty() {
             if operator_stack.last().unwrap().token_type() == IToken::LPar {
-                panic!("Expression has imbalanced parenthesis");
+                return Err(Self::imbalanced_parenthesis());
             }
 
-            Self::evaluate_operator(&mut value_stack, &mut operator_stack);
+            Self::reduce_operator(value_stack, operator_stack, current, trace)?;
         }
 
-        Self {
-            ast: value_stack.pop().unwrap(),
+        if value_stack.len() > 1 {
+            return Err(Self::imbalanced_input());
         }
+
+        value_stack
+            .pop()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Empty expression supplied"))
     }
 
     /// A helper for processing operators as they are popped from the operator_stack
-    /// Pops the operator, gets its operands from the value_stack and returns
-    /// a Num node back to the value stack representing the result of the operation
-    fn evaluate_operator(
+    /// Pops the operator, takes its operands from the value_stack, attaches
+    /// them to the operator and pushes the operator node back to the value
+    /// stack as the root of the subtree for the operation.
+    fn reduce_operator(
         value_stack: &mut Vec<Box<dyn AstNode>>,
         operator_stack: &mut Vec<Box<dyn AstNode>>,
-    ) {
-        let handle_missing_value = || {
-            eprintln!("Imbalance input supplied");
-            process::exit(1);
+        current: Option<&Token>,
+        trace: &mut Option<Vec<TraceStep>>,
+    ) -> Result<(), Error> {
+        let mut current_op = operator_stack.pop().unwrap();
+        let arity = match current_op.token_type() {
+            IToken::Add
+            | IToken::Sub
+            | IToken::Div
+            | IToken::Mul
+            | IToken::Pow
+            | IToken::To
+            | IToken::Eq => 2,
+            IToken::Neg => 1,
+            IToken::Fun(function) => function.arity(),
+            _ => panic!("Unidentified token {:#?}", current_op.token()),
         };
 
-        let current_op = operator_stack.pop().unwrap();
-        let arg_2 = value_stack.pop().unwrap_or_else(handle_missing_value);
-        let arg_1 = value_stack.pop().unwrap_or_else(handle_missing_value);
+        if value_stack.len() < arity {
+            return Err(Self::imbalanced_input());
+        }
 
-        match current_op.token_type() {
-            IToken::Add => value_stack.push(Box::new(Num {
-                token: Token {
-                    token_type: IToken::Num,
-                    associativity: None,
-                    precedence: None,
-                    literal: Some(arg_1.evaluate() + arg_2.evaluate()),
-                },
-            })),
-            IToken::Sub => value_stack.push(Box::new(Num {
-                token: Token {
-                    token_type: IToken::Num,
-                    associativity: None,
-                    precedence: None,
-                    literal: Some(arg_1.evaluate() - arg_2.evaluate()),
-                },
-            })),
-            IToken::Div => value_stack.push(Box::new(Num {
-                token: Token {
-                    token_type: IToken::Num,
-                    associativity: None,
-                    precedence: None,
-                    literal: Some(arg_1.evaluate() / arg_2.evaluate()),
-                },
-            })),
-            IToken::Mul => value_stack.push(Box::new(Num {
-                token: Token {
-                    token_type: IToken::Num,
-                    associativity: None,
-                    precedence: None,
-                    literal: Some(arg_1.evaluate() * arg_2.evaluate()),
-                },
-            })),
-            IToken::Pow => value_stack.push(Box::new(Num {
-                token: Token {
-                    token_type: IToken::Num,
-                    associativity: None,
-                    precedence: None,
-                    literal: Some(arg_1.evaluate().powf(arg_2.evaluate())),
+        let mut operands = value_stack.split_off(value_stack.len() - arity);
+
+        // equations are only joined into systems by semicolons
+        if operands
+            .iter()
+            .any(|operand| operand.token_type() == IToken::Eq)
+        {
+            return Err(Self::misplaced(
+                format!("An equation cannot be an operand of {}", current_op.token()),
+                &current_op.token(),
+            ));
+        }
+
+        if let IToken::Fun(function) = current_op.token_type() {
+            if function.binds_variable() {
+                Self::bind_variable(function, &mut operands)?;
+            }
+        }
+
+        current_op.attach(operands);
+        value_stack.push(current_op);
+
+        Self::record(
+            trace,
+            current,
+            TraceAction::Pop,
+            &value_stack.last().unwrap().token(),
+            operator_stack,
+            value_stack,
+        );
+
+        Ok(())
+    }
+
+    /// Checks that the second operand of function, which binds a variable
+    /// in its first operand, is a variable. The imaginary unit i is read
+    /// as a variable in both operands then, so sums can run over i.
+    fn bind_variable(function: IFunctions, operands: &mut [Box<dyn AstNode>]) -> Result<(), Error> {
+        let variable = operands[1].token();
+
+        match variable.token_type {
+            IToken::Var => Ok(()),
+            IToken::Imag => {
+                operands[0] = Self::imaginary_to_variable(operands[0].as_ref());
+                operands[1] = Self::imaginary_to_variable(operands[1].as_ref());
+                Ok(())
+            }
+            _ => Err(with_span(
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "The second argument of {} must be a variable",
+                        function.name()
+                    ),
+                ),
+                variable.span,
+            )),
+        }
+    }
+
+    /// Copies the tree rooted at node with the imaginary units i replaced
+    /// by variables named i, so 2i, which is 2 * i, multiplies the variable
+    fn imaginary_to_variable(node: &dyn AstNode) -> Box<dyn AstNode> {
+        let token = node.token();
+
+        match token.token_type {
+            IToken::Imag => new_node(
+                Token {
+                    token_type: IToken::Var,
+                    literal: None,
+                    ..token
                 },
-            })),
-            _ => panic!("Unidentified token {:#?}", current_op.token()),
+                vec![],
+            ),
+            _ => new_node(
+                token,
+                node.children()
+                    .iter()
+                    .map(|child| Self::imaginary_to_variable(child.as_ref()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Checks that token may follow previous, expect_operand telling
+    /// whether an operand or an operator is due, and returns whether an
+    /// operand is due after token. Functions must be followed by the
+    /// parenthesis of their call.
+    fn check_position(
+        token: &Token,
+        previous: Option<&Token>,
+        expect_operand: bool,
+    ) -> Result<bool, Error> {
+        if let Some(IToken::Fun(function)) = previous.map(|previous| previous.token_type) {
+            if token.token_type != IToken::LPar {
+                return Err(Self::misplaced(
+                    format!("Expected ( after {}, found {}", function.name(), token),
+                    token,
+                ));
+            }
+        }
+
+        // whether the token takes the place of an operand, and whether an
+        // operand is due after it
+        let (is_operand, operand_next) = match token.token_type {
+            IToken::Num
+            | IToken::Const(_)
+            | IToken::Var
+            | IToken::Imag
+            | IToken::Angle(_)
+            | IToken::Quantity
+            | IToken::Currency
+            | IToken::Unit
+            | IToken::Str => (true, false),
+            IToken::Fun(_) | IToken::LPar | IToken::Neg => (true, true),
+            IToken::RPar => (false, false),
+            _ => (false, true),
+        };
+
+        match (expect_operand, is_operand) {
+            (true, false) => Err(Self::misplaced(
+                format!("Expected an operand, found {}", token),
+                token,
+            )),
+            (false, true) => Err(Self::misplaced(
+                format!("Expected an operator, found {}", token),
+                token,
+            )),
+            _ => Ok(operand_next),
+        }
+    }
+
+    /// Checks that the call of the function token has as many arguments
+    /// as the function takes
+    fn check_arguments(function: &Token, arguments: usize) -> Result<(), Error> {
+        let IToken::Fun(name) = function.token_type else {
+            unreachable!("only functions are called");
+        };
+
+        match name.arity() {
+            arity if arity == arguments => Ok(()),
+            1 => Err(Self::misplaced(
+                format!("{} takes 1 argument, found {}", name.name(), arguments),
+                function,
+            )),
+            arity => Err(Self::misplaced(
+                format!(
+                    "{} takes {} arguments, found {}",
+                    name.name(),
+                    arity,
+                    arguments
+                ),
+                function,
+            )),
+        }
+    }
+
+    /// The error for a token in a place the grammar does not allow, with
+    /// the span of the token
+    fn misplaced(message: String, token: &Token) -> Error {
+        with_span(Error::new(ErrorKind::InvalidInput, message), token.span)
+    }
+
+    fn imbalanced_input() -> Error {
+        Error::new(ErrorKind::InvalidInput, "Imbalance input supplied")
+    }
+
+    fn imbalanced_parenthesis() -> Error {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Expression has imbalanced parenthesis",
+        )
+    }
+
+    /// Appends a snapshot of the stacks to trace when tracing is enabled
+    fn record(
+        trace: &mut Option<Vec<TraceStep>>,
+        current: Option<&Token>,
+        action: TraceAction,
+        subject: &Token,
+        operator_stack: &[Box<dyn AstNode>],
+        value_stack: &[Box<dyn AstNode>],
+    ) {
+        if let Some(steps) = trace {
+            steps.push(TraceStep::new(
+                current,
+                action,
+                subject,
+                operator_stack,
+                value_stack,
+            ));
+        }
+    }
+
+    /// Returns the root of the AST built from the token stream
+    pub fn ast(&self) -> &dyn AstNode {
+        self.ast.as_ref()
+    }
+
+    /// Returns the steps recorded by build_traced.
+    /// The trace is empty for parsers created with build.
+    pub fn trace(&self) -> &[TraceStep] {
+        &self.trace
+    }
+
+    /// Returns the names of the variables used in the expression
+    /// sorted and without duplicates. Variables bound by functions like
+    /// sum are only listed where they are used outside those functions.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = vec![];
+
+        Self::add_variables(self.ast.as_ref(), &[], &mut names);
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Appends the variables used in the tree rooted at node which are
+    /// not in bound to names
+    fn add_variables(node: &dyn AstNode, bound: &[String], names: &mut Vec<String>) {
+        let children = node.children();
+
+        match node.token_type() {
+            IToken::Var => {
+                let name = node.token().lexeme.unwrap();
+
+                if !bound.contains(&name) {
+                    names.push(name);
+                }
+            }
+            IToken::Fun(function) if function.binds_variable() => {
+                let mut inner = bound.to_vec();
+
+                inner.push(children[1].token().lexeme.unwrap());
+                Self::add_variables(children[0].as_ref(), &inner, names);
+
+                for child in &children[2..] {
+                    Self::add_variables(child.as_ref(), bound, names);
+                }
+            }
+            _ => {
+                for child in children {
+                    Self::add_variables(child.as_ref(), bound, names);
+                }
+            }
         }
     }
 
@@ -190,6 +604,147 @@ impl ShuntingYardParser {
     pub fn evaluate(&self) -> f64 {
         self.ast.evaluate()
     }
+
+    /// Evaluates the ast field using variables for the values of the
+    /// variables in the expression
+    pub fn evaluate_with(&self, variables: &Variables) -> f64 {
+        self.ast.evaluate_with(variables)
+    }
+
+    /// The ast field if it can be evaluated, an error at the equals sign
+    /// or semicolon if it is an equation or a system of them, whose
+    /// variables would otherwise be reported as unbound
+    pub fn evaluable(&self) -> Result<&dyn AstNode, Error> {
+        match self.ast.token_type() {
+            IToken::Eq | IToken::Semicolon => {
+                Err(with_span(unsupported_equations(), self.ast.token().span))
+            }
+            _ => Ok(self.ast.as_ref()),
+        }
+    }
+
+    /// Evaluates the ast field like evaluate_with but returns an error
+    /// instead of panicking when a variable has no value in variables
+    pub fn try_evaluate_with(&self, variables: &Variables) -> Result<f64, Error> {
+        self.try_evaluate_numeric(variables)
+    }
+
+    /// Evaluates the ast field with f64 taking the actions of policy for
+    /// division by zero, NaN, infinite results and domain errors, with
+    /// angles in angle_unit
+    pub fn try_evaluate_ieee(
+        &self,
+        variables: &Variables,
+        policy: &IeeePolicy,
+        angle_unit: IAngleUnit,
+    ) -> Result<Evaluation, Error> {
+        evaluate_ieee(self.evaluable()?, variables, policy, angle_unit)
+    }
+
+    /// Evaluates the ast field with the arithmetic of any Numeric type
+    pub fn try_evaluate_numeric<T: Numeric>(&self, variables: &Variables<T>) -> Result<T, Error> {
+        evaluate_numeric(self.evaluable()?, variables)
+    }
+
+    /// Evaluates the ast field like try_evaluate_numeric with the
+    /// arguments of trigonometric functions and the results of their
+    /// inverses in angle_unit
+    pub fn try_evaluate_numeric_in<T: Numeric>(
+        &self,
+        variables: &Variables<T>,
+        angle_unit: IAngleUnit,
+    ) -> Result<T, Error> {
+        evaluate_numeric_in(self.evaluable()?, variables, angle_unit)
+    }
+
+    /// Evaluates the ast field with exact rational arithmetic. Operations
+    /// without an exact result are handled as requested by inexact.
+    pub fn try_evaluate_rational(
+        &self,
+        variables: &Variables,
+        inexact: Inexact,
+    ) -> Result<RationalValue, Error> {
+        evaluate_rational(self.evaluable()?, variables, inexact)
+    }
+
+    /// Evaluates the ast field with decimal arithmetic rounding to the
+    /// precision of context
+    pub fn try_evaluate_decimal(
+        &self,
+        variables: &Variables,
+        context: &DecimalContext,
+    ) -> Result<Decimal, Error> {
+        evaluate_decimal(self.evaluable()?, variables, context)
+    }
+
+    /// Evaluates the ast field with checked arithmetic of an integer type
+    /// like i64 or i128, dividing as requested by division
+    pub fn try_evaluate_integer<T>(
+        &self,
+        variables: &Variables<T>,
+        division: Division,
+    ) -> Result<T, Error>
+    where
+        T: Numeric + PrimInt + Signed + CheckedDiv + CheckedRem + fmt::Display,
+    {
+        evaluate_integer(self.evaluable()?, variables, division)
+    }
+
+    /// Evaluates the ast field with arithmetic modulo modulus
+    pub fn try_evaluate_modular(
+        &self,
+        variables: &Variables<BigInt>,
+        modulus: &BigInt,
+    ) -> Result<BigInt, Error> {
+        evaluate_modular(self.evaluable()?, variables, modulus)
+    }
+
+    /// Evaluates the ast field with quantities carrying their dimension
+    /// through every operator, converting them with to and in
+    pub fn try_evaluate_quantity(
+        &self,
+        variables: &Variables<units::Quantity>,
+    ) -> Result<units::Quantity, Error> {
+        evaluate_quantity(self.evaluable()?, variables)
+    }
+
+    /// Evaluates the ast field with decimal amounts of money, converting
+    /// between currencies with the rates of provider
+    pub fn try_evaluate_currency(
+        &self,
+        variables: &Variables<Money>,
+        provider: &dyn ExchangeRateProvider,
+        context: &DecimalContext,
+    ) -> Result<Money, Error> {
+        evaluate_currency(self.evaluable()?, variables, provider, context)
+    }
+
+    /// Evaluates the ast field with dates, date times and durations
+    pub fn try_evaluate_temporal(
+        &self,
+        variables: &Variables<Temporal>,
+    ) -> Result<Temporal, Error> {
+        evaluate_temporal(self.evaluable()?, variables)
+    }
+
+    /// Evaluates the ast field with interval arithmetic. The result
+    /// bounds the value for every value of the intervals in variables.
+    pub fn try_evaluate_interval(
+        &self,
+        variables: &Variables<Interval>,
+    ) -> Result<Interval, Error> {
+        evaluate_interval(self.evaluable()?, variables)
+    }
+
+    /// Evaluates the ast field with dual numbers, returning its value and
+    /// its gradient with respect to the variables in with_respect_to
+    pub fn try_evaluate_gradient(
+        &self,
+        variables: &Variables,
+        with_respect_to: &[&str],
+    ) -> Result<(f64, Vec<f64>), Error> {
+        evaluate_gradient(self.evaluable()?, variables, with_respect_to)
+    }
 }
 
 #[cfg(test)]
@@ -205,18 +760,24 @@ mod tests {
                     associativity: None,
                     precedence: None,
                     literal: Some(2.0),
+                    lexeme: None,
+                    span: None,
                 },
                 Token {
                     token_type: IToken::Add,
                     associativity: Some(IAssociativity::Left),
                     precedence: Some(2),
                     literal: None,
+                    lexeme: None,
+                    span: None,
                 },
                 Token {
                     token_type: IToken::Num,
                     associativity: None,
                     precedence: None,
                     literal: Some(2.0),
+                    lexeme: None,
+                    span: None,
                 },
             ]
             .into_iter(),
@@ -235,18 +796,24 @@ mod tests {
                         associativity: None,
                         precedence: None,
                         literal: Some(2.0),
+                        lexeme: None,
+                        span: None,
                     },
                     Token {
                         token_type: IToken::Add,
                         associativity: Some(IAssociativity::Left),
                         precedence: Some(2),
                         literal: None,
+                        lexeme: None,
+                        span: None,
                     },
                     Token {
                         token_type: IToken::Num,
                         associativity: None,
                         precedence: None,
                         literal: Some(2.0),
+                        lexeme: None,
+                        span: None,
                     },
                 ]
                 .into_iter()
@@ -255,4 +822,85 @@ mod tests {
             4.0
         )
     }
+
+    #[test]
+    fn unary_minus_binds_looser_than_exponents() {
+        let evaluate = |expr: &str| {
+            crate::parse_expression_shunting_yard(expr)
+                .unwrap()
+                .evaluate()
+        };
+
+        assert_eq!(-4.0, evaluate("-2 ^ 2"));
+        assert_eq!(-6.0, evaluate("2 * -3"));
+        assert_eq!(0.5, evaluate("2 ^ -1"));
+        assert_eq!(2.0, evaluate("1 - -1"));
+        assert_eq!(1.0, evaluate("max(1, -2)"));
+        assert_eq!(-2.0, evaluate("min(1, -2)"));
+    }
+
+    #[test]
+    fn semicolons_separate_equations_into_a_system() {
+        let build = |expr: &str| crate::parse_expression_shunting_yard(expr);
+        let parser = build("x + y = 10; x - y = -2; 2x = y").unwrap();
+        let equations = parser.ast().children();
+
+        assert_eq!(IToken::Semicolon, parser.ast().token_type());
+        assert_eq!(3, equations.len());
+        assert!(equations
+            .iter()
+            .all(|equation| equation.token_type() == IToken::Eq));
+        assert_eq!(IToken::Neg, equations[1].children()[1].token_type());
+        assert_eq!(vec!["x", "y"], parser.variables());
+        assert!(build("(x = 1; y) = 2").is_err());
+        assert!(build("x = 1;").is_err());
+        assert_eq!(
+            "An equation cannot be an operand of = at 6..7",
+            build("x = 1 = 2").map(|_| ()).unwrap_err().to_string()
+        );
+        assert!(build("max(x = 1, 2)").is_err());
+    }
+
+    #[test]
+    fn tokens_out_of_place_and_wrong_argument_counts_are_rejected() {
+        let error = |expr: &str| {
+            crate::parse_expression_shunting_yard(expr)
+                .map(|_| ())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!("Expected an operator, found 3 at 2..3", error("2 3 +"));
+        assert_eq!("Expected an operand, found + at 0..1", error("+ 2 3"));
+        assert_eq!("Expected an operator, found max at 2..5", error("2 max(1)"));
+        assert_eq!("Unexpected comma at 6..7", error("max((1,2))"));
+        assert_eq!("max takes 2 arguments, found 1 at 0..3", error("max(1)"));
+        assert_eq!("sin takes 1 argument, found 2 at 0..3", error("sin(1, 2)"));
+        assert_eq!(
+            "Expected an operator, found 1 at 18..19",
+            error("integrate(x, x, 0 1)")
+        );
+        assert_eq!("Expected an operand after + at 2..3", error("1 +"));
+    }
+
+    #[test]
+    fn equations_are_rejected_before_their_variables_are_read() {
+        let parse = |expr: &str| crate::parse_expression_shunting_yard(expr).unwrap();
+        let error = parse("x = 1")
+            .try_evaluate_with(&Variables::new())
+            .unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert_eq!(
+            "Equations cannot be evaluated, only solved at 2..3",
+            error.to_string()
+        );
+        assert_eq!(
+            "Equations cannot be evaluated, only solved at 9..10",
+            parse("x + y = 1; x = 2")
+                .try_evaluate_rational(&Variables::new(), Inexact::Error)
+                .unwrap_err()
+                .to_string()
+        );
+    }
 }
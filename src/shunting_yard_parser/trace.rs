@@ -0,0 +1,146 @@
+use crate::ast::nodes::AstNode;
+use crate::ast::rpn::to_rpn;
+use crate::tokenizer::tokens::Token;
+use std::fmt;
+
+/// The action the parser took in a single step of the algorithm
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TraceAction {
+    /// A number or constant was emitted to the value stack
+    Emit,
+    /// An operator, function or left parenthesis was pushed to the operator stack
+    Push,
+    /// An operator or function was popped and applied to its operands
+    Pop,
+    /// A left parenthesis was popped and discarded at a right parenthesis
+    Discard,
+}
+
+/// A snapshot of the parser state after a single step
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// The token read in this step. None once the input is exhausted.
+    pub token: Option<Token>,
+    pub action: TraceAction,
+    /// The lexeme of the token the action was applied to
+    pub subject: String,
+    /// The operator stack from bottom to top
+    pub operator_stack: Vec<String>,
+    /// The value stack from bottom to top with every subtree written in
+    /// Reverse Polish notation, which together make up the output queue.
+    pub value_stack: Vec<String>,
+}
+
+impl TraceStep {
+    /// Takes a snapshot of the stacks of the parser
+    pub fn new(
+        token: Option<&Token>,
+        action: TraceAction,
+        subject: &Token,
+        operator_stack: &[Box<dyn AstNode>],
+        value_stack: &[Box<dyn AstNode>],
+    ) -> Self {
+        Self {
+            token: token.cloned(),
+            action,
+            subject: subject.to_string(),
+            operator_stack: operator_stack
+                .iter()
+                .map(|node| node.token().to_string())
+                .collect(),
+            value_stack: value_stack
+                .iter()
+                .map(|node| to_rpn(node.as_ref()))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for TraceAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceAction::Emit => write!(f, "emit"),
+            TraceAction::Push => write!(f, "push"),
+            TraceAction::Pop => write!(f, "pop"),
+            TraceAction::Discard => write!(f, "discard"),
+        }
+    }
+}
+
+/// Renders the steps as a plain text table with one row per step
+pub fn render_table(steps: &[TraceStep]) -> String {
+    let header = [
+        String::from("Step"),
+        String::from("Token"),
+        String::from("Action"),
+        String::from("Operator stack"),
+        String::from("Output"),
+    ];
+
+    let rows: Vec<[String; 5]> = steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            [
+                (index + 1).to_string(),
+                step.token
+                    .as_ref()
+                    .map(|token| token.to_string())
+                    .unwrap_or_else(|| String::from("(end)")),
+                format!("{} {}", step.action, step.subject),
+                step.operator_stack.join(" "),
+                step.value_stack.join(" | "),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|cell| cell.len());
+
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut table = String::new();
+
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+
+        table.push_str(cells.join(" | ").trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    #[test]
+    fn render_table_writes_a_row_per_step() {
+        let mut binding = Tokenizer::build("3 + 4 * 2");
+        let tokens = binding.tokens().as_mut().unwrap();
+        let parser = ShuntingYardParser::build_traced(tokens);
+
+        assert_eq!(
+            "Step | Token | Action | Operator stack | Output
+1    | 3     | emit 3 |                | 3
+2    | +     | push + | +              | 3
+3    | 4     | emit 4 | +              | 3 | 4
+4    | *     | push * | + *            | 3 | 4
+5    | 2     | emit 2 | + *            | 3 | 4 | 2
+6    | (end) | pop *  | +              | 3 | 4 2 *
+7    | (end) | pop +  |                | 3 4 2 * +
+",
+            render_table(parser.trace())
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use crate::ast::infix::to_infix;
+use crate::ast::nodes::{copy_node, new_node, AstNode};
+use crate::numeric;
+use crate::tokenizer::tokens::{IToken, Token};
+use std::cmp::Ordering;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// The most passes simplify_with makes over a tree. Every pass visits
+/// each node once, so simplification terminates even when the rules do
+/// not reach a tree they leave unchanged.
+pub const MAX_PASSES: usize = 16;
+
+/// The sets of rules simplify_with applies. The default applies all of
+/// them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rules {
+    /// Folds operators on numbers when the result is exact and merges
+    /// the numeric factors of products, moving them to the left
+    pub fold: bool,
+    /// Removes x + 0, x*1, x/1 and x^1, turns x*0 into 0, cancels --x
+    /// and moves negations out of products and into subtractions
+    pub identities: bool,
+    /// Combines terms differing only in their numeric factor, like
+    /// 2*x + 3*x, and factors with the same base, like x*x^2
+    pub like_terms: bool,
+    /// Regroups sums and products to the left and orders their operands:
+    /// terms by falling degree with numbers last, factors with numbers
+    /// first and otherwise alphabetically
+    pub order: bool,
+}
+
+/// A term of a sum, its numeric factor and the rest of the term. The
+/// rest of a number is None.
+type Term = (f64, Option<Box<dyn AstNode>>);
+
+/// A factor of a product, its base and its numeric exponent
+type Factor = (Box<dyn AstNode>, f64);
+
+impl Rules {
+    /// No rules, leaving the tree as it is
+    pub fn none() -> Self {
+        Rules {
+            fold: false,
+            identities: false,
+            like_terms: false,
+            order: false,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            fold: true,
+            identities: true,
+            like_terms: true,
+            order: true,
+        }
+    }
+}
+
+impl FromStr for Rules {
+    type Err = Error;
+
+    /// Parses a comma separated list of the rule sets fold, identities,
+    /// like-terms and order, or all for every rule set
+    fn from_str(rules: &str) -> Result<Self, Self::Err> {
+        let mut parsed = Rules::none();
+
+        for name in rules.split(',').map(str::trim) {
+            match name {
+                "all" => parsed = Rules::default(),
+                "fold" => parsed.fold = true,
+                "identities" => parsed.identities = true,
+                "like-terms" => parsed.like_terms = true,
+                "order" => parsed.order = true,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown rule set: {}", name),
+                    ))
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Simplifies the tree rooted at node with every rule set. Operators on
+/// numbers are folded when the result is exact, identities like x + 0,
+/// x*1 and x^1 are removed, like terms are combined and the operands of
+/// sums and products are ordered canonically.
+pub fn simplify(node: &dyn AstNode) -> Box<dyn AstNode> {
+    simplify_with(node, &Rules::default())
+}
+
+/// Simplifies the tree rooted at node with the rule sets in rules,
+/// repeating passes over the tree until it no longer changes or
+/// MAX_PASSES were made
+pub fn simplify_with(node: &dyn AstNode, rules: &Rules) -> Box<dyn AstNode> {
+    let mut simplified = pass(node, rules);
+
+    for _ in 1..MAX_PASSES {
+        let next = pass(simplified.as_ref(), rules);
+
+        if to_infix(next.as_ref()) == to_infix(simplified.as_ref()) {
+            break;
+        }
+
+        simplified = next;
+    }
+
+    simplified
+}
+
+/// Rewrites the tree rooted at node once, bottom up
+fn pass(node: &dyn AstNode, rules: &Rules) -> Box<dyn AstNode> {
+    let operands = node
+        .children()
+        .iter()
+        .map(|child| pass(child.as_ref(), rules))
+        .collect();
+
+    rewrite(node.token(), operands, rules)
+}
+
+/// Creates the node for token with the simplified operands, rewriting it
+/// while a rule in rules applies. Every rule makes the tree smaller or
+/// moves a number or negation towards the root, so rewriting terminates.
+fn rewrite(token: Token, mut operands: Vec<Box<dyn AstNode>>, rules: &Rules) -> Box<dyn AstNode> {
+    let values: Vec<Option<f64>> = operands
+        .iter()
+        .map(|operand| number(operand.as_ref()))
+        .collect();
+
+    if let Some(value) = fold(token.token_type, &values).filter(|_| rules.fold) {
+        return leaf(value);
+    }
+
+    let (a, b) = (
+        values.first().copied().flatten(),
+        values.get(1).copied().flatten(),
+    );
+
+    match (token.token_type, a, b) {
+        (IToken::Add, Some(0.0), _) if rules.identities => operands.pop().unwrap(),
+        (IToken::Add | IToken::Sub, _, Some(0.0)) if rules.identities => operands.swap_remove(0),
+        (IToken::Sub, Some(0.0), _) if rules.identities => rewrite(
+            Token::operator(IToken::Neg),
+            vec![operands.pop().unwrap()],
+            rules,
+        ),
+        (IToken::Add | IToken::Sub, _, _) if rules.identities && is_neg(operands[1].as_ref()) => {
+            let negated = inner(operands.pop().unwrap().as_ref());
+
+            rewrite(
+                Token::operator(opposite(token.token_type)),
+                vec![operands.pop().unwrap(), negated],
+                rules,
+            )
+        }
+        (IToken::Add | IToken::Sub, _, Some(value)) if rules.identities && value < 0.0 => {
+            operands.pop();
+
+            rewrite(
+                Token::operator(opposite(token.token_type)),
+                vec![operands.pop().unwrap(), leaf(-value)],
+                rules,
+            )
+        }
+        (IToken::Mul, Some(0.0), _) | (IToken::Mul, _, Some(0.0)) if rules.identities => leaf(0.0),
+        (IToken::Mul, Some(1.0), _) if rules.identities => operands.pop().unwrap(),
+        (IToken::Mul, _, Some(1.0)) if rules.identities => operands.swap_remove(0),
+        (IToken::Mul, Some(-1.0), _) if rules.identities => rewrite(
+            Token::operator(IToken::Neg),
+            vec![operands.pop().unwrap()],
+            rules,
+        ),
+        (IToken::Mul, _, Some(-1.0)) if rules.identities => rewrite(
+            Token::operator(IToken::Neg),
+            vec![operands.swap_remove(0)],
+            rules,
+        ),
+        (IToken::Mul, None, Some(_)) if rules.fold => {
+            operands.swap(0, 1);
+
+            rewrite(token, operands, rules)
+        }
+        (IToken::Mul, Some(factor), None)
+            if rules.fold
+                && leading_factor(operands[1].as_ref())
+                    .is_some_and(|leading| (factor * leading).is_finite()) =>
+        {
+            let product = operands[1].as_ref();
+            let merged = factor * leading_factor(product).unwrap();
+
+            rewrite(
+                token,
+                vec![leaf(merged), copy_node(product.children()[1].as_ref())],
+                rules,
+            )
+        }
+        // a + (b + c) is (a + b) + c and likewise for products
+        (IToken::Add | IToken::Mul, _, _)
+            if rules.order && operands[1].token_type() == token.token_type =>
+        {
+            let nested = operands.pop().unwrap();
+            let left = rewrite(
+                token.clone(),
+                vec![
+                    operands.pop().unwrap(),
+                    copy_node(nested.children()[0].as_ref()),
+                ],
+                rules,
+            );
+
+            rewrite(
+                token,
+                vec![left, copy_node(nested.children()[1].as_ref())],
+                rules,
+            )
+        }
+        (IToken::Mul | IToken::Div, _, _)
+            if rules.identities && operands.iter().any(|operand| is_neg(operand.as_ref())) =>
+        {
+            let negations = operands
+                .iter()
+                .filter(|operand| is_neg(operand.as_ref()))
+                .count();
+            let operands = operands
+                .iter()
+                .map(|operand| match is_neg(operand.as_ref()) {
+                    true => inner(operand.as_ref()),
+                    false => copy_node(operand.as_ref()),
+                })
+                .collect();
+            let product = rewrite(token, operands, rules);
+
+            // the signs of two negated operands cancel
+            match negations {
+                2 => product,
+                _ => rewrite(Token::operator(IToken::Neg), vec![product], rules),
+            }
+        }
+        // 0/0 has no value, so only other divisors give 0
+        (IToken::Div, Some(0.0), _) if rules.identities && b != Some(0.0) => leaf(0.0),
+        (IToken::Div, _, Some(1.0)) if rules.identities => operands.swap_remove(0),
+        (IToken::Pow, _, Some(1.0)) if rules.identities => operands.swap_remove(0),
+        (IToken::Pow, _, Some(0.0)) if rules.identities => leaf(1.0),
+        (IToken::Pow, Some(1.0), _) if rules.identities => leaf(1.0),
+        (IToken::Neg, _, _) if rules.identities && is_neg(operands[0].as_ref()) => {
+            inner(operands[0].as_ref())
+        }
+        _ => collect(new_node(token, operands), rules),
+    }
+}
+
+/// Folds an operator whose operands are all numbers. Divisions and powers
+/// are only folded when the result is an integer, so that 1/3 stays exact.
+fn fold(token_type: IToken, values: &[Option<f64>]) -> Option<f64> {
+    let values = values.iter().copied().collect::<Option<Vec<f64>>>()?;
+    let value = match token_type {
+        IToken::Add | IToken::Sub | IToken::Mul | IToken::Neg => {
+            numeric::apply(token_type, &values).ok()?
+        }
+        IToken::Div | IToken::Pow => numeric::apply(token_type, &values)
+            .ok()
+            .filter(|value: &f64| value.fract() == 0.0)?,
+        _ => return None,
+    };
+
+    Some(value).filter(|value| value.is_finite())
+}
+
+/// Rewrites a sum or product as a whole once its operands are rewritten,
+/// combining like terms and ordering operands as rules allow. The result
+/// is built without rewriting it again, so every node is collected once
+/// per pass.
+fn collect(node: Box<dyn AstNode>, rules: &Rules) -> Box<dyn AstNode> {
+    if !rules.like_terms && !rules.order {
+        return node;
+    }
+
+    match node.token_type() {
+        IToken::Add | IToken::Sub => sum(node.as_ref(), rules),
+        IToken::Mul => product(node.as_ref(), rules),
+        _ => node,
+    }
+}
+
+fn sum(node: &dyn AstNode, rules: &Rules) -> Box<dyn AstNode> {
+    let mut terms = vec![];
+
+    add_terms(node, 1.0, &mut terms);
+
+    if rules.like_terms {
+        let mut combined: Vec<Term> = vec![];
+
+        for (coefficient, rest) in terms {
+            let key = rest.as_deref().map(to_infix);
+
+            match combined
+                .iter_mut()
+                .find(|(_, other)| other.as_deref().map(to_infix) == key)
+            {
+                Some(like) => like.0 += coefficient,
+                None => combined.push((coefficient, rest)),
+            }
+        }
+
+        terms = combined
+            .into_iter()
+            .filter(|(coefficient, _)| *coefficient != 0.0)
+            .collect();
+    }
+
+    if rules.order {
+        terms.sort_by(compare_terms);
+    }
+
+    let mut terms = terms.into_iter();
+    let first = match terms.next() {
+        Some((coefficient, Some(rest))) if coefficient < 0.0 => new_node(
+            Token::operator(IToken::Neg),
+            vec![term(-coefficient, Some(rest))],
+        ),
+        Some((coefficient, rest)) => term(coefficient, rest),
+        None => return leaf(0.0),
+    };
+
+    terms.fold(first, |sum, (coefficient, rest)| match coefficient < 0.0 {
+        true => binary(IToken::Sub, sum, term(-coefficient, rest)),
+        false => binary(IToken::Add, sum, term(coefficient, rest)),
+    })
+}
+
+/// Splits the sum rooted at node into its terms, multiplying their
+/// numeric factors by sign
+fn add_terms(node: &dyn AstNode, sign: f64, terms: &mut Vec<Term>) {
+    let children = node.children();
+
+    match node.token_type() {
+        IToken::Add | IToken::Sub => {
+            let right = match node.token_type() {
+                IToken::Sub => -sign,
+                _ => sign,
+            };
+
+            add_terms(children[0].as_ref(), sign, terms);
+            add_terms(children[1].as_ref(), right, terms);
+        }
+        IToken::Neg => add_terms(children[0].as_ref(), -sign, terms),
+        _ => {
+            let mut factors = vec![];
+
+            add_operands(node, IToken::Mul, &mut factors);
+
+            let (numbers, others): (Vec<&dyn AstNode>, Vec<&dyn AstNode>) = factors
+                .into_iter()
+                .partition(|factor| number(*factor).is_some());
+            let coefficient: f64 = numbers.into_iter().filter_map(number).product();
+            let rest = others
+                .into_iter()
+                .map(copy_node)
+                .reduce(|product, factor| binary(IToken::Mul, product, factor));
+
+            terms.push((sign * coefficient, rest));
+        }
+    }
+}
+
+/// Collects the operands of the chain of token_type operators rooted at
+/// node from left to right
+fn add_operands<'a>(
+    node: &'a dyn AstNode,
+    token_type: IToken,
+    operands: &mut Vec<&'a dyn AstNode>,
+) {
+    match node.token_type() == token_type {
+        true => node
+            .children()
+            .iter()
+            .for_each(|child| add_operands(child.as_ref(), token_type, operands)),
+        false => operands.push(node),
+    }
+}
+
+/// Orders terms by falling degree and then alphabetically, numbers last
+fn compare_terms(a: &Term, b: &Term) -> Ordering {
+    match (&a.1, &b.1) {
+        (Some(a), Some(b)) => degree(b.as_ref())
+            .total_cmp(&degree(a.as_ref()))
+            .then_with(|| to_infix(a.as_ref()).cmp(&to_infix(b.as_ref()))),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// The degree of a term in all its variables together, like 3 for x^2*y.
+/// Functions and other leaves have degree 0.
+fn degree(node: &dyn AstNode) -> f64 {
+    let children = node.children();
+
+    match node.token_type() {
+        IToken::Var => 1.0,
+        IToken::Mul => degree(children[0].as_ref()) + degree(children[1].as_ref()),
+        IToken::Div => degree(children[0].as_ref()) - degree(children[1].as_ref()),
+        IToken::Neg => degree(children[0].as_ref()),
+        IToken::Pow => number(children[1].as_ref())
+            .map_or(0.0, |exponent| degree(children[0].as_ref()) * exponent),
+        _ => 0.0,
+    }
+}
+
+/// The term with coefficient as its numeric factor
+fn term(coefficient: f64, rest: Option<Box<dyn AstNode>>) -> Box<dyn AstNode> {
+    match rest {
+        None => leaf(coefficient),
+        Some(rest) if coefficient == 1.0 => rest,
+        Some(rest) => {
+            let mut factors = vec![];
+
+            add_operands(rest.as_ref(), IToken::Mul, &mut factors);
+            factors
+                .into_iter()
+                .fold(leaf(coefficient), |product, factor| {
+                    binary(IToken::Mul, product, copy_node(factor))
+                })
+        }
+    }
+}
+
+fn product(node: &dyn AstNode, rules: &Rules) -> Box<dyn AstNode> {
+    let mut coefficient = 1.0;
+    let mut factors = vec![];
+
+    add_factors(node, rules, &mut coefficient, &mut factors);
+
+    if rules.like_terms {
+        let mut combined: Vec<Factor> = vec![];
+
+        for (base, exponent) in factors {
+            let key = to_infix(base.as_ref());
+
+            match combined.iter_mut().find(|(other, _)| {
+                number(other.as_ref()).is_none() && to_infix(other.as_ref()) == key
+            }) {
+                Some(like) => like.1 += exponent,
+                None => combined.push((base, exponent)),
+            }
+        }
+
+        factors = combined
+            .into_iter()
+            .filter(|(_, exponent)| *exponent != 0.0)
+            .collect();
+    }
+
+    if rules.order {
+        factors.sort_by_cached_key(|(base, _)| {
+            (number(base.as_ref()).is_none(), to_infix(base.as_ref()))
+        });
+    }
+
+    let mut factors = factors.into_iter().map(|(base, exponent)| match exponent {
+        1.0 => base,
+        _ => binary(IToken::Pow, base, leaf(exponent)),
+    });
+    let first = match (coefficient, factors.next()) {
+        (1.0, Some(first)) => first,
+        (_, Some(first)) => binary(IToken::Mul, leaf(coefficient), first),
+        (_, None) => return leaf(coefficient),
+    };
+
+    factors.fold(first, |product, factor| {
+        binary(IToken::Mul, product, factor)
+    })
+}
+
+/// Splits the product rooted at node into its factors. Numbers are
+/// multiplied into coefficient when rules fold them and the coefficient
+/// stays finite.
+fn add_factors(
+    node: &dyn AstNode,
+    rules: &Rules,
+    coefficient: &mut f64,
+    factors: &mut Vec<Factor>,
+) {
+    let children = node.children();
+
+    match (node.token_type(), number(node)) {
+        (IToken::Mul, _) => {
+            add_factors(children[0].as_ref(), rules, coefficient, factors);
+            add_factors(children[1].as_ref(), rules, coefficient, factors);
+        }
+        // numbers whose product overflows stay factors
+        (IToken::Num, Some(value)) if rules.fold && (*coefficient * value).is_finite() => {
+            *coefficient *= value
+        }
+        (IToken::Pow, _) if number(children[1].as_ref()).is_some() => factors.push((
+            copy_node(children[0].as_ref()),
+            number(children[1].as_ref()).unwrap(),
+        )),
+        _ => factors.push((copy_node(node), 1.0)),
+    }
+}
+
+fn opposite(token_type: IToken) -> IToken {
+    match token_type {
+        IToken::Add => IToken::Sub,
+        _ => IToken::Add,
+    }
+}
+
+fn number(node: &dyn AstNode) -> Option<f64> {
+    match node.token_type() {
+        IToken::Num => node.token().literal,
+        _ => None,
+    }
+}
+
+/// The number a product starts with, like the 2 of 2*x
+fn leading_factor(node: &dyn AstNode) -> Option<f64> {
+    match node.token_type() {
+        IToken::Mul => number(node.children()[0].as_ref()),
+        _ => None,
+    }
+}
+
+fn is_neg(node: &dyn AstNode) -> bool {
+    node.token_type() == IToken::Neg
+}
+
+/// A copy of the operand of a negation
+fn inner(node: &dyn AstNode) -> Box<dyn AstNode> {
+    copy_node(node.children()[0].as_ref())
+}
+
+fn binary(token_type: IToken, left: Box<dyn AstNode>, right: Box<dyn AstNode>) -> Box<dyn AstNode> {
+    new_node(Token::operator(token_type), vec![left, right])
+}
+
+fn leaf(value: f64) -> Box<dyn AstNode> {
+    // adding zero turns -0 into 0
+    new_node(Token::number(value + 0.0), vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard_parser::parse::ShuntingYardParser;
+    use crate::tokenizer::tokenize::Tokenizer;
+
+    fn simplified(expr: &str) -> String {
+        let mut binding = Tokenizer::build(expr);
+        let tokens = binding.tokens().as_mut().unwrap();
+
+        to_infix(simplify(ShuntingYardParser::build(tokens).ast()).as_ref())
+    }
+
+    fn simplified_with(expr: &str, rules: &str) -> String {
+        let mut binding = Tokenizer::build(expr);
+        let tokens = binding.tokens().as_mut().unwrap();
+        let rules = rules.parse().unwrap();
+
+        to_infix(simplify_with(ShuntingYardParser::build(tokens).ast(), &rules).as_ref())
+    }
+
+    #[test]
+    fn simplify_folds_numbers_and_removes_identities() {
+        assert_eq!("x", simplified("0 + x * 1 ^ 2 - 0"));
+        assert_eq!("6*x", simplified("2 * (x * 3)"));
+        assert_eq!("x - y", simplified("x + -y"));
+        assert_eq!("x + 2", simplified("x - (1 - 3)"));
+        assert_eq!("1/3*x", simplified("1 / 3 * x"));
+        assert_eq!("x*y", simplified("-x * -y"));
+        assert_eq!("0", simplified("(x + 1) * 0"));
+    }
+
+    #[test]
+    fn simplify_keeps_undefined_and_overflowing_numbers() {
+        assert_eq!("0/0", simplified("0/0"));
+        assert_eq!("0", simplified("0/x"));
+        assert_eq!("x/0", simplified("x/0"));
+
+        let big = format!("1{}", "0".repeat(300));
+
+        assert_eq!(format!("{}*{}", big, big), simplified("10^300 * 10^300"));
+        assert_eq!(
+            format!("{}*{}*x", big, big),
+            simplified("10^300 * x * 10^300")
+        );
+    }
+
+    #[test]
+    fn simplify_combines_like_terms_in_canonical_order() {
+        assert_eq!("4*x", simplified("2*x + 3*x - x"));
+        assert_eq!("x^2*y", simplified("x * y * x"));
+        assert_eq!("2*x*y", simplified("y*x + x*y"));
+        assert_eq!("6*x^3", simplified("x * x^2 * 3 * 2"));
+        assert_eq!("x^2 + x + 3", simplified("1 + x + 2 + x^2"));
+        assert_eq!("c", simplified("b*a + c - a*b"));
+        assert_eq!("-x + y", simplified("y - x"));
+    }
+
+    #[test]
+    fn simplify_with_applies_only_the_chosen_rules() {
+        assert_eq!("x*y*x", simplified_with("x * y * x", "fold,identities"));
+        assert_eq!("x*x*y", simplified_with("x * y * x", "order"));
+        assert_eq!("x", simplified_with("--x * 1 + 0", "identities"));
+        assert_eq!("1 + 2*x", simplified_with("1 + 2*x", "fold,like-terms"));
+        assert_eq!("x + 1 + 2", simplified_with("1 + x + 2", "order"));
+        assert!("fold,expand".parse::<Rules>().is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::error;
+use std::fmt;
+use std::io::Error;
+
+/// The characters of the expression a token was read from, counted in
+/// chars from the start of the expression with the end excluded
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// The span of the single character at position
+    pub fn at(position: usize) -> Self {
+        Span {
+            start: position,
+            end: position + 1,
+        }
+    }
+
+    /// Writes a line with carets under the characters of the span, to be
+    /// printed below the expression
+    pub fn underline(&self) -> String {
+        format!(
+            "{}{}",
+            " ".repeat(self.start),
+            "^".repeat((self.end - self.start).max(1))
+        )
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// An error raised at the token with the span. It is carried inside an
+/// io::Error keeping the kind of the original error.
+#[derive(Debug)]
+pub struct SpanError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for SpanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+impl error::Error for SpanError {}
+
+/// Attaches the span to the error unless it already has one, so errors
+/// keep the span of the innermost token they were raised at
+pub fn with_span(error: Error, span: Option<Span>) -> Error {
+    match span {
+        Some(span) if span_of(&error).is_none() => Error::new(
+            error.kind(),
+            SpanError {
+                span,
+                message: error.to_string(),
+            },
+        ),
+        _ => error,
+    }
+}
+
+/// Returns the span attached to the error by with_span
+pub fn span_of(error: &Error) -> Option<Span> {
+    error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<SpanError>())
+        .map(|inner| inner.span)
+}
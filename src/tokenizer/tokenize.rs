@@ -1,7 +1,14 @@
 // use std::error::Error;
 use std::io::{Error, ErrorKind};
 
-use crate::tokenizer::tokens::{IAssociativity, IConstants, IFunctions, IToken, Token};
+use crate::currency::is_currency_code;
+use crate::span::{with_span, Span};
+use crate::tokenizer::tokens::{
+    IAngleUnit, IAssociativity, IConstants, IFunctions, IToken, Token, PI_LITERAL,
+};
+use crate::units::Unit;
+use std::iter::{Enumerate, Peekable};
+use std::str::Chars;
 
 pub struct Tokenizer {
     token_stream: Result<std::vec::IntoIter<Token>, Error>,
@@ -12,148 +19,390 @@ impl Tokenizer {
     /// and returns a Tokenizer struct with a private token_steam represent
     /// a Result of the iterator stream or an error.
     pub fn build(expr: &str) -> Self {
+        Self {
+            token_stream: Self::read(expr).map(|tokens| tokens.into_iter()),
+        }
+    }
+
+    /// Reads the tokens of expr, stopping at the first error
+    fn read(expr: &str) -> Result<Vec<Token>, Error> {
         let mut tokens: Vec<Token> = vec![];
         let mut number = String::new();
         let mut identifier = String::new();
 
-        for char in expr.chars() {
+        let mut chars = expr.chars().enumerate().peekable();
+
+        while let Some((position, char)) = chars.next() {
             match char {
                 '^' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
                     tokens.push(Token {
                         token_type: IToken::Pow,
                         associativity: Some(IAssociativity::Right),
                         precedence: Some(4),
                         literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
                     });
                 }
                 '*' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
                     tokens.push(Token {
                         token_type: IToken::Mul,
                         associativity: Some(IAssociativity::Left),
                         precedence: Some(3),
                         literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
                     });
                 }
                 '/' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
                     tokens.push(Token {
                         token_type: IToken::Div,
                         associativity: Some(IAssociativity::Left),
                         precedence: Some(3),
                         literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
                     });
                 }
                 '+' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
                     tokens.push(Token {
                         token_type: IToken::Add,
                         associativity: Some(IAssociativity::Left),
                         precedence: Some(2),
                         literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
                     })
                 }
                 '-' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
-                    tokens.push(Token {
-                        token_type: IToken::Sub,
-                        associativity: Some(IAssociativity::Left),
-                        precedence: Some(2),
-                        literal: None,
+                    // a minus sign without an operand on its left negates
+                    // the operand on its right
+                    let unary = match tokens.last() {
+                        None => true,
+                        Some(token) => matches!(
+                            token.token_type,
+                            IToken::LPar
+                                | IToken::Comma
+                                | IToken::Eq
+                                | IToken::Semicolon
+                                | IToken::Pow
+                                | IToken::Mul
+                                | IToken::Div
+                                | IToken::Add
+                                | IToken::Sub
+                                | IToken::Neg
+                        ),
+                    };
+
+                    tokens.push(if unary {
+                        Token {
+                            token_type: IToken::Neg,
+                            associativity: Some(IAssociativity::Right),
+                            precedence: Some(3),
+                            literal: None,
+                            lexeme: None,
+                            span: Some(Span::at(position)),
+                        }
+                    } else {
+                        Token {
+                            token_type: IToken::Sub,
+                            associativity: Some(IAssociativity::Left),
+                            precedence: Some(2),
+                            literal: None,
+                            lexeme: None,
+                            span: Some(Span::at(position)),
+                        }
                     });
                 }
                 '(' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
                     tokens.push(Token {
                         token_type: IToken::LPar,
                         associativity: None,
                         precedence: None,
                         literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
                     });
                 }
                 ')' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
 
                     tokens.push(Token {
                         token_type: IToken::RPar,
                         associativity: None,
                         precedence: None,
                         literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
+                    });
+                }
+                '"' => {
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
+
+                    let text: String = chars
+                        .by_ref()
+                        .map(|(_, char)| char)
+                        .take_while(|char| *char != '"')
+                        .collect();
+                    let end = position + text.chars().count() + 2;
+
+                    // the closing quote is missing when the text runs to the end
+                    if end > expr.chars().count() {
+                        return Err(Error::new::<_>(
+                            ErrorKind::InvalidData,
+                            format!("Unterminated string: \"{}", text),
+                        ));
+                    }
+
+                    tokens.push(Token {
+                        token_type: IToken::Str,
+                        associativity: None,
+                        precedence: None,
+                        literal: None,
+                        lexeme: Some(text),
+                        span: Some(Span::new(position, end)),
+                    });
+                }
+                ',' => {
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
+
+                    tokens.push(Token {
+                        token_type: IToken::Comma,
+                        associativity: None,
+                        precedence: None,
+                        literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
+                    });
+                }
+                '=' => {
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
+
+                    tokens.push(Token {
+                        token_type: IToken::Eq,
+                        associativity: Some(IAssociativity::Left),
+                        precedence: Some(0),
+                        literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
                     });
                 }
-                ',' | ' ' => {
-                    Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+                ';' => {
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
+
+                    tokens.push(Token {
+                        token_type: IToken::Semicolon,
+                        associativity: None,
+                        precedence: None,
+                        literal: None,
+                        lexeme: None,
+                        span: Some(Span::at(position)),
+                    });
+                }
+                char if char.is_ascii_whitespace() => {
+                    Self::clear_identifier_or_number(
+                        &mut identifier,
+                        &mut number,
+                        &mut tokens,
+                        position,
+                    )?;
                     continue;
                 }
+                // digits continue a name like x1 after its first letter
+                char if char.is_ascii_digit() && !identifier.is_empty() => identifier.push(char),
                 char if char.is_numeric() || char == '.' => {
-                    match Self::clear_identifier(&mut identifier) {
-                        Some(token) => tokens.push(token),
-                        None => (),
+                    if let Some(token) = Self::clear_identifier(&mut identifier, position) {
+                        tokens.push(token);
                     }
 
                     number.push(char);
                 }
-                char if char.is_alphabetic() => {
-                    match Self::clear_number(&mut number) {
-                        Some(token) => tokens.push(token),
-                        None => (),
+                char if !number.is_empty() && Self::angle_suffix(char, &chars).is_some() => {
+                    let unit = Self::angle_suffix(char, &chars).unwrap();
+                    let length = unit.suffix().chars().count();
+
+                    // the first character of the suffix is char
+                    for _ in 1..length {
+                        chars.next();
+                    }
+
+                    tokens.push(Self::clear_angle(&mut number, unit, position + length)?);
+                }
+                char if identifier.is_empty()
+                    && (!number.is_empty() || Self::takes_unit(tokens.last(), expr, position))
+                    && Self::unit_expression(char, &chars).is_some() =>
+                {
+                    let unit = Self::unit_expression(char, &chars).unwrap();
+                    let length = unit.chars().count();
+
+                    for _ in 1..length {
+                        chars.next();
+                    }
+
+                    if let Some(token) = Self::clear_number(&mut number, position)? {
+                        tokens.push(token);
+                    }
+
+                    let token = Self::unit_token(tokens.pop().unwrap(), unit, position + length);
+
+                    tokens.extend(token);
+                }
+                char if char.is_alphabetic() || (char == '_' && !identifier.is_empty()) => {
+                    if let Some(token) = Self::clear_number(&mut number, position)? {
+                        tokens.push(token);
+                        // a number written right before a name multiplies
+                        // it, like 2x, with an empty span between them
+                        tokens.push(Token {
+                            token_type: IToken::Mul,
+                            associativity: Some(IAssociativity::Left),
+                            precedence: Some(3),
+                            literal: None,
+                            lexeme: None,
+                            span: Some(Span::new(position, position)),
+                        });
                     }
 
                     identifier.push(char);
                 }
                 _ => {
-                    return Self {
-                        token_stream: Err(Error::new::<_>(
-                            ErrorKind::InvalidData,
-                            format!("Unidentified character: {}", char),
-                        )),
-                    };
+                    return Err(Error::new::<_>(
+                        ErrorKind::InvalidData,
+                        format!("Unidentified character: {}", char),
+                    ));
                 }
             }
         }
 
-        Self::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+        Self::clear_identifier_or_number(
+            &mut identifier,
+            &mut number,
+            &mut tokens,
+            expr.chars().count(),
+        )?;
 
-        Self {
-            token_stream: Ok(tokens.into_iter()),
-        }
+        Ok(tokens)
     }
 
     /// Creates a token using the current string in the identifier
     /// variable and clears the string.
-    /// Token can be a function or constant depending on the lexeme.
-    fn clear_identifier(identifier: &mut String) -> Option<Token> {
+    /// Token can be a function, constant or the imaginary unit i
+    /// depending on the lexeme.
+    /// Any other lexeme is the name of a variable.
+    fn clear_identifier(identifier: &mut String, end: usize) -> Option<Token> {
         if identifier.is_empty() {
             return None;
         }
 
+        let span = Some(Span::new(end - identifier.chars().count(), end));
+
         let token = match identifier.as_str() {
-            "min" => Token {
-                token_type: IToken::Fun(IFunctions::Min),
+            name if IFunctions::from_name(name).is_some() => Token {
+                token_type: IToken::Fun(IFunctions::from_name(name).unwrap()),
                 associativity: None,
                 precedence: None,
                 literal: None,
+                lexeme: None,
+                span,
             },
-            "max" => Token {
-                token_type: IToken::Fun(IFunctions::Max),
+            "i" => Token {
+                token_type: IToken::Imag,
                 associativity: None,
                 precedence: None,
+                literal: Some(1.0),
+                lexeme: Some(identifier.clone()),
+                span,
+            },
+            "to" | "in" => Token {
+                token_type: IToken::To,
+                associativity: Some(IAssociativity::Left),
+                precedence: Some(1),
                 literal: None,
+                lexeme: Some(identifier.clone()),
+                span,
             },
             "pi" => Token {
                 token_type: IToken::Const(IConstants::Pi),
                 associativity: None,
                 precedence: None,
-                literal: Some(3.14159265359),
+                literal: Some(PI_LITERAL),
+                lexeme: None,
+                span,
+            },
+            _ => Token {
+                token_type: IToken::Var,
+                associativity: None,
+                precedence: None,
+                literal: None,
+                lexeme: Some(identifier.clone()),
+                span,
             },
-            _ => panic!("Unidentified identifier: {}", identifier),
         };
 
         identifier.clear();
@@ -163,44 +412,207 @@ impl Tokenizer {
 
     /// Creates a number token using the current string in the number
     /// variable and clears the string.
-    fn clear_number(number: &mut String) -> Option<Token> {
+    /// The lexeme keeps the digits as written for exact evaluation.
+    fn clear_number(number: &mut String, end: usize) -> Result<Option<Token>, Error> {
         if number.is_empty() {
-            return None;
+            return Ok(None);
         }
 
+        let span = Span::new(end - number.chars().count(), end);
         let token = Token {
             token_type: IToken::Num,
             associativity: None,
             precedence: None,
-            literal: Some(number.parse::<f64>().unwrap()),
+            literal: Some(Self::parse_number(number, span)?),
+            lexeme: Some(number.clone()),
+            span: Some(span),
         };
 
         number.clear();
 
-        Some(token)
+        Ok(Some(token))
+    }
+
+    /// Parses the digits and decimal points read into number, failing
+    /// for malformed numbers like 1..2
+    fn parse_number(number: &str, span: Span) -> Result<f64, Error> {
+        number.parse::<f64>().map_err(|_| {
+            with_span(
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Malformed number: {}", number),
+                ),
+                Some(span),
+            )
+        })
+    }
+
+    /// Returns the unit of an angle when the characters starting with
+    /// char, up to the next character which is not a letter or digit, are
+    /// the suffix of its unit
+    fn angle_suffix(char: char, chars: &Peekable<Enumerate<Chars>>) -> Option<IAngleUnit> {
+        let mut rest = chars.clone();
+        let mut suffix = String::from(char);
+
+        while let Some((_, next)) = rest.next_if(|(_, next)| next.is_alphanumeric()) {
+            suffix.push(next);
+        }
+
+        IAngleUnit::from_suffix(&suffix)
+    }
+
+    /// Creates an angle token using the current string in the number
+    /// variable, which was followed by the suffix of unit, and clears the
+    /// string.
+    fn clear_angle(number: &mut String, unit: IAngleUnit, end: usize) -> Result<Token, Error> {
+        let lexeme = format!("{}{}", number, unit.suffix());
+        let span = Span::new(end - lexeme.chars().count(), end);
+        let token = Token {
+            token_type: IToken::Angle(unit),
+            associativity: None,
+            precedence: None,
+            literal: Some(Self::parse_number(number, span)?),
+            span: Some(span),
+            lexeme: Some(lexeme),
+        };
+
+        number.clear();
+
+        Ok(token)
+    }
+
+    /// Whether a unit read at position belongs to the token before it, a
+    /// number it makes a quantity of or a conversion it is the target of.
+    /// Only whitespace may separate them, so the m of max(1, m) is a
+    /// variable.
+    fn takes_unit(token: Option<&Token>, expr: &str, position: usize) -> bool {
+        match token {
+            Some(token) if matches!(token.token_type, IToken::Num | IToken::To) => {
+                let end = token.span.map_or(position, |span| span.end);
+
+                expr.chars()
+                    .skip(end)
+                    .take(position - end)
+                    .all(|char| char.is_ascii_whitespace())
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the unit expression like km or m/s^2, or the currency code
+    /// like USD, starting with char
+    /// when the characters from char on are one. The expression ends at
+    /// the first character which does not continue it, which must not
+    /// be a letter, digit or the ( of a function call.
+    fn unit_expression(char: char, chars: &Peekable<Enumerate<Chars>>) -> Option<String> {
+        let mut rest = chars.clone();
+        let mut unit = String::from(char);
+
+        loop {
+            while let Some((_, next)) = rest.next_if(|(_, next)| next.is_alphabetic()) {
+                unit.push(next);
+            }
+
+            let mut lookahead = rest.clone();
+
+            if lookahead.next_if(|(_, next)| *next == '^').is_some() {
+                let mut exponent = String::from("^");
+
+                if let Some((_, sign)) = lookahead.next_if(|(_, next)| *next == '-') {
+                    exponent.push(sign);
+                }
+
+                while let Some((_, digit)) = lookahead.next_if(|(_, next)| next.is_ascii_digit()) {
+                    exponent.push(digit);
+                }
+
+                if exponent.ends_with(|last: char| last.is_ascii_digit()) {
+                    unit.push_str(&exponent);
+                    rest = lookahead.clone();
+                }
+            }
+
+            let mut lookahead = rest.clone();
+
+            match (lookahead.next(), lookahead.next()) {
+                (Some((_, operator @ ('*' | '/'))), Some((_, next))) if next.is_alphabetic() => {
+                    unit.push(operator);
+                    unit.push(next);
+                    rest = lookahead;
+                }
+                _ => break,
+            }
+        }
+
+        match rest.peek() {
+            Some((_, next)) if next.is_alphanumeric() || *next == '(' => None,
+            _ => (unit.parse::<Unit>().is_ok() || is_currency_code(&unit)).then_some(unit),
+        }
+    }
+
+    /// Creates the token for a unit or currency code read after previous,
+    /// a Quantity or Currency token replacing previous when it is a
+    /// number and a Unit token after it otherwise
+    fn unit_token(previous: Token, unit: String, end: usize) -> Vec<Token> {
+        let unit_start = end - unit.chars().count();
+
+        match previous.token_type {
+            IToken::Num => vec![Token {
+                token_type: match is_currency_code(&unit) {
+                    true => IToken::Currency,
+                    false => IToken::Quantity,
+                },
+                associativity: None,
+                precedence: None,
+                literal: previous.literal,
+                lexeme: Some(format!("{} {}", previous.lexeme.unwrap(), unit)),
+                span: Some(Span::new(
+                    previous.span.map_or(unit_start, |span| span.start),
+                    end,
+                )),
+            }],
+            _ => vec![
+                previous,
+                Token {
+                    token_type: IToken::Unit,
+                    associativity: None,
+                    precedence: None,
+                    literal: None,
+                    lexeme: Some(unit),
+                    span: Some(Span::new(unit_start, end)),
+                },
+            ],
+        }
     }
 
-    /// A convenience over calling clear_identifier and clear_number separately
+    /// A convenience over calling clear_identifier and clear_number separately.
+    /// end is the char position the identifier or number ends at.
     fn clear_identifier_or_number<'a>(
         identifier: &'a mut String,
         number: &'a mut String,
         tokens: &mut Vec<Token>,
-    ) {
-        match Self::clear_identifier(identifier) {
-            Some(token) => tokens.push(token),
-            None => (),
+        end: usize,
+    ) -> Result<(), Error> {
+        if let Some(token) = Self::clear_identifier(identifier, end) {
+            tokens.push(token);
         }
 
-        match Self::clear_number(number) {
-            Some(token) => tokens.push(token),
-            None => (),
+        if let Some(token) = Self::clear_number(number, end)? {
+            tokens.push(token);
         }
+
+        Ok(())
     }
 
     // Returns the token_steam Result
     pub fn tokens(&mut self) -> &mut Result<std::vec::IntoIter<Token>, Error> {
         &mut self.token_stream
     }
+
+    // Consumes the Tokenizer and returns the token_steam Result
+    pub fn into_tokens(self) -> Result<std::vec::IntoIter<Token>, Error> {
+        self.token_stream
+    }
 }
 
 #[cfg(test)]
@@ -211,17 +623,17 @@ mod tests {
     fn clear_number_returns_none_when_number_empty() {
         let mut number = String::new();
 
-        assert!(Tokenizer::clear_number(&mut number).is_none());
+        assert!(Tokenizer::clear_number(&mut number, 0).unwrap().is_none());
 
         let mut number = String::from("");
 
-        assert!(Tokenizer::clear_number(&mut number).is_none());
+        assert!(Tokenizer::clear_number(&mut number, 0).unwrap().is_none());
     }
 
     #[test]
     fn clear_number_returns_token_for_non_empty_lexeme_and_clears_string() {
         let mut number = String::from("3.101");
-        let token = Tokenizer::clear_number(&mut number).unwrap();
+        let token = Tokenizer::clear_number(&mut number, 5).unwrap().unwrap();
 
         assert_eq!(
             Token {
@@ -229,6 +641,8 @@ mod tests {
                 associativity: None,
                 precedence: None,
                 literal: Some(3.101),
+                lexeme: Some(String::from("3.101")),
+                span: Some(Span::new(0, 5)),
             },
             token
         );
@@ -240,18 +654,18 @@ mod tests {
     fn clear_identifier_returns_none_when_identifier_is_empty() {
         let mut identifier = String::new();
 
-        assert!(Tokenizer::clear_identifier(&mut identifier).is_none());
+        assert!(Tokenizer::clear_identifier(&mut identifier, 0).is_none());
 
         let mut identifier = String::from("");
 
-        assert!(Tokenizer::clear_identifier(&mut identifier).is_none());
+        assert!(Tokenizer::clear_identifier(&mut identifier, 0).is_none());
     }
 
     #[test]
     fn clear_identifier_returns_token_and_clears_string() {
         // min token
         let mut identifier = String::from("min");
-        let token = Tokenizer::clear_identifier(&mut identifier).unwrap();
+        let token = Tokenizer::clear_identifier(&mut identifier, 3).unwrap();
 
         assert_eq!(
             Token {
@@ -259,6 +673,8 @@ mod tests {
                 associativity: None,
                 precedence: None,
                 literal: None,
+                lexeme: None,
+                span: Some(Span::new(0, 3)),
             },
             token
         );
@@ -267,7 +683,7 @@ mod tests {
 
         // max token
         let mut identifier = String::from("max");
-        let token = Tokenizer::clear_identifier(&mut identifier).unwrap();
+        let token = Tokenizer::clear_identifier(&mut identifier, 3).unwrap();
 
         assert_eq!(
             Token {
@@ -275,6 +691,8 @@ mod tests {
                 associativity: None,
                 precedence: None,
                 literal: None,
+                lexeme: None,
+                span: Some(Span::new(0, 3)),
             },
             token
         );
@@ -283,14 +701,16 @@ mod tests {
 
         // pi token
         let mut identifier = String::from("pi");
-        let token = Tokenizer::clear_identifier(&mut identifier).unwrap();
+        let token = Tokenizer::clear_identifier(&mut identifier, 2).unwrap();
 
         assert_eq!(
             Token {
                 token_type: IToken::Const(IConstants::Pi),
                 associativity: None,
                 precedence: None,
-                literal: Some(3.14159265359),
+                literal: Some(PI_LITERAL),
+                lexeme: None,
+                span: Some(Span::new(0, 2)),
             },
             token
         );
@@ -299,10 +719,23 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Unidentified identifier:")]
-    fn clear_identifier_panics_at_invalid_identifier() {
-        let mut identifier = String::from("invalid");
-        let _token = Tokenizer::clear_identifier(&mut identifier).unwrap();
+    fn clear_identifier_returns_variable_token_for_unknown_identifier() {
+        let mut identifier = String::from("rate");
+        let token = Tokenizer::clear_identifier(&mut identifier, 4).unwrap();
+
+        assert_eq!(
+            Token {
+                token_type: IToken::Var,
+                associativity: None,
+                precedence: None,
+                literal: None,
+                lexeme: Some(String::from("rate")),
+                span: Some(Span::new(0, 4)),
+            },
+            token
+        );
+
+        assert!(identifier.is_empty());
     }
 
     #[test]
@@ -311,7 +744,8 @@ mod tests {
         let mut identifier = String::new();
         let mut tokens = vec![];
 
-        Tokenizer::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+        Tokenizer::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens, 5)
+            .unwrap();
 
         assert_eq!(
             vec![Token {
@@ -319,6 +753,8 @@ mod tests {
                 associativity: None,
                 precedence: None,
                 literal: Some(0.331),
+                lexeme: Some(String::from("0.331")),
+                span: Some(Span::new(0, 5)),
             }],
             tokens
         );
@@ -332,14 +768,17 @@ mod tests {
         let mut identifier = String::from("pi");
         let mut tokens = vec![];
 
-        Tokenizer::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens);
+        Tokenizer::clear_identifier_or_number(&mut identifier, &mut number, &mut tokens, 2)
+            .unwrap();
 
         assert_eq!(
             vec![Token {
                 token_type: IToken::Const(IConstants::Pi),
                 associativity: None,
                 precedence: None,
-                literal: Some(3.14159265359),
+                literal: Some(PI_LITERAL),
+                lexeme: None,
+                span: Some(Span::new(0, 2)),
             }],
             tokens
         );
@@ -359,90 +798,270 @@ mod tests {
                     associativity: None,
                     precedence: None,
                     literal: Some(2.0),
+                    lexeme: Some(String::from("2")),
+                    span: Some(Span::new(0, 1)),
                 },
                 Token {
                     token_type: IToken::Add,
                     associativity: Some(IAssociativity::Left,),
                     precedence: Some(2,),
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(1, 2)),
                 },
                 Token {
                     token_type: IToken::Num,
                     associativity: None,
                     precedence: None,
                     literal: Some(2.0),
+                    lexeme: Some(String::from("2")),
+                    span: Some(Span::new(2, 3)),
                 },
                 Token {
                     token_type: IToken::LPar,
                     associativity: None,
                     precedence: None,
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(3, 4)),
                 },
                 Token {
                     token_type: IToken::Num,
                     associativity: None,
                     precedence: None,
                     literal: Some(3.0),
+                    lexeme: Some(String::from("3")),
+                    span: Some(Span::new(4, 5)),
                 },
                 Token {
                     token_type: IToken::Sub,
                     associativity: Some(IAssociativity::Left,),
                     precedence: Some(2,),
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(5, 6)),
                 },
                 Token {
                     token_type: IToken::Num,
                     associativity: None,
                     precedence: None,
                     literal: Some(1.0),
+                    lexeme: Some(String::from("1")),
+                    span: Some(Span::new(6, 7)),
                 },
                 Token {
                     token_type: IToken::RPar,
                     associativity: None,
                     precedence: None,
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(7, 8)),
                 },
                 Token {
                     token_type: IToken::Pow,
                     associativity: Some(IAssociativity::Right,),
                     precedence: Some(4,),
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(8, 9)),
                 },
                 Token {
                     token_type: IToken::Fun(IFunctions::Min,),
                     associativity: None,
                     precedence: None,
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(9, 12)),
                 },
                 Token {
                     token_type: IToken::LPar,
                     associativity: None,
                     precedence: None,
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(12, 13)),
                 },
                 Token {
                     token_type: IToken::Num,
                     associativity: None,
                     precedence: None,
                     literal: Some(1.0),
+                    lexeme: Some(String::from("1")),
+                    span: Some(Span::new(13, 14)),
+                },
+                Token {
+                    token_type: IToken::Comma,
+                    associativity: None,
+                    precedence: None,
+                    literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(14, 15)),
                 },
                 Token {
                     token_type: IToken::Num,
                     associativity: None,
                     precedence: None,
                     literal: Some(0.1),
+                    lexeme: Some(String::from("0.1")),
+                    span: Some(Span::new(15, 18)),
                 },
                 Token {
                     token_type: IToken::RPar,
                     associativity: None,
                     precedence: None,
                     literal: None,
+                    lexeme: None,
+                    span: Some(Span::new(18, 19)),
                 },
             ],
             token_stream.collect::<Vec<Token>>()
         );
     }
 
+    #[test]
+    fn build_tells_unary_minus_apart_and_multiplies_numbers_by_i() {
+        let token_types = Tokenizer::build("-2*-3i-i")
+            .into_tokens()
+            .unwrap()
+            .map(|token| token.token_type)
+            .collect::<Vec<IToken>>();
+
+        assert_eq!(
+            vec![
+                IToken::Neg,
+                IToken::Num,
+                IToken::Mul,
+                IToken::Neg,
+                IToken::Num,
+                IToken::Mul,
+                IToken::Imag,
+                IToken::Sub,
+                IToken::Imag,
+            ],
+            token_types
+        );
+    }
+
+    #[test]
+    fn build_reads_angle_suffixes_only_after_numbers() {
+        let tokens = Tokenizer::build("sin(90deg)+1.5rad*grad")
+            .into_tokens()
+            .unwrap()
+            .collect::<Vec<Token>>();
+
+        assert_eq!(IToken::Angle(IAngleUnit::Degrees), tokens[2].token_type);
+        assert_eq!(Some(90.0), tokens[2].literal);
+        assert_eq!(Some(Span::new(4, 9)), tokens[2].span);
+        assert_eq!(IToken::Angle(IAngleUnit::Radians), tokens[5].token_type);
+        assert_eq!(Some(String::from("1.5rad")), tokens[5].lexeme);
+        assert_eq!(IToken::Var, tokens[7].token_type);
+    }
+
+    #[test]
+    fn build_reads_units_after_numbers_and_conversions() {
+        let tokens = Tokenizer::build("5 km/h*2 to m/s^-1 + 3 min(1, m)")
+            .into_tokens()
+            .unwrap()
+            .collect::<Vec<Token>>();
+
+        assert_eq!(IToken::Quantity, tokens[0].token_type);
+        assert_eq!(Some(String::from("5 km/h")), tokens[0].lexeme);
+        assert_eq!(Some(Span::new(0, 6)), tokens[0].span);
+        assert_eq!(IToken::Num, tokens[2].token_type);
+        assert_eq!(IToken::To, tokens[3].token_type);
+        assert_eq!(IToken::Unit, tokens[4].token_type);
+        assert_eq!(Some(String::from("m/s^-1")), tokens[4].lexeme);
+        assert_eq!(IToken::Num, tokens[6].token_type);
+        assert_eq!(IToken::Fun(IFunctions::Min), tokens[7].token_type);
+        assert_eq!(IToken::Comma, tokens[10].token_type);
+        assert_eq!(IToken::Var, tokens[11].token_type);
+    }
+
+    #[test]
+    fn build_reads_currency_codes_after_numbers() {
+        let tokens = Tokenizer::build("100 USD in GBP")
+            .into_tokens()
+            .unwrap()
+            .collect::<Vec<Token>>();
+
+        assert_eq!(IToken::Currency, tokens[0].token_type);
+        assert_eq!(Some(String::from("100 USD")), tokens[0].lexeme);
+        assert_eq!(IToken::To, tokens[1].token_type);
+        assert_eq!(IToken::Unit, tokens[2].token_type);
+        assert_eq!(Some(String::from("GBP")), tokens[2].lexeme);
+    }
+
+    #[test]
+    fn build_reads_strings_and_identifiers_with_underscores() {
+        let tokens = Tokenizer::build("days_between(date(\"2026-10-18\"), first_day)")
+            .into_tokens()
+            .unwrap()
+            .collect::<Vec<Token>>();
+
+        assert_eq!(IToken::Fun(IFunctions::DaysBetween), tokens[0].token_type);
+        assert_eq!(IToken::Str, tokens[4].token_type);
+        assert_eq!(Some(String::from("2026-10-18")), tokens[4].lexeme);
+        assert_eq!(Some(Span::new(18, 30)), tokens[4].span);
+        assert_eq!(IToken::Comma, tokens[6].token_type);
+        assert_eq!(Some(String::from("first_day")), tokens[7].lexeme);
+        assert!(Tokenizer::build("date(\"2026-10-18)")
+            .into_tokens()
+            .is_err());
+    }
+
+    #[test]
+    fn build_reads_digits_in_names_and_any_whitespace() {
+        let tokens = Tokenizer::build("2x1 +\ta_2\n")
+            .into_tokens()
+            .unwrap()
+            .collect::<Vec<Token>>();
+
+        assert_eq!(5, tokens.len());
+        assert_eq!(IToken::Mul, tokens[1].token_type);
+        assert_eq!(Some(String::from("x1")), tokens[2].lexeme);
+        assert_eq!(Some(Span::new(1, 3)), tokens[2].span);
+        assert_eq!(Some(String::from("a_2")), tokens[4].lexeme);
+    }
+
+    #[test]
+    fn build_reads_equations_and_numbers_multiplying_names() {
+        let tokens = Tokenizer::build("2x + 3 = 11; -y=2pi")
+            .into_tokens()
+            .unwrap()
+            .map(|token| token.token_type)
+            .collect::<Vec<IToken>>();
+
+        assert_eq!(
+            vec![
+                IToken::Num,
+                IToken::Mul,
+                IToken::Var,
+                IToken::Add,
+                IToken::Num,
+                IToken::Eq,
+                IToken::Num,
+                IToken::Semicolon,
+                IToken::Neg,
+                IToken::Var,
+                IToken::Eq,
+                IToken::Num,
+                IToken::Mul,
+                IToken::Const(IConstants::Pi),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn build_returns_error_result_for_malformed_numbers() {
+        let error = Tokenizer::build("2 + 1..2").into_tokens().unwrap_err();
+
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert_eq!("Malformed number: 1..2 at 4..8", error.to_string());
+        assert!(Tokenizer::build("1.2.3i").into_tokens().is_err());
+        assert!(Tokenizer::build("1..5deg").into_tokens().is_err());
+    }
+
     #[test]
     fn build_returns_error_result_at_unrecognized_token() {
         let mut binding = Tokenizer::build("2+2(3-1)_^min(1,0.1)");
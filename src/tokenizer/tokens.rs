@@ -1,3 +1,10 @@
+use crate::span::Span;
+use std::fmt;
+
+/// The value the pi constant evaluates to
+#[allow(clippy::approx_constant)]
+pub const PI_LITERAL: f64 = 3.14159265359;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum IAssociativity {
     Left,
@@ -13,6 +20,165 @@ pub enum IConstants {
 pub enum IFunctions {
     Max,
     Min,
+    Sqrt,
+    Abs,
+    Arg,
+    Conj,
+    Re,
+    Im,
+    Exp,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Date,
+    Duration,
+    DaysBetween,
+    Integrate,
+    Solve,
+    Sum,
+    Prod,
+}
+
+/// The units angles are measured in
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum IAngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+    Gradians,
+}
+
+impl IFunctions {
+    /// Every function the tokenizer recognizes
+    pub const ALL: [IFunctions; 22] = [
+        IFunctions::Max,
+        IFunctions::Min,
+        IFunctions::Sqrt,
+        IFunctions::Abs,
+        IFunctions::Arg,
+        IFunctions::Conj,
+        IFunctions::Re,
+        IFunctions::Im,
+        IFunctions::Exp,
+        IFunctions::Sin,
+        IFunctions::Cos,
+        IFunctions::Tan,
+        IFunctions::Asin,
+        IFunctions::Acos,
+        IFunctions::Atan,
+        IFunctions::Date,
+        IFunctions::Duration,
+        IFunctions::DaysBetween,
+        IFunctions::Integrate,
+        IFunctions::Solve,
+        IFunctions::Sum,
+        IFunctions::Prod,
+    ];
+
+    /// The identifier the function is called by
+    pub fn name(&self) -> &'static str {
+        match self {
+            IFunctions::Max => "max",
+            IFunctions::Min => "min",
+            IFunctions::Sqrt => "sqrt",
+            IFunctions::Abs => "abs",
+            IFunctions::Arg => "arg",
+            IFunctions::Conj => "conj",
+            IFunctions::Re => "re",
+            IFunctions::Im => "im",
+            IFunctions::Exp => "exp",
+            IFunctions::Sin => "sin",
+            IFunctions::Cos => "cos",
+            IFunctions::Tan => "tan",
+            IFunctions::Asin => "asin",
+            IFunctions::Acos => "acos",
+            IFunctions::Atan => "atan",
+            IFunctions::Date => "date",
+            IFunctions::Duration => "duration",
+            IFunctions::DaysBetween => "days_between",
+            IFunctions::Integrate => "integrate",
+            IFunctions::Solve => "solve",
+            IFunctions::Sum => "sum",
+            IFunctions::Prod => "prod",
+        }
+    }
+
+    /// The number of arguments the function takes
+    pub fn arity(&self) -> usize {
+        match self {
+            IFunctions::Max | IFunctions::Min | IFunctions::DaysBetween => 2,
+            IFunctions::Solve => 3,
+            IFunctions::Integrate | IFunctions::Sum | IFunctions::Prod => 4,
+            _ => 1,
+        }
+    }
+
+    /// Whether the second argument of the function is a variable bound in
+    /// its first argument, which is evaluated for values of the variable
+    /// chosen by the function, like the i of sum(i^2, i, 1, 10)
+    pub fn binds_variable(&self) -> bool {
+        matches!(
+            self,
+            IFunctions::Integrate | IFunctions::Solve | IFunctions::Sum | IFunctions::Prod
+        )
+    }
+
+    /// Whether the argument of the function is an angle, measured in the
+    /// angle unit of the evaluation
+    pub fn takes_angle(&self) -> bool {
+        matches!(self, IFunctions::Sin | IFunctions::Cos | IFunctions::Tan)
+    }
+
+    /// Whether the function returns an angle, measured in the angle unit
+    /// of the evaluation
+    pub fn returns_angle(&self) -> bool {
+        matches!(
+            self,
+            IFunctions::Asin | IFunctions::Acos | IFunctions::Atan | IFunctions::Arg
+        )
+    }
+
+    /// Finds the function called by name
+    pub fn from_name(name: &str) -> Option<IFunctions> {
+        Self::ALL
+            .into_iter()
+            .find(|function| function.name() == name)
+    }
+}
+
+impl IAngleUnit {
+    pub const ALL: [IAngleUnit; 3] = [
+        IAngleUnit::Radians,
+        IAngleUnit::Degrees,
+        IAngleUnit::Gradians,
+    ];
+
+    /// The suffix of angle literals measured in the unit, like 90deg
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            IAngleUnit::Radians => "rad",
+            IAngleUnit::Degrees => "deg",
+            IAngleUnit::Gradians => "grad",
+        }
+    }
+
+    /// The number of units in half a turn, pi radians, or None for
+    /// radians themselves
+    pub fn half_turn(&self) -> Option<&'static str> {
+        match self {
+            IAngleUnit::Radians => None,
+            IAngleUnit::Degrees => Some("180"),
+            IAngleUnit::Gradians => Some("200"),
+        }
+    }
+
+    /// Finds the unit with the suffix
+    pub fn from_suffix(suffix: &str) -> Option<IAngleUnit> {
+        Self::ALL.into_iter().find(|unit| unit.suffix() == suffix)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -22,17 +188,161 @@ pub enum IToken {
     Div,
     Add,
     Sub,
+    /// The unary minus, a minus sign with no operand on its left
+    Neg,
     Fun(IFunctions),
     LPar,
     RPar,
+    /// The comma separating the arguments of a function
+    Comma,
     Num,
     Const(IConstants),
+    Var,
+    /// An imaginary number, a number with the i suffix or i on its own
+    Imag,
+    /// An angle, a number with the suffix of its unit
+    Angle(IAngleUnit),
+    /// A number followed by a unit, like 5 km or 9.8 m/s^2
+    Quantity,
+    /// A number followed by a currency code, like 100 USD
+    Currency,
+    /// A unit or currency code on its own, the target of a conversion
+    Unit,
+    /// The conversion operator, to or in
+    To,
+    /// A string in double quotes, the argument of date and duration
+    Str,
+    /// The equals sign of an equation, binding looser than any operator
+    Eq,
+    /// The semicolon separating the equations of a system
+    Semicolon,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+impl IToken {
+    /// A name for the category of the token
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IToken::Pow
+            | IToken::Mul
+            | IToken::Div
+            | IToken::Add
+            | IToken::Sub
+            | IToken::Neg
+            | IToken::To => "operator",
+            IToken::Fun(_) => "function",
+            IToken::LPar => "left_parenthesis",
+            IToken::RPar => "right_parenthesis",
+            IToken::Comma => "comma",
+            IToken::Num => "number",
+            IToken::Const(_) => "constant",
+            IToken::Var => "variable",
+            IToken::Imag => "imaginary",
+            IToken::Angle(_) => "angle",
+            IToken::Quantity => "quantity",
+            IToken::Currency => "currency",
+            IToken::Unit => "unit",
+            IToken::Str => "string",
+            IToken::Eq => "equals",
+            IToken::Semicolon => "semicolon",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: IToken,
     pub associativity: Option<IAssociativity>,
     pub precedence: Option<u8>,
     pub literal: Option<f64>,
+    /// The name of the variable for Var tokens, the text of the number
+    /// for Num, Imag and Angle tokens, the number and unit separated by a
+    /// space for Quantity and Currency tokens, the unit for Unit tokens, the
+    /// keyword for To tokens and the text between the quotes for Str tokens
+    pub lexeme: Option<String>,
+    /// Where the token was read from in the expression, None for tokens
+    /// which were not produced by the Tokenizer
+    pub span: Option<Span>,
+}
+
+impl Token {
+    /// A token for an operator or function which was not read from an
+    /// expression, with the associativity and precedence the Tokenizer
+    /// gives it
+    pub fn operator(token_type: IToken) -> Token {
+        let (associativity, precedence) = match token_type {
+            IToken::Pow => (Some(IAssociativity::Right), Some(4)),
+            IToken::Mul | IToken::Div => (Some(IAssociativity::Left), Some(3)),
+            IToken::Neg => (Some(IAssociativity::Right), Some(3)),
+            IToken::Add | IToken::Sub => (Some(IAssociativity::Left), Some(2)),
+            IToken::To => (Some(IAssociativity::Left), Some(1)),
+            IToken::Eq => (Some(IAssociativity::Left), Some(0)),
+            _ => (None, None),
+        };
+
+        Token {
+            token_type,
+            associativity,
+            precedence,
+            literal: None,
+            lexeme: None,
+            span: None,
+        }
+    }
+
+    /// A number token which was not read from an expression
+    pub fn number(value: f64) -> Token {
+        Token {
+            token_type: IToken::Num,
+            associativity: None,
+            precedence: None,
+            literal: Some(value),
+            lexeme: Some(value.to_string()),
+            span: None,
+        }
+    }
+
+    /// A variable token which was not read from an expression
+    pub fn variable(name: &str) -> Token {
+        Token {
+            token_type: IToken::Var,
+            associativity: None,
+            precedence: None,
+            literal: None,
+            lexeme: Some(String::from(name)),
+            span: None,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    /// Writes the lexeme the token was produced from. Numbers are written
+    /// using their literal value and the unary minus as neg.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.token_type {
+            IToken::Pow => write!(f, "^"),
+            IToken::Mul => write!(f, "*"),
+            IToken::Div => write!(f, "/"),
+            IToken::Add => write!(f, "+"),
+            IToken::Sub => write!(f, "-"),
+            IToken::Neg => write!(f, "neg"),
+            IToken::Fun(function) => write!(f, "{}", function.name()),
+            IToken::LPar => write!(f, "("),
+            IToken::RPar => write!(f, ")"),
+            IToken::Comma => write!(f, ","),
+            IToken::Eq => write!(f, "="),
+            IToken::Semicolon => write!(f, ";"),
+            IToken::Num => write!(f, "{}", self.literal.unwrap()),
+            IToken::Const(IConstants::Pi) => write!(f, "pi"),
+            IToken::Str => write!(f, "\"{}\"", self.lexeme.as_ref().unwrap()),
+            IToken::Var
+            | IToken::Imag
+            | IToken::Angle(_)
+            | IToken::Quantity
+            | IToken::Currency
+            | IToken::Unit
+            | IToken::To => {
+                write!(f, "{}", self.lexeme.as_ref().unwrap())
+            }
+        }
+    }
 }
@@ -0,0 +1,541 @@
+use crate::ast::nodes::{AstNode, Variables};
+use crate::numeric::{apply, evaluate_numeric, Numeric};
+use crate::span::with_span;
+use crate::tokenizer::tokens::{IConstants, IFunctions, IToken, PI_LITERAL};
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+/// The symbols of the SI base units, in the order of the exponents of a
+/// Dimension
+pub const BASE_UNITS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// The prefixes which can be put before the units marked as prefixable
+/// in UNITS, with their factors
+pub const PREFIXES: [(&str, f64); 10] = [
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("µ", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+];
+
+/// The built in units with their factor to SI base units, dimension and
+/// whether they take prefixes
+pub const UNITS: [(&str, f64, Dimension, bool); 30] = [
+    ("m", 1.0, Dimension([1, 0, 0, 0, 0, 0, 0]), true),
+    ("g", 1e-3, Dimension([0, 1, 0, 0, 0, 0, 0]), true),
+    ("s", 1.0, Dimension([0, 0, 1, 0, 0, 0, 0]), true),
+    ("A", 1.0, Dimension([0, 0, 0, 1, 0, 0, 0]), true),
+    ("K", 1.0, Dimension([0, 0, 0, 0, 1, 0, 0]), true),
+    ("mol", 1.0, Dimension([0, 0, 0, 0, 0, 1, 0]), true),
+    ("cd", 1.0, Dimension([0, 0, 0, 0, 0, 0, 1]), true),
+    ("Hz", 1.0, Dimension([0, 0, -1, 0, 0, 0, 0]), true),
+    ("N", 1.0, Dimension([1, 1, -2, 0, 0, 0, 0]), true),
+    ("Pa", 1.0, Dimension([-1, 1, -2, 0, 0, 0, 0]), true),
+    ("J", 1.0, Dimension([2, 1, -2, 0, 0, 0, 0]), true),
+    ("W", 1.0, Dimension([2, 1, -3, 0, 0, 0, 0]), true),
+    ("C", 1.0, Dimension([0, 0, 1, 1, 0, 0, 0]), true),
+    ("V", 1.0, Dimension([2, 1, -3, -1, 0, 0, 0]), true),
+    ("ohm", 1.0, Dimension([2, 1, -3, -2, 0, 0, 0]), true),
+    ("L", 1e-3, Dimension([3, 0, 0, 0, 0, 0, 0]), true),
+    ("min", 60.0, Dimension([0, 0, 1, 0, 0, 0, 0]), false),
+    ("h", 3600.0, Dimension([0, 0, 1, 0, 0, 0, 0]), false),
+    ("day", 86400.0, Dimension([0, 0, 1, 0, 0, 0, 0]), false),
+    ("days", 86400.0, Dimension([0, 0, 1, 0, 0, 0, 0]), false),
+    ("week", 604800.0, Dimension([0, 0, 1, 0, 0, 0, 0]), false),
+    ("weeks", 604800.0, Dimension([0, 0, 1, 0, 0, 0, 0]), false),
+    ("t", 1000.0, Dimension([0, 1, 0, 0, 0, 0, 0]), false),
+    ("ha", 1e4, Dimension([2, 0, 0, 0, 0, 0, 0]), false),
+    ("ft", 0.3048, Dimension([1, 0, 0, 0, 0, 0, 0]), false),
+    ("mi", 1609.344, Dimension([1, 0, 0, 0, 0, 0, 0]), false),
+    ("lb", 0.45359237, Dimension([0, 1, 0, 0, 0, 0, 0]), false),
+    ("rad", 1.0, Dimension([0; 7]), false),
+    ("deg", PI / 180.0, Dimension([0; 7]), false),
+    ("grad", PI / 200.0, Dimension([0; 7]), false),
+];
+
+/// The exponents of the SI base units, in the order of BASE_UNITS, a
+/// quantity is measured in
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Dimension(pub [i32; 7]);
+
+/// A unit a quantity can be written in, like km/h. The factor converts
+/// from the unit to SI base units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub name: String,
+    pub factor: f64,
+    pub dimension: Dimension,
+}
+
+/// A value with a dimension. The value is kept in SI base units and the
+/// unit, when set by a conversion, is only used to write it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub dimension: Dimension,
+    pub unit: Option<Unit>,
+}
+
+impl Dimension {
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.iter().all(|exponent| *exponent == 0)
+    }
+
+    /// Multiplies the dimension by other, or divides it when sign is -1,
+    /// which fails when an exponent of the result is beyond ±i32::MAX
+    fn combine(&self, other: &Dimension, sign: i32) -> Result<Dimension, Error> {
+        let mut exponents = self.0;
+
+        for (exponent, other) in exponents.iter_mut().zip(other.0) {
+            *exponent = sign
+                .checked_mul(other)
+                .and_then(|other| exponent.checked_add(other))
+                .filter(|exponent| *exponent != i32::MIN)
+                .ok_or_else(|| dimension_overflow(self))?;
+        }
+
+        Ok(Dimension(exponents))
+    }
+
+    /// Raises the dimension to exponent, which fails unless every
+    /// exponent of the result is an integer within ±i32::MAX
+    fn pow(&self, exponent: f64) -> Result<Dimension, Error> {
+        let mut exponents = self.0;
+
+        for base_exponent in exponents.iter_mut() {
+            let raised = *base_exponent as f64 * exponent;
+
+            if raised.fract() != 0.0 {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("({}) ^ {} has fractional exponents", self, exponent),
+                ));
+            }
+
+            if raised.abs() > i32::MAX as f64 {
+                return Err(dimension_overflow(self));
+            }
+
+            *base_exponent = raised as i32;
+        }
+
+        Ok(Dimension(exponents))
+    }
+}
+
+impl fmt::Display for Dimension {
+    /// Writes the dimension in SI base units like m/s^2, or 1 when it is
+    /// dimensionless
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let power = |(unit, exponent): (&&str, i32)| match exponent {
+            1 => unit.to_string(),
+            exponent => format!("{}^{}", unit, exponent),
+        };
+        let numerator: Vec<String> = BASE_UNITS
+            .iter()
+            .zip(self.0)
+            .filter(|(_, exponent)| *exponent > 0)
+            .map(power)
+            .collect();
+        let denominator: Vec<String> = BASE_UNITS
+            .iter()
+            .zip(self.0)
+            .filter(|(_, exponent)| *exponent < 0)
+            .map(|(unit, exponent)| power((unit, -exponent)))
+            .collect();
+
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (true, true) => write!(f, "1"),
+            (false, true) => write!(f, "{}", numerator.join("*")),
+            (true, false) => write!(f, "1/{}", denominator.join("/")),
+            (false, false) => write!(f, "{}/{}", numerator.join("*"), denominator.join("/")),
+        }
+    }
+}
+
+impl Unit {
+    /// Finds a unit of UNITS by name, with a prefix for prefixable units
+    pub fn lookup(name: &str) -> Option<Unit> {
+        let unit = |factor, dimension| Unit {
+            name: name.to_string(),
+            factor,
+            dimension,
+        };
+
+        if let Some((_, factor, dimension, _)) = UNITS.iter().find(|unit| unit.0 == name) {
+            return Some(unit(*factor, *dimension));
+        }
+
+        PREFIXES.iter().find_map(|(prefix, scale)| {
+            let (_, factor, dimension, _) = UNITS.iter().find(|(symbol, _, _, prefixable)| {
+                *prefixable && name.strip_prefix(prefix) == Some(symbol)
+            })?;
+
+            Some(unit(scale * factor, *dimension))
+        })
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    /// Parses a product of units like kg*m/s^2. The operators are applied
+    /// from left to right and exponents are integers.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let unknown = || Error::new(ErrorKind::InvalidInput, format!("Unknown unit: {}", name));
+        let mut parsed = Unit {
+            name: name.to_string(),
+            factor: 1.0,
+            dimension: Dimension::default(),
+        };
+        let mut sign: i32 = 1;
+
+        if name.is_empty() {
+            return Err(unknown());
+        }
+
+        for term in name.split_inclusive(['*', '/']) {
+            let next_sign = match term.chars().last() {
+                Some('/') => -1,
+                _ => 1,
+            };
+            let term = term.trim_end_matches(['*', '/']);
+            let (symbol, exponent) = match term.split_once('^') {
+                Some((symbol, exponent)) => {
+                    (symbol, exponent.parse::<i32>().map_err(|_| unknown())?)
+                }
+                None => (term, 1),
+            };
+            let unit = Unit::lookup(symbol).ok_or_else(unknown)?;
+            let exponent = sign.checked_mul(exponent).ok_or_else(unknown)?;
+
+            parsed.factor *= unit.factor.powi(exponent);
+            parsed.dimension = parsed
+                .dimension
+                .combine(&unit.dimension.pow(exponent as f64)?, 1)?;
+            sign = next_sign;
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl Quantity {
+    /// The quantity of value in unit
+    pub fn new(value: f64, unit: &Unit) -> Self {
+        Quantity {
+            value: value * unit.factor,
+            dimension: unit.dimension,
+            unit: None,
+        }
+    }
+
+    pub fn dimensionless(value: f64) -> Self {
+        Quantity {
+            value,
+            dimension: Dimension::default(),
+            unit: None,
+        }
+    }
+
+    /// Converts the quantity to unit, which must have the same dimension
+    pub fn to(&self, unit: Unit) -> Result<Self, Error> {
+        if unit.dimension != self.dimension {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Cannot convert {} to {}", self.dimension, unit.name),
+            ));
+        }
+
+        Ok(Quantity {
+            unit: Some(unit),
+            ..self.clone()
+        })
+    }
+
+    /// The value in the unit the quantity is written in
+    pub fn magnitude(&self) -> f64 {
+        match &self.unit {
+            Some(unit) => self.value / unit.factor,
+            None => self.value,
+        }
+    }
+
+    /// The unit the quantity is written in, its unit if it was converted
+    /// and SI base units otherwise. Empty for dimensionless quantities.
+    pub fn unit_name(&self) -> String {
+        match &self.unit {
+            Some(unit) => unit.name.clone(),
+            None if self.dimension.is_dimensionless() => String::new(),
+            None => self.dimension.to_string(),
+        }
+    }
+
+    fn with_value(&self, value: f64, dimension: Dimension) -> Self {
+        Quantity {
+            value,
+            dimension,
+            unit: None,
+        }
+    }
+
+    /// Fails unless other has the dimension of the quantity
+    fn same_dimension(&self, operator: &str, other: &Self) -> Result<(), Error> {
+        match self.dimension == other.dimension {
+            true => Ok(()),
+            false => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "Dimension mismatch in {} {} {}",
+                    self.dimension, operator, other.dimension
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit_name().as_str() {
+            "" => write!(f, "{}", self.magnitude()),
+            unit => write!(f, "{} {}", self.magnitude(), unit),
+        }
+    }
+}
+
+impl Numeric for Quantity {
+    fn from_literal(text: &str) -> Result<Self, Error> {
+        f64::from_literal(text).map(Quantity::dimensionless)
+    }
+    fn constant(constant: IConstants) -> Result<Self, Error> {
+        match constant {
+            IConstants::Pi => Ok(Quantity::dimensionless(PI_LITERAL)),
+        }
+    }
+    fn add(&self, other: &Self) -> Result<Self, Error> {
+        self.same_dimension("+", other)?;
+
+        Ok(self.with_value(self.value + other.value, self.dimension))
+    }
+    fn sub(&self, other: &Self) -> Result<Self, Error> {
+        self.same_dimension("-", other)?;
+
+        Ok(self.with_value(self.value - other.value, self.dimension))
+    }
+    fn mul(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self.with_value(
+            self.value * other.value,
+            self.dimension.combine(&other.dimension, 1)?,
+        ))
+    }
+    fn div(&self, other: &Self) -> Result<Self, Error> {
+        Ok(self.with_value(
+            self.value / other.value,
+            self.dimension.combine(&other.dimension, -1)?,
+        ))
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, Error> {
+        if !exponent.dimension.is_dimensionless() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("The exponent {} is not dimensionless", exponent),
+            ));
+        }
+
+        let dimension = self.dimension.pow(exponent.value)?;
+
+        Ok(self.with_value(self.value.powf(exponent.value), dimension))
+    }
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        match self.dimension == other.dimension {
+            true => self.value.partial_cmp(&other.value),
+            false => None,
+        }
+    }
+    fn neg(&self) -> Result<Self, Error> {
+        Ok(self.with_value(-self.value, self.dimension))
+    }
+    fn function(function: IFunctions, arguments: &[Self]) -> Result<Self, Error> {
+        let argument = &arguments[0];
+
+        match function {
+            IFunctions::Max | IFunctions::Min => {
+                argument.same_dimension(",", &arguments[1])?;
+
+                let values = [argument.value, arguments[1].value];
+
+                Ok(argument.with_value(f64::function(function, &values)?, argument.dimension))
+            }
+            IFunctions::Sqrt => argument.pow(&Quantity::dimensionless(0.5)),
+            IFunctions::Abs | IFunctions::Re | IFunctions::Conj | IFunctions::Im => Ok(argument
+                .with_value(
+                    f64::function(function, &[argument.value])?,
+                    argument.dimension,
+                )),
+            _ if !argument.dimension.is_dimensionless() => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "{} takes a dimensionless argument, not {}",
+                    function.name(),
+                    argument.dimension
+                ),
+            )),
+            _ => Ok(Quantity::dimensionless(f64::function(
+                function,
+                &[argument.value],
+            )?)),
+        }
+    }
+}
+
+/// Evaluates the tree rooted at node with quantities, checking that the
+/// operands of +, - and conversions with to or in have the same
+/// dimension. Errors carry the span of the operator they were raised at.
+pub fn evaluate_quantity(
+    node: &dyn AstNode,
+    variables: &Variables<Quantity>,
+) -> Result<Quantity, Error> {
+    let token = node.token();
+    let result = match token.token_type {
+        IToken::Quantity => {
+            let lexeme = token.lexeme.unwrap();
+            let (_, unit) = lexeme.split_once(' ').unwrap();
+
+            Ok(Quantity::new(token.literal.unwrap(), &unit.parse()?))
+        }
+        IToken::Unit => Ok(Quantity::new(1.0, &token.lexeme.unwrap().parse()?)),
+        IToken::To => {
+            let children = node.children();
+            let quantity = evaluate_quantity(children[0].as_ref(), variables)?;
+            let target = children[1].token();
+
+            match target.token_type {
+                IToken::Unit => quantity.to(target.lexeme.unwrap().parse()?),
+                _ => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("Expected a unit after {}, found {}", token, target),
+                )),
+            }
+        }
+        _ if node.children().is_empty() => evaluate_numeric(node, variables),
+        token_type => {
+            let operands = node
+                .children()
+                .iter()
+                .map(|child| evaluate_quantity(child.as_ref(), variables))
+                .collect::<Result<Vec<Quantity>, Error>>()?;
+
+            apply(token_type, &operands)
+        }
+    };
+
+    result.map_err(|error| with_span(error, token.span))
+}
+
+/// The error for dimensions whose exponents grow beyond ±i32::MAX
+fn dimension_overflow(dimension: &Dimension) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("The exponents of {} are too large", dimension),
+    )
+}
+
+/// Whether the tree rooted at node has quantities with units or
+/// conversions, which need evaluate_quantity
+pub fn has_units(node: &dyn AstNode) -> bool {
+    matches!(
+        node.token_type(),
+        IToken::Quantity | IToken::Unit | IToken::To
+    ) || node
+        .children()
+        .iter()
+        .any(|child| has_units(child.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::{span_of, Span};
+
+    fn evaluate(expr: &str) -> Result<Quantity, Error> {
+        let parser = crate::parse_expression_shunting_yard(expr).unwrap();
+
+        evaluate_quantity(parser.ast(), &Variables::new())
+    }
+
+    #[test]
+    fn units_parse_with_prefixes_and_exponents() {
+        let unit: Unit = "kN*m/s^2".parse().unwrap();
+
+        assert_eq!(1000.0, unit.factor);
+        assert_eq!(Dimension([2, 1, -4, 0, 0, 0, 0]), unit.dimension);
+        assert_eq!(1e-6, "mm^2".parse::<Unit>().unwrap().factor);
+        assert!("furlong".parse::<Unit>().is_err());
+        assert!("kh".parse::<Unit>().is_err());
+    }
+
+    #[test]
+    fn evaluate_quantity_carries_dimensions_through_operators() {
+        let speed = evaluate("5 km / 2 h to m/s").unwrap();
+
+        assert_eq!("0.6944444444444444 m/s", speed.to_string());
+        assert_eq!(
+            "2.5 km/h",
+            evaluate("5 km / 2 h to km/h").unwrap().to_string()
+        );
+        assert_eq!("m^2", evaluate("3 m * 2 m").unwrap().unit_name());
+        assert_eq!(
+            "m/s^2",
+            evaluate("sqrt(4 m^2/s^4)").unwrap().dimension.to_string()
+        );
+        assert_eq!("6", evaluate("3 m / 50 cm").unwrap().to_string());
+        assert_eq!("5280 ft", evaluate("1 mi in ft").unwrap().to_string());
+        assert_eq!("120 min", evaluate("2 h to min").unwrap().to_string());
+    }
+
+    #[test]
+    fn mismatched_dimensions_report_the_operator() {
+        let error = evaluate("3 m + 2 s").unwrap_err();
+
+        assert_eq!("Dimension mismatch in m + s at 4..5", error.to_string());
+        assert_eq!(Some(Span::new(4, 5)), span_of(&error));
+
+        for expr in [
+            "1 m to s",
+            "2 ^ (1 s)",
+            "sqrt(2 m)",
+            "max(1 m, 1)",
+            "exp(1 s)",
+        ] {
+            assert_eq!(
+                ErrorKind::Unsupported,
+                evaluate(expr).unwrap_err().kind(),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn dimensions_with_too_large_exponents_are_rejected() {
+        assert_eq!(
+            "The exponents of m^2147483647 are too large at 15..16",
+            evaluate("1 m^2147483647 * 1 m").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "The exponents of m are too large at 5..6",
+            evaluate("(1 m)^3000000000").unwrap_err().to_string()
+        );
+        assert!("m^2147483647/m".parse::<Unit>().is_ok());
+        assert!("m^2147483647*m".parse::<Unit>().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_shunting_parser"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn exit_code(args: &[&str]) -> i32 {
+    run(args, "").status.code().unwrap()
+}
+
+#[test]
+fn exits_with_the_code_of_the_error_category() {
+    assert_eq!(0, exit_code(&["eval", "2+2"]));
+    assert_eq!(1, exit_code(&["batch", "--file", "no/such/file.txt"]));
+    assert_eq!(2, exit_code(&["eval"]));
+    assert_eq!(2, exit_code(&["eval", "--format", "yaml", "1"]));
+    assert_eq!(3, exit_code(&["eval", "2 $ 2"]));
+    assert_eq!(3, exit_code(&["eval", "1..2"]));
+    assert_eq!(4, exit_code(&["eval", "(1 + 2"]));
+    assert_eq!(5, exit_code(&["eval", "x + 1"]));
+    assert_eq!(6, run(&["batch"], "1..2\n2+2\n").status.code().unwrap());
+}
+
+#[test]
+fn invalid_dates_and_durations_are_evaluation_errors() {
+    assert_eq!(5, exit_code(&["eval", "date(\"2024-02-30\")"]));
+    assert_eq!(5, exit_code(&["eval", "duration(\"P1Y\")"]));
+    assert_eq!(5, exit_code(&["eval", "duration(\"PT\")"]));
+}
+
+#[test]
+fn unreadable_and_malformed_rate_files_are_io_errors() {
+    let rates = std::env::temp_dir().join("malformed_rates_cli_test.txt");
+
+    std::fs::write(&rates, "USD EUR x\n").unwrap();
+
+    let malformed = run(
+        &["eval", "--rates", rates.to_str().unwrap(), "1 USD in EUR"],
+        "",
+    );
+
+    assert_eq!(Some(1), malformed.status.code());
+    assert!(String::from_utf8_lossy(&malformed.stderr).contains("Line 1: invalid rate: x"));
+    assert_eq!(
+        1,
+        exit_code(&["eval", "--rates", "no/such/rates.txt", "1 USD in EUR"])
+    );
+}
+
+#[test]
+fn rejects_angle_and_ieee_flags_for_units_dates_and_money() {
+    assert_eq!(2, exit_code(&["eval", "--angle", "deg", "sin(90) * 1 m"]));
+    assert_eq!(2, exit_code(&["eval", "--ieee", "error", "1/0 * 1 m"]));
+    assert_eq!(
+        2,
+        exit_code(&["eval", "--angle", "deg", "100 USD + 50 USD"])
+    );
+    assert_eq!(0, exit_code(&["eval", "--angle", "deg", "sin(90)"]));
+    assert_eq!(0, exit_code(&["eval", "--angle", "rad", "sin(1) * 1 m"]));
+}
+
+#[test]
+fn writes_results_and_errors_in_the_requested_format() {
+    let output = run(&["eval", "--format", "json", "1..2"], "");
+
+    assert_eq!(Some(3), output.status.code());
+    assert_eq!(
+        "{\"error\":{\"category\":\"tokenize\",\"message\":\"Malformed number: 1..2 at 0..4\",\"span\":{\"start\":0,\"end\":4}}}\n",
+        String::from_utf8(output.stdout).unwrap()
+    );
+
+    let output = run(&["batch"], "1..2\n2+2\n");
+
+    assert!(String::from_utf8(output.stdout).unwrap().contains("2: 4"));
+}
@@ -1,5 +1,3 @@
-use shunting_parser;
-
 #[test]
 fn can_evaluate_expressions_with_simple_operators() {
     assert_eq!(
@@ -49,3 +47,307 @@ fn can_evaluate_expressions_with_simple_operators_and_parenthesis_and_functions(
         )
     );
 }
+
+#[test]
+fn can_evaluate_expressions_with_variables() {
+    let parser = shunting_parser::parse_expression_shunting_yard("price * (1 - discount)").unwrap();
+    let variables = std::collections::HashMap::from([
+        (String::from("price"), 80.0),
+        (String::from("discount"), 0.25),
+    ]);
+
+    assert_eq!(vec!["discount", "price"], parser.variables());
+    assert_eq!(60.0, parser.evaluate_with(&variables));
+}
+
+#[test]
+fn can_evaluate_expressions_exactly() {
+    let parser = shunting_parser::parse_expression_shunting_yard("0.1 + 0.2 - 1 / 3").unwrap();
+    let value = parser
+        .try_evaluate_rational(
+            &std::collections::HashMap::new(),
+            shunting_parser::rational::Inexact::Error,
+        )
+        .unwrap();
+
+    assert_eq!("-1/30", value.to_string());
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Wrapping8(u8);
+
+impl shunting_parser::numeric::Numeric for Wrapping8 {
+    fn from_literal(text: &str) -> Result<Self, std::io::Error> {
+        text.parse().map(Wrapping8).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::Unsupported, "Not an 8 bit integer")
+        })
+    }
+    fn constant(
+        _constant: shunting_parser::tokenizer::tokens::IConstants,
+    ) -> Result<Self, std::io::Error> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "No constants",
+        ))
+    }
+    fn add(&self, other: &Self) -> Result<Self, std::io::Error> {
+        Ok(Wrapping8(self.0.wrapping_add(other.0)))
+    }
+    fn sub(&self, other: &Self) -> Result<Self, std::io::Error> {
+        Ok(Wrapping8(self.0.wrapping_sub(other.0)))
+    }
+    fn mul(&self, other: &Self) -> Result<Self, std::io::Error> {
+        Ok(Wrapping8(self.0.wrapping_mul(other.0)))
+    }
+    fn div(&self, other: &Self) -> Result<Self, std::io::Error> {
+        Ok(Wrapping8(self.0.wrapping_div(other.0)))
+    }
+    fn pow(&self, exponent: &Self) -> Result<Self, std::io::Error> {
+        Ok(Wrapping8(self.0.wrapping_pow(exponent.0 as u32)))
+    }
+    fn compare(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.0.cmp(&other.0))
+    }
+}
+
+#[test]
+fn can_evaluate_expressions_with_custom_numeric_types() {
+    let parser = shunting_parser::parse_expression_shunting_yard("max(x, 100) + 2 ^ 7").unwrap();
+    let variables = std::collections::HashMap::from([(String::from("x"), Wrapping8(200))]);
+
+    assert_eq!(
+        Wrapping8(72),
+        parser.try_evaluate_numeric(&variables).unwrap()
+    );
+}
+
+#[test]
+fn can_evaluate_expressions_with_intervals() {
+    use shunting_parser::interval::Interval;
+
+    let parser = shunting_parser::parse_expression_shunting_yard("r ^ 2 * pi").unwrap();
+    let variables = std::collections::HashMap::from([(
+        String::from("r"),
+        "[9.9, 10.1]".parse::<Interval>().unwrap(),
+    )]);
+    let area = parser.try_evaluate_interval(&variables).unwrap();
+
+    assert!(area.contains(9.9 * 9.9 * std::f64::consts::PI));
+    assert!(area.contains(10.1 * 10.1 * std::f64::consts::PI));
+    assert!(area.lo() > 307.0 && area.hi() < 321.0);
+}
+
+#[test]
+fn can_evaluate_expressions_modulo_a_number() {
+    use num_bigint::BigInt;
+
+    assert_eq!(
+        BigInt::from(5),
+        shunting_parser::evaluate_mod("1 / 3", 7).unwrap()
+    );
+    assert_eq!(
+        BigInt::from(445),
+        shunting_parser::evaluate_mod("4 ^ 13", 497).unwrap()
+    );
+    assert!(shunting_parser::evaluate_mod("1 / 2", 4).is_err());
+    assert_eq!(
+        "Equations cannot be evaluated, only solved at 2..3",
+        shunting_parser::evaluate_mod("x = 1", 7)
+            .unwrap_err()
+            .to_string()
+    );
+}
+
+#[test]
+fn can_evaluate_trigonometric_functions_in_degrees() {
+    use shunting_parser::tokenizer::tokens::IAngleUnit;
+
+    let parser = shunting_parser::parse_expression_shunting_yard("atan(1) * 2").unwrap();
+
+    assert_eq!(
+        90.0,
+        parser
+            .try_evaluate_numeric_in::<f64>(&Default::default(), IAngleUnit::Degrees)
+            .unwrap()
+    );
+    assert_eq!(
+        1.0,
+        shunting_parser::parse_expression_shunting_yard("sin(90deg)")
+            .unwrap()
+            .evaluate()
+    );
+}
+
+#[test]
+fn can_evaluate_expressions_with_units() {
+    let parser = shunting_parser::parse_expression_shunting_yard("5 km / 2 h to m/s").unwrap();
+    let speed = parser.try_evaluate_quantity(&Default::default()).unwrap();
+
+    assert_eq!(5000.0 / 7200.0, speed.magnitude());
+    assert_eq!("m/s", speed.unit_name());
+    assert!(shunting_parser::parse_expression_shunting_yard("3 m + 2 s")
+        .unwrap()
+        .try_evaluate_quantity(&Default::default())
+        .is_err());
+}
+
+#[test]
+fn can_evaluate_amounts_of_money() {
+    let rates: shunting_parser::currency::RateTable = "USD GBP 0.79\nEUR GBP 0.86".parse().unwrap();
+    let context = shunting_parser::decimal::DecimalContext::default();
+    let money = shunting_parser::parse_expression_shunting_yard("100 USD + 50 EUR in GBP")
+        .unwrap()
+        .try_evaluate_currency(&Default::default(), &rates, &context)
+        .unwrap();
+
+    assert_eq!("122.00 GBP", money.to_string());
+    assert!(
+        shunting_parser::parse_expression_shunting_yard("100 USD + 50 JPY")
+            .unwrap()
+            .try_evaluate_currency(&Default::default(), &rates, &context)
+            .is_err()
+    );
+}
+
+#[test]
+fn can_evaluate_dates_and_durations() {
+    let evaluate = |expr: &str| {
+        shunting_parser::parse_expression_shunting_yard(expr)
+            .unwrap()
+            .try_evaluate_temporal(&Default::default())
+    };
+
+    assert_eq!(
+        "2026-11-17",
+        evaluate("date(\"2026-10-18\") + 30 days")
+            .unwrap()
+            .to_string()
+    );
+    assert_eq!(
+        "PT7H30M",
+        evaluate("duration(\"PT2H30M\") * 3").unwrap().to_string()
+    );
+    assert!(evaluate("date(\"2026-10-18\") + date(\"2026-10-19\")").is_err());
+}
+
+#[test]
+fn can_differentiate_expressions() {
+    let parser = shunting_parser::parse_expression_shunting_yard("x^2 + 3*x").unwrap();
+    let derivative = shunting_parser::calculus::differentiate(parser.ast(), "x").unwrap();
+
+    assert_eq!(
+        "2*x + 3",
+        shunting_parser::ast::infix::to_infix(derivative.as_ref())
+    );
+    assert_eq!(
+        7.0,
+        derivative.evaluate_with(&std::collections::HashMap::from([(String::from("x"), 2.0)]))
+    );
+}
+
+#[test]
+fn can_simplify_expressions() {
+    use shunting_parser::simplify::{simplify, simplify_with, Rules};
+
+    let parser = shunting_parser::parse_expression_shunting_yard("y*x + 2*x*y + x*0").unwrap();
+    let identities = Rules {
+        identities: true,
+        ..Rules::none()
+    };
+
+    assert_eq!(
+        "3*x*y",
+        shunting_parser::ast::infix::to_infix(simplify(parser.ast()).as_ref())
+    );
+    assert_eq!(
+        "y*x + 2*x*y",
+        shunting_parser::ast::infix::to_infix(simplify_with(parser.ast(), &identities).as_ref())
+    );
+}
+
+#[test]
+fn can_evaluate_gradients() {
+    let parser = shunting_parser::parse_expression_shunting_yard("x^2 * y + sin(y)").unwrap();
+    let variables =
+        std::collections::HashMap::from([(String::from("x"), 3.0), (String::from("y"), 0.0)]);
+
+    assert_eq!(
+        (0.0, vec![0.0, 10.0]),
+        parser
+            .try_evaluate_gradient(&variables, &["x", "y"])
+            .unwrap()
+    );
+    assert_eq!(
+        (0.0, vec![10.0]),
+        parser.try_evaluate_gradient(&variables, &["y"]).unwrap()
+    );
+}
+
+#[test]
+fn can_evaluate_sums_integrals_and_roots() {
+    let parser = shunting_parser::parse_expression_shunting_yard("sum(i * n, i, 1, n)").unwrap();
+    let variables = std::collections::HashMap::from([(String::from("n"), 4.0)]);
+
+    assert_eq!(vec!["n"], parser.variables());
+    assert_eq!(40.0, parser.evaluate_with(&variables));
+    assert_eq!(
+        120.0,
+        shunting_parser::evaluate_expression_shunting_yard("prod(k, k, 1, 5)")
+    );
+    assert!(
+        (shunting_parser::evaluate_expression_shunting_yard("integrate(x^2, x, 0, 3)") - 9.0).abs()
+            < 1e-9
+    );
+    assert!(
+        (shunting_parser::evaluate_expression_shunting_yard("solve(x^2 - 2, x, 1)") - 2f64.sqrt())
+            .abs()
+            < 1e-12
+    );
+}
+
+#[test]
+fn can_expand_and_collect_polynomials() {
+    use shunting_parser::polynomial::{collect, expand, Polynomial};
+
+    let parser = shunting_parser::parse_expression_shunting_yard("(x+1)^3 - x*(x-2)").unwrap();
+    let polynomial = Polynomial::from_ast(parser.ast()).unwrap();
+
+    assert_eq!(3, polynomial.degree_in("x"));
+    assert_eq!(
+        "x^3 + 2*x^2 + 5*x + 1",
+        shunting_parser::ast::infix::to_infix(expand(parser.ast()).unwrap().as_ref())
+    );
+    assert_eq!(
+        "x^3 + 2*x^2 + 5*x + 1",
+        shunting_parser::ast::infix::to_infix(polynomial.to_ast().as_ref())
+    );
+
+    let parser = shunting_parser::parse_expression_shunting_yard("a*x + b*x + a").unwrap();
+
+    assert_eq!(
+        "(a + b)*x + a",
+        shunting_parser::ast::infix::to_infix(collect(parser.ast(), "x").unwrap().as_ref())
+    );
+}
+
+#[test]
+fn can_solve_linear_equations() {
+    use shunting_parser::linear::{solve_linear, Solution};
+
+    let parser = shunting_parser::parse_expression_shunting_yard("x + y = 10; x - y = 2").unwrap();
+
+    match solve_linear(parser.ast()).unwrap() {
+        Solution::Unique(values) => assert_eq!(
+            vec![(String::from("x"), 6.into()), (String::from("y"), 4.into())],
+            values
+                .into_iter()
+                .map(|(name, value)| (name, value.to_integer()))
+                .collect::<Vec<(String, num_bigint::BigInt)>>()
+        ),
+        solution => panic!("expected a unique solution, found {}", solution),
+    }
+
+    let parser = shunting_parser::parse_expression_shunting_yard("2x = 1; 4x = 3").unwrap();
+
+    assert_eq!(Solution::Inconsistent, solve_linear(parser.ast()).unwrap());
+}